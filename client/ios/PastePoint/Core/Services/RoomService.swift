@@ -6,6 +6,7 @@
 import Combine
 import Foundation
 import Logging
+import UIKit
 
 @MainActor
 final class RoomService: ObservableObject {
@@ -13,11 +14,22 @@ final class RoomService: ObservableObject {
 
   @Published var rooms: [String] = []
   @Published var members: [String] = []
+  /// Presence and device of each member, in the same order as `members`
+  @Published var memberDetails: [Member] = []
+  /// What each member is doing, e.g. "typing"; members doing nothing are absent
+  @Published var activities: [String: String] = [:]
   @Published var currentRoom: String = ""
+  /// Presence this user chose: "active", "idle" or "away"
+  @Published var status: String = "active"
 
   private var cancellables = Set<AnyCancellable>()
   private let wsService: WebSocketConnectionService
 
+  // Versions of the last room and member lists applied, see `isNextVersion`
+  private var roomsVersion: UInt64 = 0
+  private var membersVersion: UInt64 = 0
+  private var membersRoom = ""
+
   init(wsService: WebSocketConnectionService) {
     self.wsService = wsService
 
@@ -31,7 +43,10 @@ final class RoomService: ObservableObject {
     wsService.didConnect
       .receive(on: DispatchQueue.main)
       .sink { [weak self] _ in
-        Task { await self?.listRooms() }
+        Task {
+          await self?.registerDevice()
+          await self?.listRooms()
+        }
       }
       .store(in: &cancellables)
   }
@@ -63,27 +78,118 @@ final class RoomService: ObservableObject {
     await listRooms()
   }
 
+  /// Sets the user's presence; "active" goes back to inferring it from
+  /// activity.
+  func setStatus(_ status: String) async {
+    await wsService.send("[UserCommand] /status \(status)")
+    self.status = status
+  }
+
+  /// Tells the rest of the room what the user is doing: "typing",
+  /// "selecting-file", "uploading", or "none" to clear it.
+  func sendActivity(_ activity: String) async {
+    await wsService.send("[UserCommand] /activity \(activity)")
+  }
+
+  /// Tells the server what this device is, which the User-Agent of the
+  /// WebSocket connection does not say.
+  func registerDevice() async {
+    let device = DeviceInfo(
+      type: UIDevice.current.userInterfaceIdiom == .pad ? "tablet" : "mobile",
+      os: "\(UIDevice.current.systemName) \(UIDevice.current.systemVersion)",
+      clientVersion: Bundle.main.appVersion
+    )
+    guard let data = try? JSONEncoder().encode(device),
+          let json = String(data: data, encoding: .utf8) else { return }
+    await wsService.send("[UserCommand] /device \(json)")
+  }
+
+  struct DeviceInfo: Codable, Equatable {
+    let type: String
+    let os: String?
+    let clientVersion: String?
+
+    enum CodingKeys: String, CodingKey {
+      case type, os
+      case clientVersion = "client_version"
+    }
+  }
+
+  /// Color, emoji and initials the server derives from a peer's identity
+  struct Avatar: Decodable, Equatable {
+    let color: String
+    let emoji: String
+    let initials: String
+  }
+
+  struct Member: Decodable, Equatable {
+    let name: String
+    let presence: String
+    let device: DeviceInfo
+    let avatar: Avatar
+  }
+
+  private struct ActivityUpdate: Decodable {
+    let name: String
+    let activity: String
+  }
+
+  private struct RoomsSnapshot: Decodable {
+    let version: UInt64
+    let rooms: [String]
+  }
+
+  private struct RoomsDelta: Decodable {
+    let version: UInt64
+    let added: [String]
+    let removed: [String]
+  }
+
+  private struct MembersSnapshot: Decodable {
+    let room: String
+    let version: UInt64
+    let members: [Member]
+  }
+
+  private struct MembersDelta: Decodable {
+    let room: String
+    let version: UInt64
+    let joined: [Member]
+    let left: [String]
+    let updated: [Member]
+  }
+
   private func handleSystemMessage(_ message: String) {
-    if message.contains("[SystemRooms]") {
-      guard let range = message.range(of: "\\[SystemRooms]\\s*(.*)$", options: .regularExpression) else {
-        logger.warning("handleSystemMessage: failed to parse [SystemRooms] message: \(message)")
-        return
+    if message.contains("[SystemActivity]") {
+      guard let update = decode(ActivityUpdate.self, from: message, prefix: "[SystemActivity]") else { return }
+      activities[update.name] = update.activity == "none" ? nil : update.activity
+    } else if message.contains("[SystemRoomsDelta]") {
+      guard let delta = decode(RoomsDelta.self, from: message, prefix: "[SystemRoomsDelta]"),
+            isNextVersion(delta.version, after: &roomsVersion) else { return }
+      rooms = rooms.filter { !delta.removed.contains($0) } + delta.added
+      logger.debug("Rooms updated: \(rooms)")
+    } else if message.contains("[SystemMembersDelta]") {
+      guard let delta = decode(MembersDelta.self, from: message, prefix: "[SystemMembersDelta]"),
+            delta.room == membersRoom,
+            isNextVersion(delta.version, after: &membersVersion) else { return }
+      var details = memberDetails
+      for name in delta.left {
+        if let index = details.firstIndex(where: { $0.name == name }) { details.remove(at: index) }
       }
-      let rest = String(message[range])
-      let prefix = "[SystemRooms] "
-      let list = rest.hasPrefix(prefix) ? String(rest.dropFirst(prefix.count)) : rest
-      rooms = list.split(separator: ",").map { $0.trimmingCharacters(in: .whitespaces) }
+      for member in delta.updated {
+        if let index = details.firstIndex(where: { $0.name == member.name }) { details[index] = member }
+      }
+      setMembers(details + delta.joined)
+    } else if message.contains("[SystemRooms]") {
+      guard let snapshot = decode(RoomsSnapshot.self, from: message, prefix: "[SystemRooms]") else { return }
+      roomsVersion = snapshot.version
+      rooms = snapshot.rooms
       logger.debug("Rooms updated: \(rooms)")
     } else if message.contains("[SystemMembers]") {
-      guard let range = message.range(of: "\\[SystemMembers]\\s*(.*)$", options: .regularExpression) else {
-        logger.warning("handleSystemMessage: failed to parse [SystemMembers] message: \(message)")
-        return
-      }
-      let rest = String(message[range])
-      let prefix = "[SystemMembers] "
-      let list = rest.hasPrefix(prefix) ? String(rest.dropFirst(prefix.count)) : rest
-      members = list.split(separator: ",").map { $0.trimmingCharacters(in: .whitespaces) }
-      logger.debug("Members updated: \(members)")
+      guard let snapshot = decode(MembersSnapshot.self, from: message, prefix: "[SystemMembers]") else { return }
+      membersRoom = snapshot.room
+      membersVersion = snapshot.version
+      setMembers(snapshot.members)
     } else if message.contains("[SystemJoin]") {
       guard let range = message.range(of: "\\[SystemJoin]\\s*(\\S+)\\s*$", options: .regularExpression) else {
         logger.warning("handleSystemMessage: failed to parse [SystemJoin] message: \(message)")
@@ -99,4 +205,34 @@ final class RoomService: ObservableObject {
       Task { await self.listRooms() }
     }
   }
+
+  private func setMembers(_ details: [Member]) {
+    memberDetails = details
+    members = details.map(\.name)
+    activities = activities.filter { members.contains($0.key) }
+    logger.debug("Members updated: \(members)")
+  }
+
+  private func decode<T: Decodable>(_ type: T.Type, from message: String, prefix: String) -> T? {
+    guard let range = message.range(of: prefix),
+          let data = message[range.upperBound...].data(using: .utf8),
+          let value = try? JSONDecoder().decode(type, from: data) else {
+      logger.warning("handleSystemMessage: failed to parse \(prefix) message: \(message)")
+      return nil
+    }
+    return value
+  }
+
+  /// Deltas must arrive in order; older ones are ignored and a gap means one
+  /// was missed, so a fresh snapshot is requested instead.
+  private func isNextVersion(_ version: UInt64, after current: inout UInt64) -> Bool {
+    if version <= current { return false }
+    guard version == current + 1 else {
+      logger.warning("Missed a room update, requesting snapshot")
+      Task { await self.wsService.send("[UserCommand] /sync") }
+      return false
+    }
+    current = version
+    return true
+  }
 }
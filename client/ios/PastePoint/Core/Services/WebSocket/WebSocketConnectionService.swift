@@ -112,7 +112,9 @@ final class WebSocketConnectionService: ObservableObject {
     let session = URLSession(configuration: .default)
 #endif
 
-    task = session.webSocketTask(with: url)
+    var request = URLRequest(url: url)
+    request.setValue(Bundle.main.userAgent, forHTTPHeaderField: "User-Agent")
+    task = session.webSocketTask(with: request)
     task?.resume()
 
     startReceiveLoop()
@@ -217,6 +219,9 @@ final class WebSocketConnectionService: ObservableObject {
       msg.contains("[SystemJoin]") ||
       msg.contains("[SystemRooms]") ||
       msg.contains("[SystemMembers]") ||
+      msg.contains("[SystemRoomsDelta]") ||
+      msg.contains("[SystemMembersDelta]") ||
+      msg.contains("[SystemActivity]") ||
       msg.contains("[SystemName]")
   }
 
@@ -12,6 +12,12 @@ extension Bundle {
   var appVersion: String {
     infoDictionary?["CFBundleShortVersionString"] as? String ?? "1.0"
   }
+
+  /// User-Agent sent on the WebSocket upgrade. The server only lets clients
+  /// without an Origin header in when it matches `native_user_agents`.
+  var userAgent: String {
+    "PastePoint-iOS/\(appVersion)"
+  }
 }
 
 // MARK: - Build Environment
@@ -9,7 +9,9 @@ import SwiftUI
 struct ChatInputBar: View {
   private let logger = Logger(label: "ChatInputBar")
 
+  @EnvironmentObject private var services: AppServices
   @State private var message = ""
+  @State private var isTyping = false
 
   var body: some View {
     VStack(spacing: 10) {
@@ -18,10 +20,19 @@ struct ChatInputBar: View {
         .textFieldStyle(.plain)
         .font(.body)
         .foregroundStyle(.textPrimary)
+        .onChange(of: message) { _, newValue in
+          setTyping(!newValue.trimmingCharacters(in: .whitespacesAndNewlines).isEmpty)
+        }
+        .task(id: message) {
+          // The room stops seeing "typing" once the field sits untouched
+          do { try await Task.sleep(for: .seconds(5)) } catch { return }
+          setTyping(false)
+        }
 
       HStack(alignment: .center) {
 
-        // TODO: Implement attachment picker; add toast = .error("...") on failure
+        // TODO: Implement attachment picker; report "selecting-file" and "uploading"
+        // through sendActivity; add toast = .error("...") on failure
         Button {
           logger.info("Attachments Button Clicked")
         } label: {
@@ -72,6 +83,13 @@ struct ChatInputBar: View {
     )
     .frame(maxWidth: 360)
   }
+
+  /// Tells the room when the user starts or stops typing.
+  private func setTyping(_ typing: Bool) {
+    guard typing != isTyping else { return }
+    isTyping = typing
+    Task { await services.roomService.sendActivity(typing ? "typing" : "none") }
+  }
 }
 
 // MARK: - Preview
@@ -79,5 +97,6 @@ struct ChatInputBar: View {
 #if DEBUG
 #Preview {
   ChatInputBar()
+    .environmentObject(AppServices.preview)
 }
 #endif
@@ -12,6 +12,10 @@ struct ChatView: View {
     services.wsService.currentSessionCode != nil
   }
 
+  private var typingMembers: [String] {
+    services.roomService.activities.filter { $0.value == "typing" }.keys.sorted()
+  }
+
   var body: some View {
     ScrollView {
       VStack(alignment: .leading, spacing: 0) {
@@ -37,6 +41,13 @@ struct ChatView: View {
         }
         .frame(maxWidth: .infinity, alignment: .leading)
         .padding(.bottom, 16)
+
+        if !typingMembers.isEmpty {
+          Text("\(typingMembers.joined(separator: ", ")) typing…")
+            .font(.caption)
+            .foregroundStyle(.textSecondary)
+            .padding(.bottom, 8)
+        }
       }
       .padding(.horizontal, 16)
     }
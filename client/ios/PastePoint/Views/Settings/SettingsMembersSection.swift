@@ -30,6 +30,28 @@ struct SettingsMembersSection: View {
       }
       .padding(.horizontal)
 
+      HStack(alignment: .center, spacing: 0) {
+        Text("Your status")
+          .font(.caption2)
+          .foregroundColor(.textPrimary)
+
+        Spacer()
+
+        Menu {
+          ForEach(["active", "idle", "away"], id: \.self) { status in
+            Button(status.capitalized) {
+              Task { await services.roomService.setStatus(status) }
+            }
+          }
+        } label: {
+          Text(services.roomService.status.capitalized)
+            .font(.caption)
+            .foregroundStyle(.textSecondary)
+        }
+      }
+      .padding(.horizontal)
+      .padding(.top, 12)
+
       Group {
         let others = services.roomService.members.filter { $0 != services.userService.user }
         if others.isEmpty {
@@ -43,9 +65,17 @@ struct SettingsMembersSection: View {
               Circle().fill(.green).frame(width: 14, height: 14)
                 .padding(.trailing, 6)
 
-              Text(member)
-                .font(.subheadline)
-                .foregroundColor(.textPrimary)
+              VStack(alignment: .leading, spacing: 2) {
+                Text(member)
+                  .font(.subheadline)
+                  .foregroundColor(.textPrimary)
+
+                if let activity = activityLabel(services.roomService.activities[member]) {
+                  Text(activity)
+                    .font(.caption2)
+                    .foregroundStyle(.brand)
+                }
+              }
 
               Spacer()
 
@@ -65,4 +95,14 @@ struct SettingsMembersSection: View {
     }
     .padding(.top, 12)
   }
+
+  /// What a member is doing, or nil when they are doing nothing.
+  private func activityLabel(_ activity: String?) -> String? {
+    switch activity {
+    case "typing": "Typing…"
+    case "selecting-file": "Choosing a file…"
+    case "uploading": "Sending a file…"
+    default: nil
+    }
+  }
 }
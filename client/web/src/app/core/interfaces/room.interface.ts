@@ -1,10 +1,69 @@
 import { BehaviorSubject } from 'rxjs';
 
+export type Presence = 'active' | 'idle' | 'away';
+
+export type Activity = 'typing' | 'selecting-file' | 'uploading' | 'none';
+
+export type DeviceType = 'desktop' | 'mobile' | 'tablet' | 'unknown';
+
+export interface IDeviceInfo {
+  type: DeviceType;
+  os?: string;
+  client_version?: string;
+}
+
+export interface IAvatar {
+  color: string;
+  emoji: string;
+  initials: string;
+}
+
+export interface IMember {
+  name: string;
+  presence: Presence;
+  device: IDeviceInfo;
+  avatar: IAvatar;
+}
+
 export interface IRoomService {
   rooms$: BehaviorSubject<string[]>;
   members$: BehaviorSubject<string[]>;
+  memberDetails$: BehaviorSubject<IMember[]>;
+  activities$: BehaviorSubject<Map<string, Activity>>;
   currentRoom: string;
 
   listRooms(): void;
   joinRoom(room: string): void;
+  setStatus(status: Presence): void;
+  sendActivity(activity: Activity): void;
+}
+
+export interface IRoomsSnapshot {
+  version: number;
+  rooms: string[];
+}
+
+export interface IRoomsDelta {
+  version: number;
+  added: string[];
+  removed: string[];
+}
+
+export interface IMembersSnapshot {
+  room: string;
+  version: number;
+  members: IMember[];
+}
+
+export interface IActivityUpdate {
+  name: string;
+  activity: Activity;
+}
+
+export interface IMembersDelta {
+  room: string;
+  version: number;
+  joined: IMember[];
+  left: string[];
+  updated: IMember[];
 }
@@ -384,6 +384,9 @@ export class WebSocketConnectionService implements OnDestroy {
       message.includes('[SystemJoin]') ||
       message.includes('[SystemRooms]') ||
       message.includes('[SystemMembers]') ||
+      message.includes('[SystemRoomsDelta]') ||
+      message.includes('[SystemMembersDelta]') ||
+      message.includes('[SystemActivity]') ||
       message.includes('[SystemName]')
     );
   }
@@ -2,7 +2,17 @@ import { Injectable, NgZone, inject } from '@angular/core';
 import { BehaviorSubject } from 'rxjs';
 import { WebSocketConnectionService } from '../communication/websocket-connection.service';
 import { NGXLogger } from 'ngx-logger';
-import { IRoomService } from '../../interfaces/room.interface';
+import {
+  Activity,
+  IActivityUpdate,
+  IMember,
+  IMembersDelta,
+  IMembersSnapshot,
+  IRoomService,
+  IRoomsDelta,
+  IRoomsSnapshot,
+  Presence,
+} from '../../interfaces/room.interface';
 
 @Injectable({
   providedIn: 'root',
@@ -20,8 +30,13 @@ export class RoomService implements IRoomService {
    */
   public rooms$ = new BehaviorSubject<string[]>([]);
   public members$ = new BehaviorSubject<string[]>([]);
+  public memberDetails$ = new BehaviorSubject<IMember[]>([]);
+  public activities$ = new BehaviorSubject<Map<string, Activity>>(new Map());
   public currentRoom = 'main';
 
+  private membersRoom = '';
+  private versions = { rooms: 0, members: 0 };
+
   /**
    * ==========================================================
    * CONSTRUCTOR
@@ -61,6 +76,20 @@ export class RoomService implements IRoomService {
     }
   }
 
+  /**
+   * Sets the user's presence; 'active' goes back to inferring it from activity
+   */
+  public setStatus(status: Presence): void {
+    this.wsService.send(`[UserCommand] /status ${status}`);
+  }
+
+  /**
+   * Tells the rest of the room what the user is doing; 'none' clears it
+   */
+  public sendActivity(activity: Activity): void {
+    this.wsService.send(`[UserCommand] /activity ${activity}`);
+  }
+
   /**
    * ==========================================================
    * PRIVATE METHODS
@@ -68,25 +97,67 @@ export class RoomService implements IRoomService {
    * ==========================================================
    */
   private handleSystemMessage(message: string): void {
-    if (message.includes('[SystemRooms]')) {
-      const matchRooms = message.match(/\[SystemRooms]\s*(.*?)$/);
-      if (matchRooms) {
-        const rooms = matchRooms[1].split(',').map((room: string) => room.trim());
+    if (message.includes('[SystemActivity]')) {
+      const update = this.parsePayload<IActivityUpdate>(message, '[SystemActivity]');
+      if (update) {
+        const activities = new Map(this.activities$.value);
+        if (update.activity === 'none') {
+          activities.delete(update.name);
+        } else {
+          activities.set(update.name, update.activity);
+        }
         this.ngZone.run(() => {
-          this.rooms$.next(rooms);
+          this.activities$.next(activities);
+        });
+      }
+    } else if (message.includes('[SystemRoomsDelta]')) {
+      const delta = this.parsePayload<IRoomsDelta>(message, '[SystemRoomsDelta]');
+      if (delta && this.isNextVersion('rooms', delta.version)) {
+        const rooms = this.rooms$.value.filter((room) => !delta.removed.includes(room));
+        this.ngZone.run(() => {
+          this.rooms$.next([...rooms, ...delta.added]);
+        });
+      }
+    } else if (message.includes('[SystemMembersDelta]')) {
+      const delta = this.parsePayload<IMembersDelta>(message, '[SystemMembersDelta]');
+      if (
+        delta &&
+        delta.room === this.membersRoom &&
+        this.isNextVersion('members', delta.version)
+      ) {
+        const members = [...this.memberDetails$.value];
+        for (const name of delta.left) {
+          const index = members.findIndex((member) => member.name === name);
+          if (index !== -1) {
+            members.splice(index, 1);
+          }
+        }
+        for (const member of delta.updated) {
+          const index = members.findIndex((m) => m.name === member.name);
+          if (index !== -1) {
+            members[index] = member;
+          }
+        }
+        this.ngZone.run(() => {
+          this.setMembers([...members, ...delta.joined]);
+        });
+      }
+    } else if (message.includes('[SystemRooms]')) {
+      const snapshot = this.parsePayload<IRoomsSnapshot>(message, '[SystemRooms]');
+      if (snapshot) {
+        this.versions.rooms = snapshot.version;
+        this.ngZone.run(() => {
+          this.rooms$.next(snapshot.rooms);
         });
-      } else {
-        this.logger.warn('handleSystemMessage', `No rooms found in message: ${message}`);
       }
     } else if (message.includes('[SystemMembers]')) {
-      const matchMembers = message.match(/\[SystemMembers]\s*(.*?)$/);
-      if (matchMembers) {
-        const members = matchMembers[1].split(',').map((member: string) => member.trim());
+      const snapshot = this.parsePayload<IMembersSnapshot>(message, '[SystemMembers]');
+      if (snapshot) {
+        this.membersRoom = snapshot.room;
+        this.versions.members = snapshot.version;
         this.ngZone.run(() => {
-          this.members$.next(members);
+          this.setMembers(snapshot.members);
         });
-      } else {
-        this.logger.warn('handleSystemMessage', `No members found in message: ${message}`);
       }
     } else if (message.includes('[SystemJoin]')) {
       const matchJoin = message.match(/^(.*?)\s*\[SystemJoin]\s*(.*?)$/);
@@ -100,4 +171,40 @@ export class RoomService implements IRoomService {
       }
     }
   }
+
+  private setMembers(members: IMember[]): void {
+    const names = members.map((member) => member.name);
+    const activities = new Map(
+      [...this.activities$.value].filter(([name]) => names.includes(name))
+    );
+    this.memberDetails$.next(members);
+    this.members$.next(names);
+    this.activities$.next(activities);
+  }
+
+  private parsePayload<T>(message: string, prefix: string): T | null {
+    try {
+      return JSON.parse(message.substring(message.indexOf(prefix) + prefix.length)) as T;
+    } catch (error) {
+      this.logger.warn('parsePayload', `Invalid ${prefix} message: ${message}`, error);
+      return null;
+    }
+  }
+
+  /**
+   * Deltas must arrive in order; older ones are ignored and a gap means one
+   * was missed, so a fresh snapshot is requested instead.
+   */
+  private isNextVersion(list: 'rooms' | 'members', version: number): boolean {
+    if (version <= this.versions[list]) {
+      return false;
+    }
+    if (version > this.versions[list] + 1) {
+      this.logger.warn('isNextVersion', `Missed ${list} update, requesting snapshot`);
+      this.wsService.send('[UserCommand] /sync');
+      return false;
+    }
+    this.versions[list] = version;
+    return true;
+  }
 }
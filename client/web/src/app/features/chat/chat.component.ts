@@ -19,6 +19,7 @@ import { ThemeService } from '../../core/services/ui/theme.service';
 import { ChatService } from '../../core/services/communication/chat.service';
 import { HeartbeatService } from '../../core/services/communication/heartbeat.service';
 import { RoomService } from '../../core/services/room-management/room.service';
+import { Activity, IMember, Presence } from '../../core/interfaces/room.interface';
 import { FileTransferService } from '../../core/services/file-management/file-transfer.service';
 import { WebRTCService } from '../../core/services/communication/webrtc.service';
 import { WebSocketConnectionService } from '../../core/services/communication/websocket-connection.service';
@@ -36,6 +37,7 @@ import {
   NAVIGATION_DELAY_MS,
   CONNECTION_WARNING_DELAY_MS,
   SESSION_CODE_KEY,
+  TYPING_IDLE_MS,
   THEME_PREFERENCE_KEY,
   PREVIEW_MIME_TYPE,
 } from '../../utils/constants';
@@ -130,10 +132,12 @@ export class ChatComponent implements OnInit, OnDestroy, AfterViewInit {
   messages: ChatMessage[] = [];
   rooms: string[] = [];
   members: string[] = [];
+  memberDetails: IMember[] = [];
   memberConnectionStatus = new Map<string, boolean>(); // true = connected, false = failed
   showConnectionWarning = false;
 
   currentRoom = 'main';
+  status: Presence = 'active';
   isDarkMode = false;
   currentLanguage: LanguageCode = 'en';
   isMenuOpen = false;
@@ -147,6 +151,9 @@ export class ChatComponent implements OnInit, OnDestroy, AfterViewInit {
   activeUploads: FileUpload[] = [];
   activeDownloads: FileDownload[] = [];
 
+  activities = new Map<string, Activity>();
+  typingMembers: string[] = [];
+
   private isNavigatingIntentionally = false;
   private lastMessagesLength: number = 0;
   private connectionInitTimeouts: ReturnType<typeof setTimeout>[] = [];
@@ -154,6 +161,8 @@ export class ChatComponent implements OnInit, OnDestroy, AfterViewInit {
   private statusCheckIntervalId: ReturnType<typeof setInterval> | null = null;
   private connectionWarningDismissed = false;
   private connectionWarningTimeouts = new Map<string, ReturnType<typeof setTimeout>>();
+  private activity: Activity = 'none';
+  private typingTimeout: ReturnType<typeof setTimeout> | null = null;
 
   appVersion: string = packageJson.version;
 
@@ -363,6 +372,12 @@ export class ChatComponent implements OnInit, OnDestroy, AfterViewInit {
     }
     this.connectionWarningTimeouts.clear();
 
+    // Clear typing timeout
+    if (this.typingTimeout) {
+      clearTimeout(this.typingTimeout);
+      this.typingTimeout = null;
+    }
+
     if (isPlatformBrowser(this.platformId) && this.visibilityChangeListener) {
       document.removeEventListener('visibilitychange', this.visibilityChangeListener);
     }
@@ -525,6 +540,29 @@ export class ChatComponent implements OnInit, OnDestroy, AfterViewInit {
       })
     );
 
+    // Listen for member details such as their devices
+    this.subscriptions.push(
+      this.roomService.memberDetails$.subscribe((details: IMember[]) => {
+        this.ngZone.run(() => {
+          this.memberDetails = details;
+          this.cdr.detectChanges();
+        });
+      })
+    );
+
+    // Listen for what other members are doing
+    this.subscriptions.push(
+      this.roomService.activities$.subscribe((activities: Map<string, Activity>) => {
+        this.ngZone.run(() => {
+          this.activities = activities;
+          this.typingMembers = [...activities.entries()]
+            .filter(([, activity]) => activity === 'typing')
+            .map(([member]) => member);
+          this.cdr.detectChanges();
+        });
+      })
+    );
+
     // Listen for current members in the room
     this.subscriptions.push(
       this.roomService.members$.subscribe((allMembers: string[]) => {
@@ -560,6 +598,9 @@ export class ChatComponent implements OnInit, OnDestroy, AfterViewInit {
         this.logger.info('activeUploads', `Active uploads: ${uploads.length} (length)`);
         this.ngZone.run(() => {
           this.activeUploads = uploads;
+          if (uploads.length > 0 || this.activity === 'uploading') {
+            this.setActivity(uploads.length > 0 ? 'uploading' : 'none');
+          }
           this.cdr.detectChanges();
         });
       })
@@ -831,6 +872,7 @@ export class ChatComponent implements OnInit, OnDestroy, AfterViewInit {
 
       this.ngZone.run(() => {
         this.message = '';
+        this.setActivity('none');
         messageForm.resetForm({ message: '' });
         this.cdr.detectChanges();
         this.scrollToBottom();
@@ -922,6 +964,7 @@ export class ChatComponent implements OnInit, OnDestroy, AfterViewInit {
    * ==========================================================
    */
   async sendAttachments(event: Event): Promise<void> {
+    this.setActivity('none');
     const input = event.target as HTMLInputElement;
     if (input.files && input.files.length > 0) {
       const filesToSend = Array.from(input.files);
@@ -979,6 +1022,7 @@ export class ChatComponent implements OnInit, OnDestroy, AfterViewInit {
    */
   openFilePickerForMember(member: string): void {
     this.overrideRecipients = [member];
+    this.setActivity('selecting-file');
     if (this.fileInput?.nativeElement) {
       this.fileInput.nativeElement.value = '';
       this.fileInput.nativeElement.click();
@@ -1096,6 +1140,56 @@ export class ChatComponent implements OnInit, OnDestroy, AfterViewInit {
     }
   }
 
+  /**
+   * ==========================================================
+   * SET STATUS
+   * Shares the user's chosen presence with the room.
+   * ==========================================================
+   */
+  setStatus(status: Presence): void {
+    this.roomService.setStatus(status);
+    this.status = status;
+  }
+
+  /**
+   * ==========================================================
+   * MESSAGE CHANGE
+   * Tracks the input and tells the room while the user is typing.
+   * ==========================================================
+   */
+  onMessageChange(message: string): void {
+    this.message = message;
+    if (this.typingTimeout) {
+      clearTimeout(this.typingTimeout);
+      this.typingTimeout = null;
+    }
+    if (!message.trim()) {
+      this.setActivity('none');
+      return;
+    }
+    this.setActivity('typing');
+    this.typingTimeout = setTimeout(() => {
+      this.typingTimeout = null;
+      if (this.activity === 'typing') {
+        this.setActivity('none');
+      }
+    }, TYPING_IDLE_MS);
+  }
+
+  /**
+   * ==========================================================
+   * FILE PICKER TOGGLED
+   * Tells the room while the user is choosing files to send.
+   * ==========================================================
+   */
+  onFilePickerToggled(open: boolean): void {
+    if (open) {
+      this.setActivity('selecting-file');
+    } else if (this.activity === 'selecting-file') {
+      this.setActivity('none');
+    }
+  }
+
   /**
    * ==========================================================
    * OPEN CREATE ROOM POPUP
@@ -1284,6 +1378,7 @@ export class ChatComponent implements OnInit, OnDestroy, AfterViewInit {
       this.activeUploads = [];
       this.activeDownloads = [];
       this.overrideRecipients = null;
+      this.activity = 'none';
 
       // Disconnect WebSocket
       this.wsConnectionService.disconnect();
@@ -1653,4 +1748,21 @@ export class ChatComponent implements OnInit, OnDestroy, AfterViewInit {
       }
     }
   }
+
+  /**
+   * ==========================================================
+   * SET ACTIVITY
+   * Sends the user's activity when it changes. Clearing it while
+   * files are still being sent reports the upload instead.
+   * ==========================================================
+   */
+  private setActivity(activity: Activity): void {
+    if (activity === 'none' && this.activeUploads.length > 0) {
+      activity = 'uploading';
+    }
+    if (activity !== this.activity) {
+      this.activity = activity;
+      this.roomService.sendActivity(activity);
+    }
+  }
 }
@@ -41,6 +41,7 @@ export class ChatInputComponent implements OnDestroy {
   @Output() autoResize = new EventEmitter<void>();
   @Output() filesAttached = new EventEmitter<Event>();
   @Output() filesDropped = new EventEmitter<File[]>();
+  @Output() filePickerToggled = new EventEmitter<boolean>();
 
   @ViewChild('messageTextarea', { static: false }) messageTextarea!: ElementRef;
   @ViewChild('fileInput', { static: false }) fileInput!: ElementRef<HTMLInputElement>;
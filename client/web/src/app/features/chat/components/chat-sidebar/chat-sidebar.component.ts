@@ -5,6 +5,7 @@ import { TranslateModule } from '@ngx-translate/core';
 import { NGXLogger } from 'ngx-logger';
 
 import { FileDownload, FileUpload } from '../../../../utils/constants';
+import { Activity, IAvatar, IMember, Presence } from '../../../../core/interfaces/room.interface';
 
 @Component({
   selector: 'app-chat-sidebar',
@@ -15,6 +16,7 @@ import { FileDownload, FileUpload } from '../../../../utils/constants';
 export class ChatSidebarComponent {
   @Input() rooms: string[] = [];
   @Input() members: string[] = [];
+  @Input() memberDetails: IMember[] = [];
   @Input() currentRoom = '';
   @Input() sessionCode = '';
   @Input() activeUploads: FileUpload[] = [];
@@ -26,6 +28,8 @@ export class ChatSidebarComponent {
   @Input() skipDrawerAnim = false;
   @Input() currentUser: string | null = null;
   @Input() appVersion = '';
+  @Input() status: Presence = 'active';
+  @Input() activities: Map<string, Activity> = new Map();
 
   @Output() isMenuOpenChange = new EventEmitter<boolean>();
 
@@ -39,6 +43,9 @@ export class ChatSidebarComponent {
   @Output() filePickerRequested = new EventEmitter<string>();
   @Output() cancelUploadRequested = new EventEmitter<FileUpload>();
   @Output() cancelDownloadRequested = new EventEmitter<FileDownload>();
+  @Output() statusChangeRequested = new EventEmitter<Presence>();
+
+  protected readonly statuses: Presence[] = ['active', 'idle', 'away'];
 
   private logger = inject(NGXLogger);
 
@@ -46,6 +53,39 @@ export class ChatSidebarComponent {
     return this.memberConnectionStatus.get(member) ?? false;
   }
 
+  /**
+   * Avatar the server assigned to a member, the same on every client
+   */
+  protected memberAvatar(member: string): IAvatar | undefined {
+    return this.memberDetails.find((m) => m.name === member)?.avatar;
+  }
+
+  /**
+   * Short description of a member's device, e.g. "iOS 17.4 · mobile"
+   */
+  protected memberDevice(member: string): string {
+    const device = this.memberDetails.find((m) => m.name === member)?.device;
+    if (!device) {
+      return '';
+    }
+    const type = device.type === 'unknown' ? '' : device.type;
+    return [device.os, type].filter(Boolean).join(' · ');
+  }
+
+  /**
+   * Translation key for what a member is doing, or '' when nothing
+   */
+  protected memberActivity(member: string): string {
+    const activity = this.activities.get(member);
+    return activity && activity !== 'none'
+      ? `ACTIVITY_${activity.replace('-', '_').toUpperCase()}`
+      : '';
+  }
+
+  protected statusLabel(status: Presence): string {
+    return `STATUS_${status.toUpperCase()}`;
+  }
+
   protected progressValue(progress: number, type: 'upload' | 'download', fileId: string): number {
     const clampedProgress = Math.min(100, Math.max(0, progress));
 
@@ -28,6 +28,7 @@ export const NAVIGATION_DELAY_MS = 100;
 export const IDLE_TIMEOUT = 12 * 60 * 60 * 1000; // 12 hours
 export const BACKGROUND_EXPIRY_THRESHOLD = 5 * 60 * 1000; // 5 minutes
 export const CONNECTION_WARNING_DELAY_MS = 5_000; // 5 seconds before showing connection warning
+export const TYPING_IDLE_MS = 5_000; // 5 seconds without a keystroke clears the typing indicator
 
 // WebRTC constants
 export const MAX_RECONNECT_ATTEMPTS = 5;
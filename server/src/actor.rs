@@ -1,5 +1,5 @@
-use crate::{LeaveRoom, WsChatServer, WsChatSession};
-use actix::{Context, SystemService, prelude::Actor};
+use crate::{LeaveRoom, WsChatServer, WsChatSession, message::RejectedSession};
+use actix::{ActorContext, Context, SystemService, prelude::Actor};
 use actix_broker::BrokerSubscribe;
 use actix_web_actors::ws;
 use rand::{RngExt, rng};
@@ -27,6 +27,16 @@ impl Actor for WsChatSession {
             self.id
         );
         log::debug!(target: "Websocket","Auto-join is set to: {}", self.auto_join);
+        if let Some(identity) = &self.peer.identity {
+            log::info!(
+                target: "Websocket",
+                "Session {} authenticated as '{identity}'",
+                self.id
+            );
+        }
+        if let Some(peer_id) = &self.peer.peer_id {
+            log::debug!(target: "Websocket", "Session {} has peer identity '{peer_id}'", self.id);
+        }
 
         self.last_heartbeat = Some(Instant::now());
         self.start_heartbeat(ctx);
@@ -56,6 +66,10 @@ impl Actor for WsChatSession {
             );
         }
 
+        if let Some(ip) = self.peer.client_ip {
+            self.session_store.release_ip_connection(ip);
+        }
+
         if let Ok(uuid) = Uuid::parse_str(&self.session_id) {
             log::debug!(target: "Websocket","Removing client {uuid} from session");
             self.session_store.remove_client(&uuid);
@@ -68,3 +82,15 @@ impl Actor for WsChatSession {
         }
     }
 }
+
+impl Actor for RejectedSession {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Again,
+            description: Some(self.reason.clone()),
+        }));
+        ctx.stop();
+    }
+}
@@ -0,0 +1,203 @@
+use crate::{ServerError, config::AuthConfig, metrics::metrics, screening::constant_time_eq};
+use actix_web::{HttpRequest, http::header};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const AUTH_FAILURE_METRIC: &str = "pastepoint_auth_failures_total";
+
+/// Permissions granted to an API key or token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    CreateSession,
+    Join,
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::CreateSession => "create-session",
+            Scope::Join => "join",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A static API key from the configuration.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApiKey {
+    pub name: String,
+    pub key: String,
+    pub scopes: Vec<Scope>,
+}
+
+/// Payload of an HMAC-signed bearer token.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenClaims {
+    pub sub: String,
+    pub scopes: Vec<Scope>,
+    /// Expiry as seconds since the Unix epoch.
+    pub exp: u64,
+}
+
+/// The authenticated caller of a request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Identity {
+    pub subject: String,
+    pub scopes: Vec<Scope>,
+}
+
+impl Identity {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.subject)
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn hmac_sha256(secret: &str, data: &[u8]) -> Result<Hmac<Sha256>, hmac::digest::InvalidLength> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())?;
+    mac.update(data);
+    Ok(mac)
+}
+
+/// Issues a token of the form `base64url(claims).base64url(hmac_sha256)`.
+pub fn issue_token(secret: &str, claims: &TokenClaims) -> Result<String, ServerError> {
+    let payload = serde_json::to_vec(claims).map_err(|_| ServerError::InternalServerError)?;
+    let payload = URL_SAFE_NO_PAD.encode(payload);
+    let signature = hmac_sha256(secret, payload.as_bytes())
+        .map_err(|_| ServerError::InternalServerError)?
+        .finalize()
+        .into_bytes();
+    Ok(format!("{payload}.{}", URL_SAFE_NO_PAD.encode(signature)))
+}
+
+impl AuthConfig {
+    /// Extracts the credential from `Authorization: Bearer ...` or, for
+    /// browser WebSocket clients that cannot set headers, the query string.
+    /// Bearer JWTs are left to the JWT verifier.
+    fn credential(&self, req: &HttpRequest) -> Option<String> {
+        if let Some(value) = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            && let Some(token) = value.strip_prefix("Bearer ")
+            && token.trim().split('.').count() < 3
+        {
+            return Some(token.trim().to_string());
+        }
+
+        url::form_urlencoded::parse(req.query_string().as_bytes())
+            .find(|(name, _)| *name == self.query_param.as_str())
+            .map(|(_, value)| value.into_owned())
+    }
+
+    fn verify_token(&self, token: &str) -> Result<Identity, &'static str> {
+        let secret = self.token_secret.as_deref().ok_or("unknown_credential")?;
+        let (payload, signature) = token.split_once('.').ok_or("malformed_token")?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| "malformed_token")?;
+        hmac_sha256(secret, payload.as_bytes())
+            .map_err(|_| "malformed_token")?
+            .verify_slice(&signature)
+            .map_err(|_| "bad_signature")?;
+
+        let claims: TokenClaims = URL_SAFE_NO_PAD
+            .decode(payload)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or("malformed_token")?;
+        if claims.exp.saturating_add(self.clock_skew_secs) < unix_now() {
+            return Err("expired_token");
+        }
+
+        Ok(Identity {
+            subject: claims.sub,
+            scopes: claims.scopes,
+        })
+    }
+
+    /// Verifies the request's credential against the configured API keys and
+    /// token secret. Returns a short failure reason used for logs and metrics.
+    pub fn authenticate(&self, req: &HttpRequest) -> Result<Identity, &'static str> {
+        let credential = self.credential(req).ok_or("missing_credential")?;
+
+        if let Some(api_key) = self
+            .api_keys
+            .iter()
+            .find(|k| constant_time_eq(k.key.as_bytes(), credential.as_bytes()))
+        {
+            return Ok(Identity {
+                subject: api_key.name.clone(),
+                scopes: api_key.scopes.clone(),
+            });
+        }
+
+        self.verify_token(&credential)
+    }
+
+    /// Authorizes a request for `scope`. Returns `None` when authentication is
+    /// disabled, so routes behave as before.
+    pub fn authorize(
+        &self,
+        req: &HttpRequest,
+        scope: Scope,
+    ) -> Result<Option<Identity>, ServerError> {
+        if !self.enabled {
+            return Ok(None);
+        }
+
+        let identity = match self.authenticate(req) {
+            Ok(identity) => identity,
+            Err(reason) => {
+                log::warn!(target: "Websocket", "Unauthenticated request to {}: {reason}", req.path());
+                metrics().increment(
+                    AUTH_FAILURE_METRIC,
+                    &[("scope", scope.as_str()), ("reason", reason)],
+                );
+                return Err(ServerError::Unauthorized);
+            }
+        };
+
+        if !identity.has_scope(scope) {
+            log::warn!(
+                target: "Websocket",
+                "Identity '{identity}' lacks scope '{scope}' for {}",
+                req.path()
+            );
+            metrics().increment(
+                AUTH_FAILURE_METRIC,
+                &[("scope", scope.as_str()), ("reason", "missing_scope")],
+            );
+            return Err(ServerError::Forbidden);
+        }
+
+        log::debug!(target: "Websocket", "Authorized '{identity}' for {scope} on {}", req.path());
+        Ok(Some(identity))
+    }
+}
@@ -0,0 +1,48 @@
+use crate::consts::{AVATAR_COLORS, AVATAR_EMOJI};
+use serde::Serialize;
+
+/// How a member is drawn. The server assigns it, so every client shows the
+/// same avatar for a member without coordinating. It only stays the same
+/// across connections for peers with a stable identity; anonymous peers are
+/// drawn from their generated name and get a new avatar when they reconnect.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Avatar {
+    pub color: &'static str,
+    pub emoji: &'static str,
+    pub initials: String,
+}
+
+impl Avatar {
+    /// Avatar for a peer identified by `identity`, usually
+    /// `PeerInfo::peer_id`, whose display name is `name`.
+    pub fn new(identity: &str, name: &str) -> Self {
+        let hash = fnv1a(identity.as_bytes());
+        Avatar {
+            color: AVATAR_COLORS[(hash % AVATAR_COLORS.len() as u64) as usize],
+            emoji: AVATAR_EMOJI[((hash >> 32) % AVATAR_EMOJI.len() as u64) as usize],
+            initials: initials(name),
+        }
+    }
+}
+
+// Helper function to hash identities the same way on every build and
+// platform, which `DefaultHasher` does not promise
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// Helper function to take the first letter of the first and last words
+fn initials(name: &str) -> String {
+    let mut words = name
+        .split_whitespace()
+        .filter_map(|word| word.chars().next());
+    let first = words.next();
+    let last = words.next_back();
+    first
+        .into_iter()
+        .chain(last)
+        .flat_map(char::to_uppercase)
+        .collect()
+}
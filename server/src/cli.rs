@@ -0,0 +1,79 @@
+use crate::config::ConfigSources;
+
+/// Help text printed for `--help` and after invalid arguments.
+pub const USAGE: &str = "\
+Usage: server_bin [OPTIONS]
+
+Options:
+  -c, --config <FILE>       Config file [default: $PASTEPOINT_CONFIG or config/$RUN_ENV.toml]
+  -b, --bind <ADDRESS>      Address of the default TLS listener
+      --log-level <FILTER>  Log filter, e.g. info or server=debug
+      --key <FILE>          TLS private key
+      --cert <FILE>         TLS certificate chain
+      --public-url <URL>    Public URL used to build join links
+      --auto-join           Join clients to their public session automatically
+      --no-auto-join        Let clients pick a session themselves
+  -s, --set <KEY=VALUE>     Set any [server] field, e.g. mdns.enabled=true
+  -h, --help                Print help
+  -V, --version             Print version
+
+Every field can also be set with a PASTEPOINT_* environment variable, using
+`__` between nested keys, e.g. PASTEPOINT_MDNS__ENABLED=true. Lists and tables
+take JSON values. Flags take precedence over environment variables, which take
+precedence over the config file.
+";
+
+/// What the command line asked the binary to do.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CliCommand {
+    /// Start the server with these configuration sources.
+    Run(ConfigSources),
+    Help,
+    Version,
+}
+
+/// Parses the arguments following the program name. Both `--flag value` and
+/// `--flag=value` are accepted.
+pub fn parse_args<I>(args: I) -> Result<CliCommand, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut sources = ConfigSources::from_env();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{flag} requires a value"))
+        };
+
+        match flag {
+            "-h" | "--help" => return Ok(CliCommand::Help),
+            "-V" | "--version" => return Ok(CliCommand::Version),
+            "-c" | "--config" => sources.file = Some(value()?),
+            "-b" | "--bind" => sources.set("bind_address", value()?),
+            "--log-level" => sources.set("log_level", value()?),
+            "--key" => sources.set("key_file_path", value()?),
+            "--cert" => sources.set("cert_file_path", value()?),
+            "--public-url" => sources.set("public_base_url", value()?),
+            "--auto-join" => sources.set("auto_join", "true"),
+            "--no-auto-join" => sources.set("auto_join", "false"),
+            "-s" | "--set" => {
+                let setting = value()?;
+                let (key, value) = setting
+                    .split_once('=')
+                    .ok_or_else(|| format!("--set expects KEY=VALUE, got '{setting}'"))?;
+                sources.set(key.trim(), value);
+            }
+            _ => return Err(format!("unknown argument '{arg}'")),
+        }
+    }
+
+    Ok(CliCommand::Run(sources))
+}
@@ -0,0 +1,91 @@
+use crate::config::ForwardedHeader;
+use actix_http::header::HeaderMap;
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+
+/// Resolves the originating client address of a request.
+///
+/// The peer address is only looked past when it belongs to a trusted proxy.
+/// In that case the forwarding chain in `header`, the one header the proxies
+/// write, is walked from right to left and the first hop that is not itself
+/// a trusted proxy is returned. A hop that cannot be parsed ends the walk at
+/// the last address we could verify. Other forwarding headers are ignored,
+/// as proxies pass them through from the client unchanged.
+pub fn resolve_client_ip(
+    peer: Option<IpAddr>,
+    headers: &HeaderMap,
+    trusted_proxies: &[IpNet],
+    header: ForwardedHeader,
+) -> Option<IpAddr> {
+    let peer = peer?.to_canonical();
+    if !is_trusted(peer, trusted_proxies) {
+        return Some(peer);
+    }
+
+    let mut client = peer;
+    for hop in forwarded_chain(headers, header).iter().rev() {
+        match parse_node(hop) {
+            Some(ip) => {
+                client = ip;
+                if !is_trusted(ip, trusted_proxies) {
+                    break;
+                }
+            }
+            None => {
+                log::debug!(target: "Websocket", "Unusable forwarding hop '{hop}', stopping at {client}");
+                break;
+            }
+        }
+    }
+    Some(client)
+}
+
+fn is_trusted(ip: IpAddr, trusted_proxies: &[IpNet]) -> bool {
+    trusted_proxies.iter().any(|net| net.contains(&ip))
+}
+
+// Collects the forwarding chain in `header`, left (client) to right
+// (nearest proxy).
+fn forwarded_chain(headers: &HeaderMap, header: ForwardedHeader) -> Vec<String> {
+    match header {
+        ForwardedHeader::Forwarded => headers
+            .get_all("Forwarded")
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(|element| {
+                element.split(';').find_map(|pair| {
+                    let (key, value) = pair.split_once('=')?;
+                    key.trim()
+                        .eq_ignore_ascii_case("for")
+                        .then(|| value.trim().trim_matches('"').to_string())
+                })
+            })
+            .collect(),
+        ForwardedHeader::XForwardedFor => headers
+            .get_all("X-Forwarded-For")
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(|hop| hop.trim().to_string())
+            .filter(|hop| !hop.is_empty())
+            .collect(),
+        ForwardedHeader::XRealIp => headers
+            .get("X-Real-IP")
+            .and_then(|v| v.to_str().ok())
+            .map(|ip| vec![ip.trim().to_string()])
+            .unwrap_or_default(),
+    }
+}
+
+// Parses a node such as `192.0.2.60`, `192.0.2.60:4711` or `[2001:db8::1]:4711`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim();
+    node.parse::<IpAddr>()
+        .or_else(|_| node.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .or_else(|_| {
+            node.trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<IpAddr>()
+        })
+        .ok()
+        .map(|ip| ip.to_canonical())
+}
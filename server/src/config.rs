@@ -1,7 +1,28 @@
+use crate::{
+    BROADCAST_DEBOUNCE, CONFIG_ENV_PREFIX, CONFIG_FILE_ENV, DEFAULT_USER_AGENT_DENY,
+    HEARTBEAT_INTERVAL, HEARTBEAT_TIMEOUT, IDLE_THRESHOLD, MAX_FRAME_SIZE, MAX_SIGNAL_SIZE,
+    MDNS_PORT, MIN_USER_AGENT_LENGTH, SESSION_EXPIRATION_TIME,
+    auth::ApiKey,
+    consts::{
+        ABUSE_WINDOW, ACTIVITY_QUOTA, BINARY_QUOTA, COMMAND_QUOTA, MAX_CLIENTS_PER_ROOM,
+        MAX_CONNECTIONS_PER_IP, MAX_CONNECTIONS_PER_SESSION, MAX_OUTBOUND_BYTES,
+        MAX_OUTBOUND_MESSAGES, MAX_ROOMS_PER_SESSION, MAX_SESSIONS, MAX_THROTTLED_MESSAGES,
+        SIGNAL_QUOTA,
+    },
+    origin::{OriginPattern, deserialize_origins},
+    screening::UaPattern,
+};
 use actix_http::header::HeaderValue;
-use config::{Config, ConfigError, File};
-use serde::Deserialize;
-use std::env;
+use config::{Config, ConfigError, File, Map, Value, ValueKind};
+use ipnet::IpNet;
+use jsonwebtoken::Algorithm;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs},
+    time::Duration,
+};
 use url::Url;
 
 // This function provides a default value for the log level.
@@ -9,36 +30,825 @@ fn default_log_level() -> String {
     "debug".to_string()
 }
 
+fn default_rate_limit_per_second() -> u64 {
+    50
+}
+
+fn default_rate_limit_burst_size() -> u32 {
+    100
+}
+
+fn default_cert_watch_interval() -> u64 {
+    30
+}
+
+fn default_config_watch_interval() -> u64 {
+    30
+}
+
+fn default_socket_mode() -> u32 {
+    0o660
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct ServerConfig {
     pub bind_address: String,
+    /// Only required when a TLS listener is configured.
+    #[serde(default)]
     pub key_file_path: String,
+    #[serde(default)]
     pub cert_file_path: String,
+    /// How often the key and certificate files are checked for changes;
+    /// 0 disables watching. SIGHUP always triggers a reload.
+    #[serde(default = "default_cert_watch_interval")]
+    pub cert_watch_interval_secs: u64,
+    /// How often the config file is checked for changes; 0 disables
+    /// watching. SIGHUP always triggers a reload.
+    #[serde(default = "default_config_watch_interval")]
+    pub config_watch_interval_secs: u64,
     pub auto_join: bool,
+    #[serde(default = "default_rate_limit_per_second")]
     pub rate_limit_per_second: u64,
+    #[serde(default = "default_rate_limit_burst_size")]
     pub rate_limit_burst_size: u32,
     #[serde(default = "default_log_level")]
     pub log_level: String,
-    pub cors_allowed_origins: String,
+    /// Browser origins allowed by CORS and on WebSocket upgrades.
+    #[serde(default, deserialize_with = "deserialize_origins")]
+    pub cors_allowed_origins: Vec<OriginPattern>,
+    /// Public URL clients use to reach the web app, used to build join links.
+    /// Falls back to the scheme and host of the incoming request when unset.
+    #[serde(default)]
+    pub public_base_url: Option<String>,
+    /// Proxies whose forwarding headers are trusted when resolving client IPs.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpNet>,
+    /// The forwarding header the trusted proxies write. Every other one is
+    /// ignored, since a proxy passes those through from the client.
+    #[serde(default)]
+    pub forwarded_header: ForwardedHeader,
+    /// Where the server accepts connections. Defaults to a single TLS
+    /// listener on `bind_address`.
+    #[serde(default)]
+    pub listeners: Vec<ListenerConfig>,
+    #[serde(default)]
+    pub https_redirect: HttpsRedirectConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub mdns: MdnsConfig,
+    #[serde(default)]
+    pub session_grouping: SessionGroupingConfig,
+    #[serde(default)]
+    pub ip_filter: IpFilterConfig,
+    #[serde(default)]
+    pub screening: ScreeningConfig,
+    #[serde(default)]
+    pub websocket_origin: WebSocketOriginConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub jwt: JwtConfig,
+    #[serde(default)]
+    pub client_auth: ClientAuthConfig,
+}
+
+/// A socket the server accepts connections on.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ListenerConfig {
+    /// HTTPS using `key_file_path` and `cert_file_path`.
+    Tls { address: String },
+    /// Plain HTTP, for running behind a TLS-terminating proxy.
+    Tcp { address: String },
+    /// Plain HTTP on a Unix domain socket. Connections count as loopback,
+    /// so add `127.0.0.1/32` to `trusted_proxies` when a proxy forwards
+    /// client addresses over the socket.
+    Unix {
+        path: String,
+        /// File mode applied to the socket, e.g. `0o660`.
+        #[serde(default = "default_socket_mode")]
+        mode: u32,
+    },
+}
+
+/// Plain HTTP listener redirecting every request to HTTPS.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct HttpsRedirectConfig {
+    pub enabled: bool,
+    pub bind_address: String,
+    /// Port used in the redirect target; defaults to the first TLS listener.
+    pub https_port: Option<u16>,
+}
+
+impl Default for HttpsRedirectConfig {
+    fn default() -> Self {
+        HttpsRedirectConfig {
+            enabled: false,
+            bind_address: "0.0.0.0:80".to_string(),
+            https_port: None,
+        }
+    }
+}
+
+/// Resource limits for sessions, rooms and WebSocket connections.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// Largest WebSocket frame accepted, in bytes.
+    pub max_frame_size: usize,
+    /// Largest signaling message relayed between peers, in bytes.
+    pub max_signal_size: usize,
+    /// Rooms a single session may hold.
+    pub max_rooms_per_session: usize,
+    /// Sessions the server keeps at once, public and private.
+    pub max_sessions: usize,
+    /// Concurrent WebSockets from a single client IP.
+    pub max_connections_per_ip: usize,
+    /// Concurrent WebSockets joined to a single session.
+    pub max_connections_per_session: usize,
+    /// Clients in a single room at once.
+    pub max_clients_per_room: usize,
+    /// Budget for commands, keep-alives and other text messages.
+    pub commands: MessageQuota,
+    /// Budget for signaling messages relayed to other peers.
+    pub signals: MessageQuota,
+    /// Budget for binary frames.
+    pub binary: MessageQuota,
+    /// Budget for `/activity` commands fanned out to the room.
+    pub activity: MessageQuota,
+    /// Dropped messages tolerated within `abuse_window_secs` before the
+    /// client is disconnected.
+    pub max_throttled_messages: u32,
+    pub abuse_window_secs: u64,
+    /// Messages queued for a client that is not keeping up.
+    pub max_outbound_messages: usize,
+    /// Bytes queued for a client that is not keeping up.
+    pub max_outbound_bytes: usize,
+    /// What to do once a client's outbound queue is full.
+    pub outbound_overflow: OverflowPolicy,
+    /// How long room and member changes are collected before the deltas
+    /// are sent, in milliseconds.
+    pub broadcast_debounce_ms: u64,
+    /// How often clients are pinged.
+    pub heartbeat_interval_secs: u64,
+    /// Clients silent for longer than this are disconnected.
+    pub heartbeat_timeout_secs: u64,
+    /// Clients that sent nothing but heartbeats for this long are shown
+    /// as idle.
+    pub idle_threshold_secs: u64,
+    /// How long a private session code survives its last client.
+    pub session_expiration_secs: u64,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            max_frame_size: MAX_FRAME_SIZE,
+            max_signal_size: MAX_SIGNAL_SIZE,
+            max_rooms_per_session: MAX_ROOMS_PER_SESSION,
+            max_sessions: MAX_SESSIONS,
+            max_connections_per_ip: MAX_CONNECTIONS_PER_IP,
+            max_connections_per_session: MAX_CONNECTIONS_PER_SESSION,
+            max_clients_per_room: MAX_CLIENTS_PER_ROOM,
+            commands: COMMAND_QUOTA,
+            signals: SIGNAL_QUOTA,
+            binary: BINARY_QUOTA,
+            activity: ACTIVITY_QUOTA,
+            max_throttled_messages: MAX_THROTTLED_MESSAGES,
+            abuse_window_secs: ABUSE_WINDOW.as_secs(),
+            max_outbound_messages: MAX_OUTBOUND_MESSAGES,
+            max_outbound_bytes: MAX_OUTBOUND_BYTES,
+            outbound_overflow: OverflowPolicy::default(),
+            broadcast_debounce_ms: BROADCAST_DEBOUNCE.as_millis() as u64,
+            heartbeat_interval_secs: HEARTBEAT_INTERVAL.as_secs(),
+            heartbeat_timeout_secs: HEARTBEAT_TIMEOUT.as_secs(),
+            idle_threshold_secs: IDLE_THRESHOLD.as_secs(),
+            session_expiration_secs: SESSION_EXPIRATION_TIME.as_secs(),
+        }
+    }
+}
+
+impl LimitsConfig {
+    pub fn heartbeat_interval(&self) -> Duration {
+        Duration::from_secs(self.heartbeat_interval_secs)
+    }
+
+    pub fn heartbeat_timeout(&self) -> Duration {
+        Duration::from_secs(self.heartbeat_timeout_secs)
+    }
+
+    pub fn idle_threshold(&self) -> Duration {
+        Duration::from_secs(self.idle_threshold_secs)
+    }
+
+    pub fn session_expiration(&self) -> Duration {
+        Duration::from_secs(self.session_expiration_secs)
+    }
+
+    pub fn abuse_window(&self) -> Duration {
+        Duration::from_secs(self.abuse_window_secs)
+    }
+
+    pub fn broadcast_debounce(&self) -> Duration {
+        Duration::from_millis(self.broadcast_debounce_ms)
+    }
+
+    // Helper function to collect limits that would stop the server working
+    fn validate(&self, errors: &mut Vec<String>) {
+        for (field, value) in [
+            ("max_frame_size", self.max_frame_size as u64),
+            ("max_signal_size", self.max_signal_size as u64),
+            ("max_rooms_per_session", self.max_rooms_per_session as u64),
+            ("max_sessions", self.max_sessions as u64),
+            ("max_connections_per_ip", self.max_connections_per_ip as u64),
+            (
+                "max_connections_per_session",
+                self.max_connections_per_session as u64,
+            ),
+            ("max_clients_per_room", self.max_clients_per_room as u64),
+            ("heartbeat_interval_secs", self.heartbeat_interval_secs),
+            ("idle_threshold_secs", self.idle_threshold_secs),
+            ("abuse_window_secs", self.abuse_window_secs),
+            ("max_outbound_messages", self.max_outbound_messages as u64),
+        ] {
+            if value == 0 {
+                errors.push(format!("limits.{field} must be greater than 0"));
+            }
+        }
+        // A message larger than the byte burst could never be sent.
+        for (name, quota, largest) in [
+            ("commands", &self.commands, self.max_frame_size),
+            ("signals", &self.signals, self.max_signal_size),
+            ("binary", &self.binary, self.max_frame_size),
+            ("activity", &self.activity, self.max_frame_size),
+        ] {
+            quota.validate(name, largest as u64, errors);
+        }
+        if self.max_outbound_bytes < self.max_signal_size {
+            errors.push(
+                "limits.max_outbound_bytes must be at least limits.max_signal_size".to_string(),
+            );
+        }
+        if self.heartbeat_timeout_secs <= self.heartbeat_interval_secs {
+            errors.push(
+                "limits.heartbeat_timeout_secs must be greater than heartbeat_interval_secs"
+                    .to_string(),
+            );
+        }
+    }
+}
+
+/// Header a trusted proxy records the client address in.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForwardedHeader {
+    /// `X-Forwarded-For`, as set by the bundled nginx configuration.
+    #[default]
+    XForwardedFor,
+    /// `X-Real-IP`, a single address.
+    XRealIp,
+    /// RFC 7239 `Forwarded`, using the `for` parameter of each element.
+    Forwarded,
+}
+
+/// How a full outbound queue is handled. Room and member lists are always
+/// coalesced, since only the latest one matters.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Drop the oldest join notices, member lists and keep-alives, and
+    /// disconnect only when nothing is left to drop.
+    #[default]
+    DropOldest,
+    /// Disconnect the client as soon as its queue is full.
+    Disconnect,
+}
+
+/// Token-bucket budget for one kind of WebSocket message. A message is
+/// accepted only when both the message and the byte bucket have room.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct MessageQuota {
+    /// Messages refilled per second.
+    pub messages_per_sec: u32,
+    /// Messages that can be sent at once after a quiet period.
+    pub message_burst: u32,
+    /// Bytes refilled per second.
+    pub bytes_per_sec: u64,
+    /// Bytes that can be sent at once after a quiet period.
+    pub byte_burst: u64,
+}
+
+impl MessageQuota {
+    pub const fn new(
+        messages_per_sec: u32,
+        message_burst: u32,
+        bytes_per_sec: u64,
+        byte_burst: u64,
+    ) -> Self {
+        MessageQuota {
+            messages_per_sec,
+            message_burst,
+            bytes_per_sec,
+            byte_burst,
+        }
+    }
+
+    // Helper function to collect budgets that would block every message
+    fn validate(&self, name: &str, largest_message: u64, errors: &mut Vec<String>) {
+        for (field, value) in [
+            ("messages_per_sec", self.messages_per_sec.into()),
+            ("message_burst", self.message_burst.into()),
+            ("bytes_per_sec", self.bytes_per_sec),
+            ("byte_burst", self.byte_burst),
+        ] {
+            if value == 0 {
+                errors.push(format!("limits.{name}.{field} must be greater than 0"));
+            }
+        }
+        if self.byte_burst < largest_message {
+            errors.push(format!(
+                "limits.{name}.byte_burst must be at least the largest allowed message ({largest_message} bytes)"
+            ));
+        }
+    }
+}
+
+/// Whether a route needs a verified TLS client certificate.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientCertPolicy {
+    /// Certificates are ignored.
+    Off,
+    /// A presented certificate is used as the caller's identity.
+    Optional,
+    /// Requests without a certificate are rejected.
+    Required,
+}
+
+/// Mutual TLS: client certificates verified against a CA bundle.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ClientAuthConfig {
+    pub enabled: bool,
+    /// PEM bundle of CAs trusted to sign client certificates.
+    pub ca_file: Option<String>,
+    pub admin: ClientCertPolicy,
+    pub websocket: ClientCertPolicy,
+    pub create_session: ClientCertPolicy,
+}
+
+impl Default for ClientAuthConfig {
+    fn default() -> Self {
+        ClientAuthConfig {
+            enabled: false,
+            ca_file: None,
+            admin: ClientCertPolicy::Required,
+            websocket: ClientCertPolicy::Optional,
+            create_session: ClientCertPolicy::Optional,
+        }
+    }
+}
+
+/// Verification of externally issued JWTs used to name WebSocket clients.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct JwtConfig {
+    pub enabled: bool,
+    /// Reject WebSocket connections that carry no token.
+    pub required: bool,
+    /// Signature algorithms accepted in token headers.
+    pub algorithms: Vec<Algorithm>,
+    /// Shared secret for HS256/HS384/HS512 tokens.
+    pub hmac_secret: Option<String>,
+    /// PEM public key for RS*/PS* tokens.
+    pub rsa_public_key_file: Option<String>,
+    /// PEM public key for EdDSA tokens.
+    pub ed25519_public_key_file: Option<String>,
+    /// Local JWKS document; keys are matched by the token's `kid`.
+    pub jwks_file: Option<String>,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    /// Claim used as the session's display name.
+    pub name_claim: String,
+    /// Claim used as the stable peer identity.
+    pub identity_claim: String,
+    /// Query parameter carrying the token for browser WebSocket clients.
+    pub query_param: String,
+    /// Allowed clock skew for `exp`/`nbf`, in seconds.
+    pub leeway_secs: u64,
+}
+
+impl Default for JwtConfig {
+    fn default() -> Self {
+        JwtConfig {
+            enabled: false,
+            required: false,
+            algorithms: vec![Algorithm::HS256, Algorithm::RS256, Algorithm::EdDSA],
+            hmac_secret: None,
+            rsa_public_key_file: None,
+            ed25519_public_key_file: None,
+            jwks_file: None,
+            issuer: None,
+            audience: None,
+            name_claim: "name".to_string(),
+            identity_claim: "sub".to_string(),
+            query_param: "jwt".to_string(),
+            leeway_secs: 30,
+        }
+    }
+}
+
+/// Optional API-key / bearer-token authentication for HTTP and WebSocket routes.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub enabled: bool,
+    /// Static keys, each with a name used as the caller's identity.
+    pub api_keys: Vec<ApiKey>,
+    /// Secret for HMAC-SHA256 signed tokens; tokens are rejected when unset.
+    pub token_secret: Option<String>,
+    /// Query parameter carrying the credential for clients that cannot set
+    /// the `Authorization` header (browser WebSockets).
+    pub query_param: String,
+    /// Grace period applied to token expiry.
+    pub clock_skew_secs: u64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            enabled: false,
+            api_keys: Vec::new(),
+            token_secret: None,
+            query_param: "token".to_string(),
+            clock_skew_secs: 30,
+        }
+    }
+}
+
+impl AuthConfig {
+    fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.api_keys.is_empty() && self.token_secret.is_none() {
+            return Err("auth is enabled but no api_keys or token_secret are configured".into());
+        }
+        if self.token_secret.as_ref().is_some_and(|s| s.len() < 32) {
+            return Err("auth.token_secret must be at least 32 bytes".into());
+        }
+        if let Some(key) = self.api_keys.iter().find(|k| k.key.len() < 16) {
+            return Err(format!(
+                "auth api key '{}' must be at least 16 bytes",
+                key.name
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Origin enforcement for WebSocket upgrades, which browsers never preflight.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct WebSocketOriginConfig {
+    /// Reject upgrades whose `Origin` does not match `cors_allowed_origins`.
+    pub enforce: bool,
+    /// Accept upgrades without an `Origin` header from any client.
+    pub allow_missing: bool,
+    /// Native clients allowed to connect without an `Origin` header.
+    pub native_user_agents: Vec<UaPattern>,
+}
+
+impl Default for WebSocketOriginConfig {
+    fn default() -> Self {
+        WebSocketOriginConfig {
+            enforce: true,
+            allow_missing: true,
+            native_user_agents: Vec::new(),
+        }
+    }
+}
+
+/// Rules screening WebSocket connection attempts.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ScreeningConfig {
+    /// User-Agents matching any of these patterns skip the User-Agent checks.
+    pub user_agent_allow: Vec<UaPattern>,
+    /// User-Agents matching any of these patterns are rejected.
+    pub user_agent_deny: Vec<UaPattern>,
+    /// Minimum length of a User-Agent header, when one is sent.
+    pub min_user_agent_length: usize,
+    /// Headers every connection attempt must carry.
+    pub required_headers: Vec<String>,
+    /// Keys that bypass every screening rule.
+    pub bypass_api_keys: Vec<String>,
+    /// Header carrying a bypass key.
+    pub api_key_header: String,
+}
+
+impl Default for ScreeningConfig {
+    fn default() -> Self {
+        ScreeningConfig {
+            user_agent_allow: Vec::new(),
+            user_agent_deny: vec![UaPattern(
+                Regex::new(DEFAULT_USER_AGENT_DENY).expect("Invalid default User-Agent pattern"),
+            )],
+            min_user_agent_length: MIN_USER_AGENT_LENGTH,
+            required_headers: Vec::new(),
+            bypass_api_keys: Vec::new(),
+            api_key_header: "X-Api-Key".to_string(),
+        }
+    }
+}
+
+/// CIDR allow/deny rules enforced before session and WebSocket routes.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct IpFilterConfig {
+    /// If non-empty, only these ranges may connect.
+    pub allow: Vec<IpNet>,
+    /// Ranges that are always rejected, even if allowed above.
+    pub deny: Vec<IpNet>,
+    /// Optional TOML file with additional `allow`/`deny` lists that can be
+    /// reloaded at runtime.
+    pub rules_file: Option<String>,
+    /// How often the rules file is checked for changes; 0 disables watching.
+    pub watch_interval_secs: u64,
+}
+
+impl Default for IpFilterConfig {
+    fn default() -> Self {
+        IpFilterConfig {
+            allow: Vec::new(),
+            deny: Vec::new(),
+            rules_file: None,
+            watch_interval_secs: 5,
+        }
+    }
+}
+
+impl IpFilterConfig {
+    pub fn watch_interval(&self) -> Option<Duration> {
+        (self.watch_interval_secs > 0).then(|| Duration::from_secs(self.watch_interval_secs))
+    }
+}
+
+/// How public sessions are keyed by client address.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupingStrategy {
+    /// Each distinct client IP gets its own public session.
+    #[default]
+    Exact,
+    /// Clients sharing an IPv4/IPv6 network prefix share a public session.
+    Prefix,
+}
+
+/// Explicit mapping of a network range to a named public session group.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SubnetGroup {
+    pub cidr: IpNet,
+    pub name: String,
+}
+
+/// Settings controlling which clients meet in the same public session.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SessionGroupingConfig {
+    pub strategy: GroupingStrategy,
+    /// Prefix length used for IPv4 clients with the `prefix` strategy.
+    pub ipv4_prefix: u8,
+    /// Prefix length used for IPv6 clients with the `prefix` strategy.
+    pub ipv6_prefix: u8,
+    /// Explicit CIDR-to-group mappings, checked before the strategy applies.
+    pub groups: Vec<SubnetGroup>,
+}
+
+impl Default for SessionGroupingConfig {
+    fn default() -> Self {
+        SessionGroupingConfig {
+            strategy: GroupingStrategy::Exact,
+            ipv4_prefix: 24,
+            ipv6_prefix: 64,
+            groups: Vec::new(),
+        }
+    }
+}
+
+/// Settings for advertising the server as a DNS-SD service over mDNS.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct MdnsConfig {
+    pub enabled: bool,
+    /// Human-readable service instance name shown by browsers.
+    pub instance_name: String,
+    /// Host label advertised under `.local`; defaults to the system hostname.
+    pub hostname: Option<String>,
+    /// Advertised service port; defaults to the port of `bind_address`.
+    pub port: Option<u16>,
+    /// WebSocket path advertised in the TXT record.
+    pub path: String,
+    /// Addresses published in A/AAAA records; detected automatically when empty.
+    pub addresses: Vec<IpAddr>,
+    /// TTL in seconds for the published records.
+    pub ttl: u32,
+    /// UDP port the responder listens on.
+    pub listen_port: u16,
+    /// IPv4 interface used to join the mDNS multicast group.
+    pub interface: Ipv4Addr,
+}
+
+impl Default for MdnsConfig {
+    fn default() -> Self {
+        MdnsConfig {
+            enabled: false,
+            instance_name: "PastePoint".to_string(),
+            hostname: None,
+            port: None,
+            path: "/ws".to_string(),
+            addresses: Vec::new(),
+            ttl: 120,
+            listen_port: MDNS_PORT,
+            interface: Ipv4Addr::UNSPECIFIED,
+        }
+    }
+}
+
+/// Where settings are read from. Later layers take precedence: the config
+/// file, then `PASTEPOINT_*` environment variables, then `overrides`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConfigSources {
+    /// Config file; defaults to `PASTEPOINT_CONFIG`, then `config/{RUN_ENV}`.
+    pub file: Option<String>,
+    /// `PASTEPOINT_*` environment variables, e.g. `PASTEPOINT_MDNS__ENABLED`.
+    pub env: Vec<(String, String)>,
+    /// `[server]` keys set on the command line, e.g. `mdns.enabled`.
+    pub overrides: Vec<(String, String)>,
+}
+
+impl ConfigSources {
+    /// The config file to load, without the `.toml` extension unless one was
+    /// given explicitly.
+    pub fn config_file(&self) -> String {
+        self.file
+            .clone()
+            .or_else(|| {
+                self.env
+                    .iter()
+                    .find(|(name, _)| name == CONFIG_FILE_ENV)
+                    .map(|(_, path)| path.clone())
+            })
+            .unwrap_or_else(|| {
+                let environment = env::var("RUN_ENV").unwrap_or_else(|_| "development".to_string());
+                format!("config/{environment}")
+            })
+    }
+
+    /// Sources with the `PASTEPOINT_*` variables of the current process.
+    pub fn from_env() -> Self {
+        let env = env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+            .filter(|(name, _)| name.starts_with(CONFIG_ENV_PREFIX))
+            .collect();
+        ConfigSources {
+            env,
+            ..ConfigSources::default()
+        }
+    }
+
+    /// Overrides a `[server]` key, taking precedence over earlier calls.
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.overrides.push((key.into(), value.into()));
+    }
+
+    // Helper function to list every override as (source, key, value), with
+    // environment variables mapped to dotted `[server]` keys
+    fn layered_overrides(&self) -> impl Iterator<Item = (String, String, &str)> {
+        let env = self.env.iter().filter_map(|(name, value)| {
+            let key = name.strip_prefix(CONFIG_ENV_PREFIX)?;
+            (name != CONFIG_FILE_ENV && !key.is_empty()).then(|| {
+                (
+                    name.clone(),
+                    key.to_lowercase().replace("__", "."),
+                    value.as_str(),
+                )
+            })
+        });
+        let cli = self
+            .overrides
+            .iter()
+            .map(|(key, value)| (format!("--set {key}"), key.clone(), value.as_str()));
+        env.chain(cli)
+    }
+}
+
+// Helper function to turn an override string into a config value. JSON
+// arrays and objects are parsed so list and table settings can be set too.
+fn override_value(raw: &str) -> Result<Value, String> {
+    if raw.trim_start().starts_with(['[', '{']) {
+        serde_json::from_str(raw).map_err(|e| format!("invalid JSON: {e}"))
+    } else {
+        Ok(Value::from(raw))
+    }
+}
+
+// Helper function to read the key an error refers to, up to the first list
+// index so the whole list is dropped
+fn error_key(error: &ConfigError) -> Option<String> {
+    let key = match error {
+        ConfigError::Type { key, .. } | ConfigError::At { key, .. } => key.clone()?,
+        ConfigError::NotFound(key) => key.clone(),
+        _ => return None,
+    };
+    Some(key.split('[').next().unwrap_or_default().to_string())
+}
+
+// Helper function to remove a dotted key from a table
+fn remove_key(table: &mut Map<String, Value>, key: &str) -> bool {
+    match key.split_once('.') {
+        None => table.remove(key).is_some(),
+        Some((head, rest)) => match table.get_mut(head).map(|value| &mut value.kind) {
+            Some(ValueKind::Table(inner)) => remove_key(inner, rest),
+            _ => false,
+        },
+    }
+}
+
+// Helper function to deserialize the `[server]` table, dropping each field
+// that fails so every invalid field is reported, not just the first one
+fn deserialize_server(
+    mut table: Map<String, Value>,
+    errors: &mut Vec<String>,
+) -> Option<ServerConfig> {
+    let mut removed = Vec::new();
+    loop {
+        match Value::new(None, table.clone()).try_deserialize::<ServerConfig>() {
+            Ok(config) => return Some(config),
+            // A required field was dropped after an earlier error.
+            Err(ConfigError::NotFound(key)) if removed.contains(&key) => return None,
+            Err(e) => {
+                errors.push(e.to_string());
+                let key = error_key(&e)?;
+                if !remove_key(&mut table, &key) {
+                    return None;
+                }
+                removed.push(key);
+            }
+        }
+    }
+}
+
+// Helper function to check that an address can be bound
+fn check_address(field: &str, address: &str, errors: &mut Vec<String>) {
+    if address.to_socket_addrs().is_err() {
+        errors.push(format!("{field} '{address}' is not a valid socket address"));
+    }
 }
 
 impl ServerConfig {
     pub fn load(auto_join_override: Option<bool>) -> Result<Self, ConfigError> {
-        let environment = env::var("RUN_ENV").unwrap_or_else(|_| "development".to_string());
-        log::debug!(
-            target: "Websocket",
-            "Loading configuration for environment: {environment}"
-        );
+        let mut sources = ConfigSources::from_env();
+        if let Some(auto_join) = auto_join_override {
+            sources.set("auto_join", auto_join.to_string());
+        }
+        Self::load_from(&sources)
+    }
 
-        let mut builder = Config::builder()
-            .add_source(File::with_name(&format!("config/{environment}")).required(true));
+    /// Loads and validates the configuration, reporting every invalid field
+    /// in a single error.
+    pub fn load_from(sources: &ConfigSources) -> Result<Self, ConfigError> {
+        let file = sources.config_file();
+        log::debug!(target: "Websocket", "Loading configuration from {file}");
 
-        if let Some(auto_join) = auto_join_override {
-            builder = builder.set_override("server.auto_join", auto_join)?;
+        let mut errors = Vec::new();
+        let mut builder = Config::builder().add_source(File::with_name(&file).required(true));
+        for (source, key, raw) in sources.layered_overrides() {
+            match override_value(raw) {
+                Ok(value) => builder = builder.set_override(format!("server.{key}"), value)?,
+                Err(e) => errors.push(format!("{source}: {e}")),
+            }
         }
 
         let settings = builder.build()?;
-        settings.get::<ServerConfig>("server")
+        let config = deserialize_server(settings.get_table("server")?, &mut errors);
+        if let Some(config) = &config {
+            errors.extend(config.validate());
+        }
+
+        match config {
+            Some(config) if errors.is_empty() => Ok(config),
+            _ => {
+                errors.sort();
+                errors.dedup();
+                Err(ConfigError::Message(format!(
+                    "invalid configuration:\n  - {}",
+                    errors.join("\n  - ")
+                )))
+            }
+        }
     }
 
     pub fn is_dev_env() -> bool {
@@ -50,25 +860,108 @@ impl ServerConfig {
         environment == "development" || environment == "docker-dev"
     }
 
-    pub fn check_origin(&self, origin: &HeaderValue) -> bool {
-        fn extract_host(input: &str) -> Option<String> {
-            Url::parse(input)
-                .or_else(|_| Url::parse(&format!("https://{input}")))
-                .ok()
-                .and_then(|u| u.host_str().map(|s| s.to_ascii_lowercase()))
-        }
-
-        if let Ok(origin_str) = origin.to_str() {
-            if let (Some(origin_host), Some(allowed_host)) = (
-                extract_host(origin_str),
-                extract_host(&self.cors_allowed_origins),
-            ) {
-                origin_host == allowed_host || origin_host.ends_with(&format!(".{allowed_host}"))
-            } else {
-                false
-            }
+    pub fn cert_watch_interval(&self) -> Option<Duration> {
+        (self.cert_watch_interval_secs > 0)
+            .then(|| Duration::from_secs(self.cert_watch_interval_secs))
+    }
+
+    pub fn config_watch_interval(&self) -> Option<Duration> {
+        (self.config_watch_interval_secs > 0)
+            .then(|| Duration::from_secs(self.config_watch_interval_secs))
+    }
+
+    /// The configured listeners, or a TLS listener on `bind_address`.
+    pub fn listeners(&self) -> Vec<ListenerConfig> {
+        if self.listeners.is_empty() {
+            vec![ListenerConfig::Tls {
+                address: self.bind_address.clone(),
+            }]
         } else {
-            false
+            self.listeners.clone()
         }
     }
+
+    /// Whether any listener terminates TLS itself.
+    pub fn uses_tls(&self) -> bool {
+        self.listeners()
+            .iter()
+            .any(|listener| matches!(listener, ListenerConfig::Tls { .. }))
+    }
+
+    /// Port of the first TLS listener, used for redirects.
+    pub fn https_port(&self) -> Option<u16> {
+        self.listeners().iter().find_map(|listener| match listener {
+            ListenerConfig::Tls { address } => address.parse::<SocketAddr>().ok().map(|a| a.port()),
+            _ => None,
+        })
+    }
+
+    // Helper function to collect every setting that parses but cannot be used
+    fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        check_address("bind_address", &self.bind_address, &mut errors);
+        for listener in &self.listeners {
+            match listener {
+                ListenerConfig::Tls { address } | ListenerConfig::Tcp { address } => {
+                    check_address("listener address", address, &mut errors)
+                }
+                ListenerConfig::Unix { path, .. } if path.is_empty() => {
+                    errors.push("unix listener path must not be empty".to_string())
+                }
+                ListenerConfig::Unix { .. } => {}
+            }
+        }
+
+        if self.uses_tls() {
+            if self.key_file_path.is_empty() {
+                errors.push("key_file_path is required for tls listeners".to_string());
+            }
+            if self.cert_file_path.is_empty() {
+                errors.push("cert_file_path is required for tls listeners".to_string());
+            }
+        }
+        if self.https_redirect.enabled {
+            check_address(
+                "https_redirect.bind_address",
+                &self.https_redirect.bind_address,
+                &mut errors,
+            );
+            if self.https_redirect.https_port.is_none() && self.https_port().is_none() {
+                errors.push("https_redirect needs https_port or a tls listener".to_string());
+            }
+        }
+
+        if self.rate_limit_per_second == 0 {
+            errors.push("rate_limit_per_second must be greater than 0".to_string());
+        }
+        if self.rate_limit_burst_size == 0 {
+            errors.push("rate_limit_burst_size must be greater than 0".to_string());
+        }
+        if let Some(url) = &self.public_base_url
+            && !Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+        {
+            errors.push(format!("public_base_url '{url}' is not an http(s) URL"));
+        }
+        if self.client_auth.enabled && self.client_auth.ca_file.is_none() {
+            errors.push("client_auth is enabled but ca_file is not set".to_string());
+        }
+        if let Err(e) = self.auth.validate() {
+            errors.push(e);
+        }
+        self.limits.validate(&mut errors);
+        errors
+    }
+
+    /// Parses `bind_address` into a socket address.
+    pub fn bind_socket_addr(&self) -> Option<SocketAddr> {
+        self.bind_address.parse().ok()
+    }
+
+    pub fn check_origin(&self, origin: &HeaderValue) -> bool {
+        origin.to_str().is_ok_and(|origin| {
+            self.cors_allowed_origins
+                .iter()
+                .any(|allowed| allowed.matches(origin))
+        })
+    }
 }
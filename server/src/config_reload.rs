@@ -0,0 +1,238 @@
+use crate::{
+    ConfigSources, ServerConfig, SessionStore, WsChatServer, logging::set_log_filter,
+    message::UpdateLimits, metrics::metrics, rate_limit::RateLimiter, watch::watch_files,
+};
+use actix::SystemService;
+use actix_http::header::HeaderValue;
+use actix_rt::task::JoinHandle;
+use serde::Serialize;
+use std::{
+    io,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+pub const CONFIG_RELOAD_METRIC: &str = "pastepoint_config_reloads_total";
+
+/// What a configuration reload changed.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ReloadReport {
+    /// Settings that changed and are now in effect.
+    pub applied: Vec<String>,
+    /// Settings that changed in the file but only take effect after a restart.
+    pub restart_required: Vec<String>,
+    /// Why the reload was rejected; the previous configuration stays active.
+    pub error: Option<String>,
+}
+
+// Lists the fields whose values differ, comparing their debug output.
+macro_rules! changed_fields {
+    ($old:expr, $new:expr, [$($field:ident),* $(,)?]) => {
+        [$((stringify!($field), format!("{:?}", $old.$field) != format!("{:?}", $new.$field))),*]
+            .into_iter()
+            .filter_map(|(field, changed)| changed.then(|| field.to_string()))
+            .collect::<Vec<_>>()
+    };
+}
+
+/// Re-reads the configuration on demand and applies the settings that can
+/// change without dropping connections: the log filter, HTTP rate limits,
+/// allowed origins and `limits`. Everything else is reported as needing a
+/// restart and keeps its startup value.
+#[derive(Clone)]
+pub struct ConfigReloader {
+    sources: ConfigSources,
+    current: Arc<RwLock<Arc<ServerConfig>>>,
+    rate_limiter: RateLimiter,
+    store: SessionStore,
+    last_report: Arc<Mutex<Option<ReloadReport>>>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        sources: ConfigSources,
+        config: ServerConfig,
+        rate_limiter: RateLimiter,
+        store: SessionStore,
+    ) -> Self {
+        ConfigReloader {
+            sources,
+            current: Arc::new(RwLock::new(Arc::new(config))),
+            rate_limiter,
+            store,
+            last_report: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Returns the configuration currently in effect.
+    pub fn current(&self) -> Arc<ServerConfig> {
+        match self.current.read() {
+            Ok(config) => config.clone(),
+            Err(e) => {
+                log::error!(target: "Websocket", "Failed to acquire lock on configuration: {e:?}");
+                e.into_inner().clone()
+            }
+        }
+    }
+
+    /// Checks `origin` against the currently allowed origins.
+    pub fn check_origin(&self, origin: &HeaderValue) -> bool {
+        self.current().check_origin(origin)
+    }
+
+    /// Returns the outcome of the most recent reload, if any.
+    pub fn last_report(&self) -> Option<ReloadReport> {
+        self.last_report
+            .lock()
+            .ok()
+            .and_then(|report| report.clone())
+    }
+
+    /// Loads the configuration again and applies the safe settings. An
+    /// invalid configuration is rejected as a whole and the previous one
+    /// stays in effect.
+    pub fn reload(&self) -> Result<ReloadReport, String> {
+        let result = ServerConfig::load_from(&self.sources)
+            .map_err(|e| format!("Failed to reload configuration: {e}"))
+            .and_then(|config| self.apply(config));
+
+        let outcome = if result.is_ok() { "success" } else { "failure" };
+        metrics().increment(CONFIG_RELOAD_METRIC, &[("result", outcome)]);
+
+        let report = match &result {
+            Ok(report) => report.clone(),
+            Err(e) => {
+                log::error!(target: "Websocket", "{e}, keeping previous configuration");
+                ReloadReport {
+                    error: Some(e.clone()),
+                    ..ReloadReport::default()
+                }
+            }
+        };
+        if let Ok(mut last) = self.last_report.lock() {
+            *last = Some(report);
+        }
+        result
+    }
+
+    fn apply(&self, new: ServerConfig) -> Result<ReloadReport, String> {
+        let old = self.current();
+        let applied = changed_fields!(
+            old,
+            new,
+            [
+                log_level,
+                rate_limit_per_second,
+                rate_limit_burst_size,
+                cors_allowed_origins,
+                limits,
+            ]
+        );
+        let restart_required = changed_fields!(
+            old,
+            new,
+            [
+                bind_address,
+                key_file_path,
+                cert_file_path,
+                cert_watch_interval_secs,
+                config_watch_interval_secs,
+                auto_join,
+                public_base_url,
+                trusted_proxies,
+                forwarded_header,
+                listeners,
+                https_redirect,
+                mdns,
+                session_grouping,
+                ip_filter,
+                screening,
+                websocket_origin,
+                auth,
+                jwt,
+                client_auth,
+            ]
+        );
+
+        if old.rate_limit_per_second != new.rate_limit_per_second
+            || old.rate_limit_burst_size != new.rate_limit_burst_size
+        {
+            self.rate_limiter
+                .update(new.rate_limit_per_second, new.rate_limit_burst_size)?;
+        }
+        if old.log_level != new.log_level {
+            set_log_filter(&new.log_level);
+        }
+        if old.limits != new.limits {
+            self.store.set_limits(new.limits);
+            WsChatServer::from_registry().do_send(UpdateLimits(new.limits));
+        }
+
+        let merged = ServerConfig {
+            log_level: new.log_level,
+            rate_limit_per_second: new.rate_limit_per_second,
+            rate_limit_burst_size: new.rate_limit_burst_size,
+            cors_allowed_origins: new.cors_allowed_origins,
+            limits: new.limits,
+            ..(*old).clone()
+        };
+        let mut current = self
+            .current
+            .write()
+            .map_err(|e| format!("Failed to acquire lock on configuration: {e:?}"))?;
+        *current = Arc::new(merged);
+
+        if applied.is_empty() {
+            log::info!(target: "Websocket", "Configuration reloaded, no live settings changed");
+        } else {
+            log::info!(target: "Websocket", "Configuration reloaded, applied: {}", applied.join(", "));
+        }
+        if !restart_required.is_empty() {
+            log::warn!(
+                target: "Websocket",
+                "Configuration changes need a restart to take effect: {}",
+                restart_required.join(", ")
+            );
+        }
+        Ok(ReloadReport {
+            applied,
+            restart_required,
+            error: None,
+        })
+    }
+
+    /// Reloads the configuration whenever the config file changes.
+    pub fn watch(&self, interval: Duration) -> JoinHandle<()> {
+        let reloader = self.clone();
+        watch_files(vec![self.config_path()], interval, move || {
+            let _ = reloader.reload();
+        })
+    }
+
+    /// Reloads the configuration whenever the process receives SIGHUP.
+    #[cfg(unix)]
+    pub fn reload_on_hangup(&self) -> io::Result<JoinHandle<()>> {
+        use actix_rt::signal::unix::{SignalKind, signal};
+
+        let mut hangup = signal(SignalKind::hangup())?;
+        let reloader = self.clone();
+        Ok(actix_rt::spawn(async move {
+            while hangup.recv().await.is_some() {
+                log::info!(target: "Websocket", "Received SIGHUP, reloading configuration");
+                let _ = reloader.reload();
+            }
+        }))
+    }
+
+    // Helper function to resolve the config file on disk, which `config`
+    // looks up with a `.toml` extension when none is given.
+    fn config_path(&self) -> PathBuf {
+        let file = PathBuf::from(self.sources.config_file());
+        if file.extension().is_some() {
+            file
+        } else {
+            file.with_extension("toml")
+        }
+    }
+}
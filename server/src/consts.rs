@@ -1,37 +1,97 @@
-use std::time::Duration;
+use crate::config::MessageQuota;
+use std::{net::Ipv4Addr, time::Duration};
 
-// WebSocket frame and message size limits
+// Default WebSocket frame and message size limits, see `LimitsConfig`
 pub const MAX_FRAME_SIZE: usize = 64 * 1024;
 pub const MAX_SIGNAL_SIZE: usize = 128 * 1024;
 
-// Security limits
+// Default security limits, see `LimitsConfig`
 pub const MAX_ROOMS_PER_SESSION: usize = 50;
 pub const MAX_SESSIONS: usize = 100_000;
-pub const MAX_WS_MESSAGES_PER_SEC: usize = 30;
+pub const MAX_CONNECTIONS_PER_IP: usize = 50;
+pub const MAX_CONNECTIONS_PER_SESSION: usize = 100;
+pub const MAX_CLIENTS_PER_ROOM: usize = 100;
+
+// Default per-session WebSocket budgets, see `MessageQuota`
+pub const COMMAND_QUOTA: MessageQuota = MessageQuota::new(10, 20, 16 * 1024, 64 * 1024);
+pub const SIGNAL_QUOTA: MessageQuota = MessageQuota::new(50, 100, 256 * 1024, 512 * 1024);
+pub const BINARY_QUOTA: MessageQuota = MessageQuota::new(30, 60, 1024 * 1024, 2 * 1024 * 1024);
+pub const ACTIVITY_QUOTA: MessageQuota = MessageQuota::new(2, 5, 16 * 1024, 64 * 1024);
+pub const MAX_THROTTLED_MESSAGES: u32 = 50;
+pub const ABUSE_WINDOW: Duration = Duration::from_secs(10);
+
+// Default per-session outbound queue limits, see `Outbox`
+pub const MAX_OUTBOUND_MESSAGES: usize = 256;
+pub const MAX_OUTBOUND_BYTES: usize = 1024 * 1024;
+
+// Configuration sources
+pub const CONFIG_ENV_PREFIX: &str = "PASTEPOINT_";
+pub const CONFIG_FILE_ENV: &str = "PASTEPOINT_CONFIG";
 
 // Timing intervals
 pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(3600);
 pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
 pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(90);
+pub const IDLE_THRESHOLD: Duration = Duration::from_secs(300);
 pub const SESSION_EXPIRATION_TIME: Duration = Duration::from_secs(60);
+pub const BROADCAST_DEBOUNCE: Duration = Duration::from_millis(50);
 pub const CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
 
 // Session configuration
 pub const SESSION_CODE_LENGTH: usize = 10;
+pub const MAX_DISPLAY_NAME_LENGTH: usize = 64;
+pub const MAX_DEVICE_FIELD_LENGTH: usize = 64;
+
+// Avatars
+pub const AVATAR_COLORS: &[&str] = &[
+    "#E57373", "#F06292", "#BA68C8", "#9575CD", "#7986CB", "#64B5F6", "#4DB6AC", "#81C784",
+    "#DCE775", "#FFD54F", "#FFB74D", "#A1887F",
+];
+pub const AVATAR_EMOJI: &[&str] = &[
+    "🦊", "🐼", "🐨", "🦁", "🐯", "🐸", "🐙", "🦉", "🐧", "🐢", "🦋", "🐝", "🐳", "🦄", "🐞", "🦔",
+];
+
+// TLS configuration
+pub const TLS_SESSION_CACHE_SIZE: usize = 1024;
 pub const SAFE_CHARSET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz23456789";
 
 // HTTP configuration
 pub const CORS_MAX_AGE: usize = 3600;
 pub const CONTENT_TYPE_TEXT_PLAIN: &str = "text/plain; charset=utf-8";
 pub const MIN_USER_AGENT_LENGTH: usize = 5;
+pub const DEFAULT_USER_AGENT_DENY: &str = "(?i)bot";
+pub const CONTENT_TYPE_SVG: &str = "image/svg+xml";
+pub const CONTENT_TYPE_PNG: &str = "image/png";
+pub const CONTENT_TYPE_PROMETHEUS: &str = "text/plain; version=0.0.4; charset=utf-8";
+pub const ORIGIN_REJECTION_METRIC: &str = "pastepoint_ws_origin_rejections_total";
+pub const WS_THROTTLE_METRIC: &str = "pastepoint_ws_throttled_messages_total";
+pub const WS_CONNECTION_LIMIT_METRIC: &str = "pastepoint_ws_connection_limit_rejections_total";
+pub const WS_SLOW_CONSUMER_METRIC: &str = "pastepoint_ws_slow_consumer_messages_total";
+
+// QR code rendering limits (pixels)
+pub const QR_DEFAULT_SIZE: u32 = 256;
+pub const QR_MIN_SIZE: u32 = 64;
+pub const QR_MAX_SIZE: u32 = 2048;
+
+// mDNS / DNS-SD advertisement
+pub const MDNS_PORT: u16 = 5353;
+pub const MDNS_MULTICAST_V4: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+pub const MDNS_SERVICE_TYPE: &str = "_pastepoint._tcp.local";
+pub const MDNS_SERVICES_META_QUERY: &str = "_services._dns-sd._udp.local";
+pub const MDNS_RECV_RETRY_DELAY: Duration = Duration::from_millis(100);
+pub const MDNS_MAX_RECV_FAILURES: u32 = 10;
 
 // WebSocket message prefixes
 pub const WS_PREFIX_KEEP_ALIVE: &str = "[KeepAlive]";
 pub const WS_PREFIX_SYSTEM_ERROR: &str = "[SystemError]";
+pub const WS_PREFIX_SYSTEM_WARNING: &str = "[SystemWarning]";
 pub const WS_PREFIX_SYSTEM_ROOMS: &str = "[SystemRooms]";
 pub const WS_PREFIX_SYSTEM_NAME: &str = "[SystemName]";
 pub const WS_PREFIX_SYSTEM_JOIN: &str = "[SystemJoin]";
 pub const WS_PREFIX_SYSTEM_MEMBERS: &str = "[SystemMembers]";
+pub const WS_PREFIX_SYSTEM_ROOMS_DELTA: &str = "[SystemRoomsDelta]";
+pub const WS_PREFIX_SYSTEM_MEMBERS_DELTA: &str = "[SystemMembersDelta]";
+pub const WS_PREFIX_SYSTEM_ACTIVITY: &str = "[SystemActivity]";
 pub const WS_PREFIX_SIGNAL_MESSAGE: &str = "[SignalMessage]";
 pub const WS_PREFIX_USER_COMMAND: &str = "[UserCommand]";
 pub const WS_PREFIX_USER_DISCONNECTED: &str = "[UserDisconnected]";
@@ -0,0 +1,111 @@
+use crate::consts::MAX_DEVICE_FIELD_LENGTH;
+use serde::{Deserialize, Serialize};
+
+/// Kind of device a client runs on.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceType {
+    Desktop,
+    Mobile,
+    Tablet,
+    #[default]
+    Unknown,
+}
+
+/// Device details shown next to a member, so people can tell their phone
+/// from their laptop.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(default)]
+pub struct DeviceInfo {
+    #[serde(rename = "type")]
+    pub device_type: DeviceType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_version: Option<String>,
+}
+
+impl DeviceInfo {
+    /// Best guess from a `User-Agent` header. Native clients identify
+    /// themselves as `PastePoint/<version>`.
+    pub fn from_user_agent(user_agent: &str) -> Self {
+        let os = if user_agent.contains("iPhone") || user_agent.contains("iPad") {
+            Some(match version_after(user_agent, "OS ") {
+                Some(version) => format!("iOS {}", version.replace('_', ".")),
+                None => "iOS".to_string(),
+            })
+        } else if user_agent.contains("Android") {
+            Some(match version_after(user_agent, "Android ") {
+                Some(version) => format!("Android {version}"),
+                None => "Android".to_string(),
+            })
+        } else if user_agent.contains("Windows") {
+            Some("Windows".to_string())
+        } else if user_agent.contains("Macintosh") || user_agent.contains("Mac OS X") {
+            Some("macOS".to_string())
+        } else if user_agent.contains("CrOS") {
+            Some("ChromeOS".to_string())
+        } else if user_agent.contains("Linux") {
+            Some("Linux".to_string())
+        } else {
+            None
+        };
+
+        let device_type = if user_agent.contains("iPad") || user_agent.contains("Tablet") {
+            DeviceType::Tablet
+        } else if user_agent.contains("Mobile") || user_agent.contains("iPhone") {
+            DeviceType::Mobile
+        } else if user_agent.contains("Android") {
+            // Android tablets leave "Mobile" out.
+            DeviceType::Tablet
+        } else if os.is_some() {
+            DeviceType::Desktop
+        } else {
+            DeviceType::Unknown
+        };
+
+        DeviceInfo {
+            device_type,
+            os,
+            client_version: version_after(user_agent, "PastePoint/").map(str::to_string),
+        }
+    }
+
+    /// Applies details a client registered itself, keeping anything it
+    /// left out.
+    pub fn register(&mut self, registered: DeviceInfo) {
+        if registered.device_type != DeviceType::Unknown {
+            self.device_type = registered.device_type;
+        }
+        if let Some(os) = registered.os.as_deref().and_then(sanitize) {
+            self.os = Some(os);
+        }
+        if let Some(version) = registered.client_version.as_deref().and_then(sanitize) {
+            self.client_version = Some(version);
+        }
+    }
+}
+
+// Helper function to read the version that follows `marker`, e.g. `14` in
+// `Android 14;`
+fn version_after<'a>(user_agent: &'a str, marker: &str) -> Option<&'a str> {
+    let start = user_agent.find(marker)? + marker.len();
+    let rest = &user_agent[start..];
+    let end = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-'))
+        .unwrap_or(rest.len());
+    Some(&rest[..end])
+        .filter(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+        .map(|version| &version[..version.len().min(MAX_DEVICE_FIELD_LENGTH)])
+}
+
+// Helper function to strip control characters and cap the length of
+// client-supplied fields
+fn sanitize(value: &str) -> Option<String> {
+    let value = value
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_DEVICE_FIELD_LENGTH)
+        .collect::<String>();
+    Some(value.trim().to_string()).filter(|value| !value.is_empty())
+}
@@ -1,5 +1,5 @@
 use crate::CONTENT_TYPE_TEXT_PLAIN;
-use actix_web::{HttpResponse, ResponseError};
+use actix_web::{HttpResponse, ResponseError, http::header};
 use derive_more::{Display, From};
 
 #[derive(Debug, Display, From)]
@@ -10,6 +10,8 @@ pub enum ServerError {
     NotFound,
     #[display("Bad Request: {}", _0)]
     BadRequest(String),
+    #[display("Unauthorized")]
+    Unauthorized,
     #[display("Forbidden")]
     Forbidden,
     #[display("Index out of bounds")]
@@ -36,6 +38,10 @@ impl ResponseError for ServerError {
             ServerError::BadRequest(ref message) => HttpResponse::BadRequest()
                 .content_type(CONTENT_TYPE_TEXT_PLAIN)
                 .body(message.clone()),
+            ServerError::Unauthorized => HttpResponse::Unauthorized()
+                .content_type(CONTENT_TYPE_TEXT_PLAIN)
+                .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+                .body("Unauthorized"),
             ServerError::Forbidden => HttpResponse::Forbidden()
                 .content_type(CONTENT_TYPE_TEXT_PLAIN)
                 .body("Forbidden"),
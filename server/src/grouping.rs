@@ -0,0 +1,42 @@
+use crate::config::{GroupingStrategy, SessionGroupingConfig};
+use ipnet::IpNet;
+use std::net::IpAddr;
+
+impl SessionGroupingConfig {
+    /// Returns the group identifier used to key the public session of a client.
+    ///
+    /// Explicit CIDR mappings win over the configured strategy; when several
+    /// mappings match, the most specific network is used.
+    pub fn group_for(&self, ip: IpAddr) -> String {
+        let ip = ip.to_canonical();
+
+        if let Some(group) = self
+            .groups
+            .iter()
+            .filter(|group| group.cidr.contains(&ip))
+            .max_by_key(|group| group.cidr.prefix_len())
+        {
+            return format!("group:{}", group.name);
+        }
+
+        match self.strategy {
+            GroupingStrategy::Exact => ip.to_string(),
+            GroupingStrategy::Prefix => {
+                let prefix = match ip {
+                    IpAddr::V4(_) => self.ipv4_prefix,
+                    IpAddr::V6(_) => self.ipv6_prefix,
+                };
+                match IpNet::new(ip, prefix) {
+                    Ok(net) => net.trunc().to_string(),
+                    Err(_) => {
+                        log::warn!(
+                            target: "Websocket",
+                            "Invalid grouping prefix /{prefix} for {ip}, falling back to exact IP"
+                        );
+                        ip.to_string()
+                    }
+                }
+            }
+        }
+    }
+}
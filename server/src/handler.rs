@@ -1,36 +1,76 @@
 use actix::{Handler, MessageResult};
 
 use crate::{
-    ChatMessage, JoinRoom, LeaveRoom, ListRooms, WS_PREFIX_SIGNAL_MESSAGE, WS_PREFIX_SYSTEM_ERROR,
-    WS_PREFIX_SYSTEM_JOIN, WsChatServer, WsChatSession,
-    message::{CleanupSession, RelaySignalMessage, ValidateAndRelaySignal},
+    ChatMessage, ClientMetadata, ConnectionLimit, JoinRoom, LeaveRoom, ListRooms,
+    WS_PREFIX_SIGNAL_MESSAGE, WS_PREFIX_SYSTEM_JOIN, WsChatServer, WsChatSession,
+    message::{
+        CleanupSession, RelayActivity, RelaySignalMessage, SetDevice, SetPresence, Snapshot,
+        UpdateLimits, ValidateAndRelaySignal,
+    },
 };
 
 impl Handler<JoinRoom> for WsChatServer {
     type Result = MessageResult<JoinRoom>;
 
-    fn handle(&mut self, msg: JoinRoom, _ctx: &mut Self::Context) -> Self::Result {
-        let JoinRoom(session_id, room_name, client_name, client) = msg;
-
-        match self.add_client_to_room(
-            &session_id,
-            &room_name,
-            None,
-            client.clone(),
-            client_name.clone(),
-        ) {
+    fn handle(&mut self, msg: JoinRoom, ctx: &mut Self::Context) -> Self::Result {
+        let JoinRoom(session_id, room_name, client_name, client, outbox, presence, device, avatar) =
+            msg;
+
+        let room_full = self.is_room_full(&session_id, &room_name);
+        let client_name = self.unique_name(&session_id, &client_name);
+        let mut member = ClientMetadata::new(client, client_name.clone(), outbox);
+        member.presence = presence;
+        member.device = device;
+        member.avatar = avatar;
+        match self.add_member(&session_id, &room_name, None, member) {
             Some(id) => {
                 let join_msg = format!("{client_name} {WS_PREFIX_SYSTEM_JOIN} {room_name}");
                 self.send_join_message(&session_id, &room_name, &join_msg, id);
-                self.broadcast_room_members(&session_id, &room_name);
-                MessageResult(id)
+                self.send_snapshot(&session_id, &room_name, id);
+                self.schedule_publish(ctx);
+                MessageResult(Ok((id, client_name)))
             }
-            None => {
-                let _ = client.try_send(ChatMessage(format!(
-                    "{WS_PREFIX_SYSTEM_ERROR} Room limit or session limit reached"
-                )));
-                MessageResult(0)
+            None if room_full => {
+                let limit = ConnectionLimit::PerRoom(self.limits.max_clients_per_room);
+                MessageResult(Err(limit.close_reason()))
             }
+            None => MessageResult(Err("Room limit or session limit reached".to_string())),
+        }
+    }
+}
+
+impl Handler<SetPresence> for WsChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetPresence, ctx: &mut Self::Context) {
+        let SetPresence(session_id, room_name, id, presence) = msg;
+        if self.set_presence(&session_id, &room_name, id, presence) {
+            self.schedule_publish(ctx);
+        }
+    }
+}
+
+impl Handler<SetDevice> for WsChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetDevice, ctx: &mut Self::Context) {
+        let SetDevice(session_id, room_name, id, device) = msg;
+        if self.set_device(&session_id, &room_name, id, device) {
+            self.schedule_publish(ctx);
+        }
+    }
+}
+
+impl Handler<RelayActivity> for WsChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: RelayActivity, _ctx: &mut Self::Context) {
+        let RelayActivity(session_id, room_name, id, activity) = msg;
+        if !self.relay_activity(&session_id, &room_name, id, activity) {
+            log::debug!(
+                target: "Websocket",
+                "Dropping activity from client {id}, not in room {room_name}"
+            );
         }
     }
 }
@@ -38,7 +78,7 @@ impl Handler<JoinRoom> for WsChatServer {
 impl Handler<LeaveRoom> for WsChatServer {
     type Result = ();
 
-    fn handle(&mut self, msg: LeaveRoom, _ctx: &mut Self::Context) {
+    fn handle(&mut self, msg: LeaveRoom, ctx: &mut Self::Context) {
         if let Some(rooms) = self.rooms.get_mut(&msg.0)
             && let Some(room) = rooms.get_mut(&msg.1)
         {
@@ -54,8 +94,8 @@ impl Handler<LeaveRoom> for WsChatServer {
                 );
             }
 
-            self.broadcast_room_list(&msg.0);
-            self.broadcast_room_members(&msg.0, &msg.1);
+            self.mark_changed(&msg.0);
+            self.schedule_publish(ctx);
 
             log::debug!(
                 target: "Websocket",
@@ -86,11 +126,21 @@ impl Handler<ListRooms> for WsChatServer {
     }
 }
 
+impl Handler<Snapshot> for WsChatServer {
+    type Result = MessageResult<Snapshot>;
+
+    fn handle(&mut self, msg: Snapshot, _ctx: &mut Self::Context) -> Self::Result {
+        let Snapshot(session_id, room_name) = msg;
+        MessageResult(self.snapshot(&session_id, room_name.as_deref()))
+    }
+}
+
 impl Handler<ChatMessage> for WsChatSession {
     type Result = ();
 
     fn handle(&mut self, msg: ChatMessage, ctx: &mut Self::Context) {
         ctx.text(msg.0);
+        self.flush_outbox(ctx);
     }
 }
 
@@ -125,6 +175,17 @@ impl Handler<CleanupSession> for WsChatServer {
             log::debug!(target: "Websocket","Removing client {} from rooms", msg.0);
             self.rooms.remove(&msg.0);
         }
+        self.rosters.remove(&msg.0);
+        self.changed.remove(&msg.0);
+    }
+}
+
+impl Handler<UpdateLimits> for WsChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: UpdateLimits, _ctx: &mut Self::Context) -> Self::Result {
+        log::debug!(target: "Websocket", "Room limits updated: {:?}", msg.0);
+        self.limits = msg.0;
     }
 }
 
@@ -0,0 +1,151 @@
+use crate::{
+    ServerConfig, ServerError, client_ip::resolve_client_ip, config::IpFilterConfig,
+    listener::peer_ip, watch::watch_files,
+};
+use actix_rt::task::JoinHandle;
+use actix_web::{
+    Error, ResponseError,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web::Data,
+};
+use config::{Config, File, FileFormat};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use std::{
+    net::IpAddr,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+/// A set of CIDR allow/deny rules.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct IpRules {
+    pub allow: Vec<IpNet>,
+    pub deny: Vec<IpNet>,
+}
+
+impl IpRules {
+    /// Deny rules always win; a non-empty allow list admits only its ranges.
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        if self.deny.iter().any(|net| net.contains(&ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|net| net.contains(&ip))
+    }
+}
+
+/// Live IP allow/deny filter shared by all workers.
+///
+/// The effective rules are the ones from `ServerConfig` plus those read from
+/// the optional rules file, which can be reloaded without a restart.
+#[derive(Clone)]
+pub struct IpFilter {
+    base: IpRules,
+    rules_file: Option<PathBuf>,
+    rules: Arc<RwLock<IpRules>>,
+}
+
+impl IpFilter {
+    pub fn from_config(config: &IpFilterConfig) -> Result<Self, String> {
+        let filter = IpFilter {
+            base: IpRules {
+                allow: config.allow.clone(),
+                deny: config.deny.clone(),
+            },
+            rules_file: config.rules_file.as_ref().map(PathBuf::from),
+            rules: Arc::new(RwLock::new(IpRules::default())),
+        };
+        filter.reload()?;
+        Ok(filter)
+    }
+
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        match self.rules.read() {
+            Ok(rules) => rules.is_allowed(ip),
+            Err(e) => {
+                log::error!(target: "Websocket", "Failed to acquire lock on IP rules: {e:?}");
+                false
+            }
+        }
+    }
+
+    /// Returns a copy of the rules currently enforced.
+    pub fn current_rules(&self) -> IpRules {
+        self.rules
+            .read()
+            .map(|rules| rules.clone())
+            .unwrap_or_default()
+    }
+
+    /// Re-reads the rules file and swaps in the new rules. On error the
+    /// previous rules stay in effect.
+    pub fn reload(&self) -> Result<IpRules, String> {
+        let mut rules = self.base.clone();
+        if let Some(path) = &self.rules_file {
+            let file_rules = Config::builder()
+                .add_source(File::from(path.as_path()).format(FileFormat::Toml))
+                .build()
+                .and_then(|settings| settings.try_deserialize::<IpRules>())
+                .map_err(|e| format!("Failed to load IP rules from {}: {e}", path.display()))?;
+            rules.allow.extend(file_rules.allow);
+            rules.deny.extend(file_rules.deny);
+        }
+
+        let mut current = self
+            .rules
+            .write()
+            .map_err(|e| format!("Failed to acquire lock on IP rules: {e:?}"))?;
+        *current = rules.clone();
+        log::info!(
+            target: "Websocket",
+            "IP rules loaded: {} allow, {} deny",
+            rules.allow.len(),
+            rules.deny.len()
+        );
+        Ok(rules)
+    }
+
+    /// Reloads the rules whenever the rules file changes.
+    pub fn watch(&self, interval: Duration) -> Option<JoinHandle<()>> {
+        let path = self.rules_file.clone()?;
+        let filter = self.clone();
+        Some(watch_files(vec![path], interval, move || {
+            if let Err(e) = filter.reload() {
+                log::error!(target: "Websocket", "{e}, keeping previous rules");
+            }
+        }))
+    }
+}
+
+/// Middleware rejecting requests from addresses blocked by the `IpFilter`.
+/// Requests pass through untouched when no filter is registered.
+pub async fn enforce_ip_filter(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    if let Some(filter) = req.app_data::<Data<IpFilter>>() {
+        let (trusted_proxies, forwarded_header) = req
+            .app_data::<Data<ServerConfig>>()
+            .map(|config| (config.trusted_proxies.clone(), config.forwarded_header))
+            .unwrap_or_default();
+        let peer_ip = peer_ip(req.request());
+
+        match resolve_client_ip(peer_ip, req.headers(), &trusted_proxies, forwarded_header) {
+            Some(ip) if filter.is_allowed(ip) => {}
+            ip => {
+                log::warn!(target: "Websocket", "Blocked request from {ip:?} to {}", req.path());
+                let response = ServerError::Forbidden.error_response();
+                return Ok(req.into_response(response).map_into_right_body());
+            }
+        }
+    }
+
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
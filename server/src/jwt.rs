@@ -0,0 +1,209 @@
+use crate::{ServerError, config::JwtConfig, metrics::metrics};
+use actix_web::{HttpRequest, http::header};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, Validation, decode, decode_header,
+    jwk::{Jwk, JwkSet},
+};
+use serde_json::{Map, Value};
+use std::fs;
+
+pub const JWT_FAILURE_METRIC: &str = "pastepoint_jwt_failures_total";
+
+/// Who a verified JWT says the connecting client is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JwtIdentity {
+    /// Stable peer identity taken from `identity_claim`.
+    pub peer_id: String,
+    /// Display name taken from `name_claim`, if present.
+    pub display_name: Option<String>,
+}
+
+/// Verifies JWTs presented on WebSocket upgrades, with keys loaded once at
+/// startup from the configuration and an optional local JWKS file.
+pub struct JwtVerifier {
+    config: JwtConfig,
+    hmac_key: Option<DecodingKey>,
+    rsa_key: Option<DecodingKey>,
+    ed_key: Option<DecodingKey>,
+    jwks: Vec<(Option<String>, Jwk)>,
+}
+
+fn read_key_file(path: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("Failed to read JWT key file {path}: {e}"))
+}
+
+// Reads a claim as a non-empty string, accepting numbers for identities.
+fn claim_string(claims: &Map<String, Value>, name: &str) -> Option<String> {
+    match claims.get(name)? {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+impl JwtVerifier {
+    pub fn from_config(config: &JwtConfig) -> Result<Self, String> {
+        let hmac_key = config
+            .hmac_secret
+            .as_ref()
+            .map(|secret| DecodingKey::from_secret(secret.as_bytes()));
+        let rsa_key = match &config.rsa_public_key_file {
+            Some(path) => Some(
+                DecodingKey::from_rsa_pem(&read_key_file(path)?)
+                    .map_err(|e| format!("Invalid RSA public key in {path}: {e}"))?,
+            ),
+            None => None,
+        };
+        let ed_key = match &config.ed25519_public_key_file {
+            Some(path) => Some(
+                DecodingKey::from_ed_pem(&read_key_file(path)?)
+                    .map_err(|e| format!("Invalid Ed25519 public key in {path}: {e}"))?,
+            ),
+            None => None,
+        };
+        let jwks = match &config.jwks_file {
+            Some(path) => {
+                let set: JwkSet = serde_json::from_slice(&read_key_file(path)?)
+                    .map_err(|e| format!("Invalid JWKS file {path}: {e}"))?;
+                set.keys
+                    .into_iter()
+                    .map(|jwk| (jwk.common.key_id.clone(), jwk))
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        if config.enabled
+            && hmac_key.is_none()
+            && rsa_key.is_none()
+            && ed_key.is_none()
+            && jwks.is_empty()
+        {
+            return Err("JWT verification is enabled but no keys are configured".to_string());
+        }
+
+        Ok(JwtVerifier {
+            config: config.clone(),
+            hmac_key,
+            rsa_key,
+            ed_key,
+            jwks,
+        })
+    }
+
+    /// Reads a JWT from `Authorization: Bearer` or the configured query
+    /// parameter. Bearer values that are not JWTs are left to API-key auth.
+    fn token(&self, req: &HttpRequest) -> Option<String> {
+        if let Some(token) = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+            && token.split('.').count() == 3
+        {
+            return Some(token.to_string());
+        }
+
+        url::form_urlencoded::parse(req.query_string().as_bytes())
+            .find(|(name, _)| *name == self.config.query_param.as_str())
+            .map(|(_, value)| value.into_owned())
+    }
+
+    // Picks the key for the token's algorithm, preferring a JWKS entry whose
+    // `kid` matches the token header.
+    fn key_for(&self, alg: Algorithm, kid: Option<&str>) -> Option<DecodingKey> {
+        if let Some(kid) = kid
+            && let Some((_, jwk)) = self.jwks.iter().find(|(id, _)| id.as_deref() == Some(kid))
+        {
+            return DecodingKey::from_jwk(jwk).ok();
+        }
+
+        let configured = match alg {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => self.hmac_key.clone(),
+            Algorithm::EdDSA => self.ed_key.clone(),
+            Algorithm::ES256 | Algorithm::ES384 => None,
+            _ => self.rsa_key.clone(),
+        };
+        configured.or_else(|| {
+            self.jwks
+                .iter()
+                .filter_map(|(_, jwk)| DecodingKey::from_jwk(jwk).ok())
+                .find(|key| key.family() == alg.family())
+        })
+    }
+
+    /// Verifies `token` and maps its claims to an identity.
+    pub fn verify(&self, token: &str) -> Result<JwtIdentity, String> {
+        let header = decode_header(token).map_err(|e| format!("malformed token: {e}"))?;
+        if !self.config.algorithms.contains(&header.alg) {
+            return Err(format!("algorithm {:?} is not allowed", header.alg));
+        }
+        let key = self
+            .key_for(header.alg, header.kid.as_deref())
+            .ok_or_else(|| format!("no key configured for {:?}", header.alg))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.leeway = self.config.leeway_secs;
+        match &self.config.issuer {
+            Some(issuer) => {
+                validation.set_issuer(&[issuer]);
+                validation.required_spec_claims.insert("iss".to_string());
+            }
+            None => validation.iss = None,
+        }
+        match &self.config.audience {
+            Some(audience) => {
+                validation.set_audience(&[audience]);
+                validation.required_spec_claims.insert("aud".to_string());
+            }
+            None => validation.validate_aud = false,
+        }
+
+        let claims = decode::<Map<String, Value>>(token, &key, &validation)
+            .map_err(|e| format!("invalid token: {e}"))?
+            .claims;
+        let peer_id = claim_string(&claims, &self.config.identity_claim)
+            .ok_or_else(|| format!("token has no '{}' claim", self.config.identity_claim))?;
+
+        Ok(JwtIdentity {
+            peer_id,
+            display_name: claim_string(&claims, &self.config.name_claim),
+        })
+    }
+
+    /// Verifies the request's JWT, if any. Requests without a token get
+    /// `None` (random names) unless `required` is set; present but invalid
+    /// tokens are always rejected.
+    pub fn identify(&self, req: &HttpRequest) -> Result<Option<JwtIdentity>, ServerError> {
+        if !self.config.enabled {
+            return Ok(None);
+        }
+
+        let Some(token) = self.token(req) else {
+            if self.config.required {
+                log::warn!(target: "Websocket", "Rejected connection without JWT to {}", req.path());
+                metrics().increment(JWT_FAILURE_METRIC, &[("reason", "missing")]);
+                return Err(ServerError::Unauthorized);
+            }
+            return Ok(None);
+        };
+
+        match self.verify(&token) {
+            Ok(identity) => {
+                log::debug!(
+                    target: "Websocket",
+                    "JWT verified for peer '{}' ({:?})",
+                    identity.peer_id,
+                    identity.display_name
+                );
+                Ok(Some(identity))
+            }
+            Err(e) => {
+                log::warn!(target: "Websocket", "Rejected JWT on {}: {e}", req.path());
+                metrics().increment(JWT_FAILURE_METRIC, &[("reason", "invalid")]);
+                Err(ServerError::Unauthorized)
+            }
+        }
+    }
+}
@@ -1,26 +1,106 @@
+#[cfg(not(any(feature = "openssl", feature = "rustls")))]
+compile_error!("enable the `openssl` or `rustls` feature to select a TLS backend");
+
 mod actor;
+mod auth;
+mod avatar;
+mod cli;
+mod client_ip;
 mod config;
+mod config_reload;
 mod consts;
+mod device;
 mod error;
+mod grouping;
 mod handler;
+mod ip_filter;
+mod jwt;
+mod listener;
+mod logging;
+mod mdns;
 mod message;
+mod metrics;
+mod mtls;
+mod origin;
+mod outbound;
+mod qr;
+mod rate_limit;
+mod roster;
 mod routes;
+mod screening;
 mod server;
 mod session;
 mod session_store;
+mod throttle;
+mod tls;
+#[cfg(feature = "openssl")]
+mod tls_openssl;
+#[cfg(feature = "rustls")]
+mod tls_rustls;
+mod watch;
 
-pub use config::ServerConfig;
+pub use auth::{AUTH_FAILURE_METRIC, ApiKey, Identity, Scope, TokenClaims, issue_token};
+pub use avatar::Avatar;
+pub use cli::{CliCommand, USAGE, parse_args};
+pub use client_ip::resolve_client_ip;
+pub use config::{
+    AuthConfig, ClientAuthConfig, ClientCertPolicy, ConfigSources, ForwardedHeader,
+    GroupingStrategy, HttpsRedirectConfig, IpFilterConfig, JwtConfig, LimitsConfig, ListenerConfig,
+    MdnsConfig, MessageQuota, OverflowPolicy, ScreeningConfig, ServerConfig, SessionGroupingConfig,
+    SubnetGroup, WebSocketOriginConfig,
+};
+pub use config_reload::{CONFIG_RELOAD_METRIC, ConfigReloader, ReloadReport};
 pub use consts::{
-    CLEANUP_INTERVAL, CONTENT_TYPE_TEXT_PLAIN, CORS_MAX_AGE, HEARTBEAT_INTERVAL, HEARTBEAT_TIMEOUT,
-    KEEP_ALIVE_INTERVAL, MAX_FRAME_SIZE, MAX_SIGNAL_SIZE, MIN_USER_AGENT_LENGTH, SAFE_CHARSET,
-    SESSION_CODE_LENGTH, SESSION_EXPIRATION_TIME, WS_PREFIX_KEEP_ALIVE, WS_PREFIX_SIGNAL_MESSAGE,
-    WS_PREFIX_SYSTEM_ERROR, WS_PREFIX_SYSTEM_JOIN, WS_PREFIX_SYSTEM_MEMBERS, WS_PREFIX_SYSTEM_NAME,
-    WS_PREFIX_SYSTEM_ROOMS, WS_PREFIX_USER_COMMAND, WS_PREFIX_USER_DISCONNECTED,
+    AVATAR_COLORS, AVATAR_EMOJI, BROADCAST_DEBOUNCE, CLEANUP_INTERVAL, CONFIG_ENV_PREFIX,
+    CONFIG_FILE_ENV, CONTENT_TYPE_PNG, CONTENT_TYPE_PROMETHEUS, CONTENT_TYPE_SVG,
+    CONTENT_TYPE_TEXT_PLAIN, CORS_MAX_AGE, DEFAULT_USER_AGENT_DENY, HEARTBEAT_INTERVAL,
+    HEARTBEAT_TIMEOUT, IDLE_THRESHOLD, KEEP_ALIVE_INTERVAL, MAX_FRAME_SIZE, MAX_OUTBOUND_BYTES,
+    MAX_OUTBOUND_MESSAGES, MAX_SIGNAL_SIZE, MDNS_MULTICAST_V4, MDNS_PORT, MDNS_SERVICE_TYPE,
+    MDNS_SERVICES_META_QUERY, MIN_USER_AGENT_LENGTH, ORIGIN_REJECTION_METRIC, QR_DEFAULT_SIZE,
+    QR_MAX_SIZE, QR_MIN_SIZE, SAFE_CHARSET, SESSION_CODE_LENGTH, SESSION_EXPIRATION_TIME,
+    TLS_SESSION_CACHE_SIZE, WS_CONNECTION_LIMIT_METRIC, WS_PREFIX_KEEP_ALIVE,
+    WS_PREFIX_SIGNAL_MESSAGE, WS_PREFIX_SYSTEM_ACTIVITY, WS_PREFIX_SYSTEM_ERROR,
+    WS_PREFIX_SYSTEM_JOIN, WS_PREFIX_SYSTEM_MEMBERS, WS_PREFIX_SYSTEM_MEMBERS_DELTA,
+    WS_PREFIX_SYSTEM_NAME, WS_PREFIX_SYSTEM_ROOMS, WS_PREFIX_SYSTEM_ROOMS_DELTA,
+    WS_PREFIX_SYSTEM_WARNING, WS_PREFIX_USER_COMMAND, WS_PREFIX_USER_DISCONNECTED,
+    WS_SLOW_CONSUMER_METRIC, WS_THROTTLE_METRIC,
 };
+pub use device::{DeviceInfo, DeviceType};
 pub use error::ServerError;
+pub use ip_filter::{IpFilter, IpRules, enforce_ip_filter};
+pub use jwt::{JWT_FAILURE_METRIC, JwtIdentity, JwtVerifier};
+pub use listener::{
+    ClientIpKeyExtractor, UnixSocketPeer, bind_listeners, bind_unix_socket, on_connect, peer_ip,
+    redirect_to_https,
+};
+pub use logging::{init_logging, set_log_filter};
+pub use mdns::{MdnsResponder, MdnsService};
 pub use message::{
-    ChatMessage, ClientMetadata, JoinRoom, LeaveRoom, ListRooms, RelaySignalMessage, WsChatServer,
-    WsChatSession,
+    Activity, ChatMessage, ClientMetadata, JoinRoom, LeaveRoom, ListRooms, PeerInfo, Presence,
+    RelayActivity, RelaySignalMessage, SetDevice, SetPresence, Snapshot, UpdateLimits,
+    WsChatServer, WsChatSession,
+};
+pub use metrics::{Metrics, metrics};
+#[cfg(feature = "rustls")]
+pub use mtls::client_cert_verifier;
+#[cfg(feature = "openssl")]
+pub use mtls::configure_client_auth;
+pub use mtls::{CLIENT_CERT_REJECTION_METRIC, ClientCert, extract_client_cert};
+pub use origin::OriginPattern;
+pub use outbound::{OutboundKind, Outbox, Push};
+pub use qr::{QrOptions, build_join_url, render_png, render_svg};
+pub use rate_limit::{RateLimiter, enforce_rate_limit};
+pub use roster::{Roster, RosterChanges};
+pub use routes::{
+    admin_config, admin_metrics, chat_ws, create_session, health, index, private_chat_ws,
+    reload_config, reload_ip_rules, session_qr_png, session_qr_svg,
 };
-pub use routes::{chat_ws, create_session, health, index, private_chat_ws};
-pub use session_store::SessionStore;
+pub use screening::{SCREENING_METRIC, ScreeningDecision, ScreeningRule, UaPattern};
+pub use session_store::{ConnectionLimit, SessionStore};
+pub use throttle::{MessageKind, SessionRateLimiter, Throttle};
+pub use tls::{ServerCertificates, TLS_RELOAD_METRIC};
+#[cfg(feature = "openssl")]
+pub use tls_openssl::TlsCertificates;
+#[cfg(feature = "rustls")]
+pub use tls_rustls::RustlsCertificates;
+pub use watch::watch_files;
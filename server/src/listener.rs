@@ -0,0 +1,181 @@
+use crate::{
+    ServerCertificates, ServerConfig,
+    client_ip::resolve_client_ip,
+    config::{ForwardedHeader, ListenerConfig},
+    mtls::extract_client_cert,
+};
+use actix_governor::{KeyExtractor, SimpleKeyExtractionError};
+use actix_http::{Extensions, Request, Response};
+use actix_service::IntoServiceFactory;
+use actix_web::{
+    Error, HttpRequest, HttpResponse, HttpServer,
+    body::MessageBody,
+    dev::{AppConfig, Service, ServiceFactory, ServiceRequest},
+    http::header,
+    web,
+};
+use ipnet::IpNet;
+use std::{
+    any::Any,
+    fmt, io,
+    net::{IpAddr, Ipv4Addr},
+};
+
+/// Marks connections accepted on a Unix domain socket, which have no peer
+/// address of their own.
+#[derive(Clone, Copy, Debug)]
+pub struct UnixSocketPeer;
+
+/// `HttpServer::on_connect` callback recording per-connection details: the
+/// TLS client certificate, or whether the peer came in over a Unix socket.
+pub fn on_connect(connection: &dyn Any, data: &mut Extensions) {
+    extract_client_cert(connection, data);
+
+    #[cfg(unix)]
+    if connection.is::<actix_web::rt::net::UnixStream>() {
+        data.insert(UnixSocketPeer);
+    }
+}
+
+/// The address of the directly connected peer. Unix socket peers are local
+/// processes and are reported as loopback.
+pub fn peer_ip(req: &HttpRequest) -> Option<IpAddr> {
+    req.peer_addr().map(|peer| peer.ip()).or_else(|| {
+        req.conn_data::<UnixSocketPeer>()
+            .map(|_| IpAddr::V4(Ipv4Addr::LOCALHOST))
+    })
+}
+
+/// Rate-limit key for `actix-governor`: the resolved client address, so
+/// requests arriving through a proxy or Unix socket are not all counted
+/// against the proxy. IPv6 clients are grouped by /56 prefix.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientIpKeyExtractor {
+    pub trusted_proxies: Vec<IpNet>,
+    pub forwarded_header: ForwardedHeader,
+}
+
+impl KeyExtractor for ClientIpKeyExtractor {
+    type Key = IpAddr;
+    type KeyExtractionError = SimpleKeyExtractionError<&'static str>;
+
+    fn extract(&self, req: &ServiceRequest) -> Result<Self::Key, Self::KeyExtractionError> {
+        let ip = resolve_client_ip(
+            peer_ip(req.request()),
+            req.headers(),
+            &self.trusted_proxies,
+            self.forwarded_header,
+        )
+        .ok_or_else(|| SimpleKeyExtractionError::new("Could not determine client IP address"))?;
+
+        Ok(match ip {
+            IpAddr::V6(ipv6) => {
+                let mut octets = ipv6.octets();
+                octets[7..16].fill(0);
+                IpAddr::V6(octets.into())
+            }
+            ip => ip,
+        })
+    }
+}
+
+/// Creates a Unix domain socket at `path` with the given file mode,
+/// replacing a stale socket left behind by a previous run.
+#[cfg(unix)]
+pub fn bind_unix_socket(path: &str, mode: u32) -> io::Result<std::os::unix::net::UnixListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{path} exists and is not a socket"),
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+
+    let listener = std::os::unix::net::UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    Ok(listener)
+}
+
+/// Binds `server` to every configured listener. `certificates` must be
+/// provided when a TLS listener is configured.
+pub fn bind_listeners<F, I, S, B>(
+    mut server: HttpServer<F, I, S, B>,
+    config: &ServerConfig,
+    certificates: Option<&ServerCertificates>,
+) -> io::Result<HttpServer<F, I, S, B>>
+where
+    F: Fn() -> I + Send + Clone + 'static,
+    I: IntoServiceFactory<S, Request>,
+    S: ServiceFactory<Request, Config = AppConfig> + 'static,
+    S::Error: Into<Error> + 'static,
+    S::InitError: fmt::Debug,
+    S::Response: Into<Response<B>> + 'static,
+    <S::Service as Service<Request>>::Future: 'static,
+    S::Service: 'static,
+    B: MessageBody + 'static,
+{
+    for listener in config.listeners() {
+        server = match listener {
+            ListenerConfig::Tls { address } => {
+                let certificates = certificates.ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "TLS listener configured without certificates",
+                    )
+                })?;
+                log::info!(target: "Websocket", "Listening for HTTPS on {address}");
+                certificates.bind(server, &address)?
+            }
+            ListenerConfig::Tcp { address } => {
+                log::info!(target: "Websocket", "Listening for plain HTTP on {address}");
+                server.bind(&address)?
+            }
+            #[cfg(unix)]
+            ListenerConfig::Unix { path, mode } => {
+                log::info!(target: "Websocket", "Listening on Unix socket {path} (mode {mode:o})");
+                server.listen_uds(bind_unix_socket(&path, mode)?)?
+            }
+            #[cfg(not(unix))]
+            ListenerConfig::Unix { path, .. } => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("Unix socket listener {path} is not supported on this platform"),
+                ));
+            }
+        };
+    }
+    Ok(server)
+}
+
+/// Default service of the redirect listener: sends every request to the
+/// same host and path over HTTPS.
+pub async fn redirect_to_https(req: HttpRequest, config: web::Data<ServerConfig>) -> HttpResponse {
+    let Some(host) = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+    else {
+        return HttpResponse::BadRequest().body("Missing Host header");
+    };
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !name.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => host,
+    };
+
+    let port = config
+        .https_redirect
+        .https_port
+        .or_else(|| config.https_port())
+        .filter(|&port| port != 443)
+        .map(|port| format!(":{port}"))
+        .unwrap_or_default();
+    let path = req.uri().path_and_query().map_or("/", |path| path.as_str());
+
+    HttpResponse::PermanentRedirect()
+        .append_header((header::LOCATION, format!("https://{host}{port}{path}")))
+        .finish()
+}
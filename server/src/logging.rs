@@ -0,0 +1,45 @@
+use env_logger::{Builder, Env, Logger};
+use log::{Log, Metadata, Record, SetLoggerError};
+use std::sync::{LazyLock, RwLock};
+
+static LOGGER: LazyLock<ReloadableLogger> =
+    LazyLock::new(|| ReloadableLogger(RwLock::new(Builder::new().build())));
+
+/// `env_logger` can only be installed once, so the global logger forwards
+/// to an inner one that is replaced when the log filter changes.
+struct ReloadableLogger(RwLock<Logger>);
+
+impl Log for ReloadableLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.0.read().is_ok_and(|logger| logger.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        if let Ok(logger) = self.0.read() {
+            logger.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(logger) = self.0.read() {
+            logger.flush();
+        }
+    }
+}
+
+/// Installs the global logger with `filter`. As before, `RUST_LOG` takes
+/// precedence when set.
+pub fn init_logging(filter: &str) -> Result<(), SetLoggerError> {
+    set_log_filter(filter);
+    log::set_logger(&*LOGGER)
+}
+
+/// Replaces the active log filter, e.g. `info` or `server=debug`.
+pub fn set_log_filter(filter: &str) {
+    let logger = Builder::from_env(Env::new().default_filter_or(filter)).build();
+    log::set_max_level(logger.filter());
+    match LOGGER.0.write() {
+        Ok(mut current) => *current = logger,
+        Err(e) => eprintln!("Failed to acquire lock on logger: {e:?}"),
+    }
+}
@@ -1,13 +1,20 @@
+use actix::{Actor, SystemRegistry};
 use actix_cors::Cors;
-use actix_governor::{Governor, GovernorConfigBuilder};
 use actix_http::KeepAlive;
-use actix_web::{App, HttpServer, middleware::Logger, web::Data};
-use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod};
+use actix_web::{
+    App, HttpServer,
+    middleware::{Logger, from_fn},
+    web,
+    web::Data,
+};
 use server::{
-    CORS_MAX_AGE, KEEP_ALIVE_INTERVAL, ServerConfig, SessionStore, chat_ws, create_session, health,
-    index, private_chat_ws,
+    CORS_MAX_AGE, CliCommand, ConfigReloader, IpFilter, JwtVerifier, KEEP_ALIVE_INTERVAL,
+    MdnsResponder, MdnsService, RateLimiter, ServerCertificates, ServerConfig, SessionStore, USAGE,
+    WsChatServer, admin_config, admin_metrics, bind_listeners, chat_ws, create_session,
+    enforce_rate_limit, health, index, init_logging, on_connect, parse_args, private_chat_ws,
+    redirect_to_https, reload_config, reload_ip_rules, session_qr_png, session_qr_svg,
 };
-use std::io::Result;
+use std::{env, io::Result, process};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const NAME: &str = env!("CARGO_PKG_NAME");
@@ -15,66 +22,145 @@ const AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
 
 #[actix_web::main]
 async fn main() -> Result<()> {
-    let config = ServerConfig::load(None).expect("Failed to load server configuration");
-
-    env_logger::init_from_env(env_logger::Env::new().default_filter_or(&config.log_level));
-    let governor_conf = GovernorConfigBuilder::default()
-        .requests_per_second(config.rate_limit_per_second)
-        .burst_size(config.rate_limit_burst_size)
-        .use_headers()
-        .finish()
-        .expect("Invalid rate limit configuration");
+    let sources = match parse_args(env::args().skip(1)) {
+        Ok(CliCommand::Run(sources)) => sources,
+        Ok(CliCommand::Help) => {
+            print!("{USAGE}");
+            return Ok(());
+        }
+        Ok(CliCommand::Version) => {
+            println!("{NAME} {VERSION}");
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+    let config = ServerConfig::load_from(&sources).unwrap_or_else(|e| {
+        eprintln!("Failed to load server configuration: {e}");
+        process::exit(1);
+    });
 
-    log::debug!(target: "Websocket", "Rate limiting configured: {governor_conf:?}");
+    init_logging(&config.log_level).expect("Failed to initialize logger");
+    let rate_limiter = RateLimiter::from_config(&config)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
 
     log::info!(
         target: "Websocket",
-        "Starting HTTPS server at https://{} - PastePoint({}) - {} - {}",
-        config.bind_address,
+        "Starting server with {} listener(s) - PastePoint({}) - {} - {}",
+        config.listeners().len(),
         NAME,
         VERSION,
         AUTHORS
     );
 
-    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
-    builder
-        .set_private_key_file(&config.key_file_path, SslFiletype::PEM)
-        .map_err(|e| log::error!(target: "Websocket","Failed to load private key: {e}"))
-        .expect("Cannot find private key file");
-    builder
-        .set_certificate_chain_file(&config.cert_file_path)
-        .map_err(|e| log::error!(target: "Websocket","Failed to load certificate chain file: {e}"))
-        .expect("Cannot find certificate chain file");
+    let certificates = if config.uses_tls() {
+        let certificates = ServerCertificates::from_config(&config).map_err(|e| {
+            log::error!(target: "Websocket", "{e}");
+            std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+        })?;
+        if let Some(interval) = config.cert_watch_interval() {
+            certificates.watch(interval);
+        }
+        #[cfg(unix)]
+        certificates.reload_on_hangup()?;
+
+        log::debug!(target: "Websocket","Using key file: {}", &config.key_file_path);
+        log::debug!(target: "Websocket","Using cert file: {}", &config.cert_file_path);
+        Some(certificates)
+    } else {
+        None
+    };
+
+    if config.mdns.enabled {
+        let service = MdnsService::from_config(&config)?;
+        MdnsResponder::bind(service, config.mdns.listen_port, config.mdns.interface)?.spawn();
+    }
+
+    let ip_filter = IpFilter::from_config(&config.ip_filter)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    if let Some(interval) = config.ip_filter.watch_interval() {
+        ip_filter.watch(interval);
+    }
+    let ip_filter = Data::new(ip_filter);
 
-    log::debug!(target: "Websocket","Using key file: {}", &config.key_file_path);
-    log::debug!(target: "Websocket","Using cert file: {}", &config.cert_file_path);
+    let jwt_verifier = JwtVerifier::from_config(&config.jwt)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let jwt_verifier = Data::new(jwt_verifier);
 
-    let session_manager = Data::new(SessionStore::default());
+    SystemRegistry::set(WsChatServer::new(config.limits).start());
+    let session_store = SessionStore::new(config.limits);
+
+    let reloader = ConfigReloader::new(
+        sources,
+        config.clone(),
+        rate_limiter.clone(),
+        session_store.clone(),
+    );
+    if let Some(interval) = config.config_watch_interval() {
+        reloader.watch(interval);
+    }
+    #[cfg(unix)]
+    reloader.reload_on_hangup()?;
+
+    let session_manager = Data::new(session_store);
+    let rate_limiter = Data::new(rate_limiter);
+    let reloader = Data::new(reloader);
     let server_config = Data::new(config.clone());
 
-    HttpServer::new(move || {
-        let server_config = server_config.clone();
-        let server_config_for_app = server_config.clone();
+    if config.https_redirect.enabled {
+        let redirect_config = server_config.clone();
+        log::info!(
+            target: "Websocket",
+            "Redirecting plain HTTP on {} to HTTPS",
+            config.https_redirect.bind_address
+        );
+        let redirect = HttpServer::new(move || {
+            App::new()
+                .app_data(redirect_config.clone())
+                .default_service(web::to(redirect_to_https))
+        })
+        .workers(1)
+        .bind(&config.https_redirect.bind_address)?
+        .run();
+        actix_web::rt::spawn(redirect);
+    }
+
+    let server = HttpServer::new(move || {
+        let live_config = reloader.clone();
         let cors = Cors::default()
-            .allowed_origin_fn(move |origin, _req_head| server_config.check_origin(origin))
+            .allowed_origin_fn(move |origin, _req_head| live_config.check_origin(origin))
             .allowed_methods(vec!["GET", "OPTIONS"])
             .supports_credentials()
             .max_age(CORS_MAX_AGE);
 
         App::new()
-            .wrap(Governor::new(&governor_conf))
+            .wrap(from_fn(enforce_rate_limit))
             .wrap(Logger::default())
             .wrap(cors)
             .app_data(session_manager.clone())
-            .app_data(server_config_for_app)
+            .app_data(server_config.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(reloader.clone())
+            .app_data(ip_filter.clone())
+            .app_data(jwt_verifier.clone())
             .service(index)
             .service(health)
             .service(create_session)
+            .service(session_qr_svg)
+            .service(session_qr_png)
             .service(chat_ws)
             .service(private_chat_ws)
+            .service(reload_ip_rules)
+            .service(reload_config)
+            .service(admin_config)
+            .service(admin_metrics)
     })
-    .keep_alive(KeepAlive::Timeout(KEEP_ALIVE_INTERVAL))
-    .bind_openssl(&config.bind_address, builder)?
-    .run()
-    .await
+    .on_connect(on_connect)
+    .keep_alive(KeepAlive::Timeout(KEEP_ALIVE_INTERVAL));
+
+    bind_listeners(server, &config, certificates.as_ref())?
+        .run()
+        .await
 }
@@ -0,0 +1,392 @@
+use crate::{
+    MDNS_MULTICAST_V4, MDNS_PORT, MDNS_SERVICE_TYPE, MDNS_SERVICES_META_QUERY, ServerConfig,
+    config::ListenerConfig,
+    consts::{MDNS_MAX_RECV_FAILURES, MDNS_RECV_RETRY_DELAY},
+};
+use actix_rt::{net::UdpSocket, task::JoinHandle, time::sleep};
+use simple_dns::{
+    CLASS, Name, Packet, PacketFlag, QTYPE, Question, ResourceRecord, TYPE,
+    rdata::{A, AAAA, PTR, RData, SRV, TXT},
+};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    env, fs, io,
+    net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4},
+    sync::Arc,
+};
+
+const MAX_PACKET_SIZE: usize = 9000;
+
+/// Description of the `_pastepoint._tcp` service published over mDNS.
+#[derive(Clone, Debug)]
+pub struct MdnsService {
+    /// Fully qualified instance name, e.g. `PastePoint._pastepoint._tcp.local`.
+    pub instance: String,
+    /// Fully qualified host name, e.g. `office-server.local`.
+    pub host: String,
+    pub port: u16,
+    pub addresses: Vec<IpAddr>,
+    /// TXT record entries in `key=value` form.
+    pub txt: Vec<String>,
+    pub ttl: u32,
+}
+
+impl MdnsService {
+    /// Builds the service description from the server configuration.
+    pub fn from_config(config: &ServerConfig) -> io::Result<Self> {
+        let mdns = &config.mdns;
+        let listener = advertised_listener(config);
+        let port = mdns
+            .port
+            .or(listener.map(|(port, _)| port))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "mDNS port is not set and no TCP listener has a port",
+                )
+            })?;
+        let tls = listener.map_or(config.uses_tls(), |(_, tls)| tls);
+
+        let host_label = mdns.hostname.clone().unwrap_or_else(system_hostname);
+        let addresses = if mdns.addresses.is_empty() {
+            detect_local_address().into_iter().collect()
+        } else {
+            mdns.addresses.clone()
+        };
+
+        Ok(MdnsService {
+            instance: format!("{}.{MDNS_SERVICE_TYPE}", mdns.instance_name),
+            host: format!("{}.local", sanitize_label(&host_label)),
+            port,
+            addresses,
+            txt: vec![
+                format!("version={}", env!("CARGO_PKG_VERSION")),
+                format!("tls={}", u8::from(tls)),
+                format!("path={}", mdns.path),
+            ],
+            ttl: mdns.ttl,
+        })
+    }
+
+    fn ptr_record(&self) -> ResourceRecord<'_> {
+        ResourceRecord::new(
+            Name::new_unchecked(MDNS_SERVICE_TYPE),
+            CLASS::IN,
+            self.ttl,
+            RData::PTR(PTR(Name::new_unchecked(&self.instance))),
+        )
+    }
+
+    fn meta_ptr_record(&self) -> ResourceRecord<'_> {
+        ResourceRecord::new(
+            Name::new_unchecked(MDNS_SERVICES_META_QUERY),
+            CLASS::IN,
+            self.ttl,
+            RData::PTR(PTR(Name::new_unchecked(MDNS_SERVICE_TYPE))),
+        )
+    }
+
+    fn srv_record(&self) -> ResourceRecord<'_> {
+        ResourceRecord::new(
+            Name::new_unchecked(&self.instance),
+            CLASS::IN,
+            self.ttl,
+            RData::SRV(SRV {
+                priority: 0,
+                weight: 0,
+                port: self.port,
+                target: Name::new_unchecked(&self.host),
+            }),
+        )
+        .with_cache_flush(true)
+    }
+
+    fn txt_record(&self) -> ResourceRecord<'_> {
+        let mut txt = TXT::new();
+        for entry in &self.txt {
+            if let Err(e) = txt.add_string(entry) {
+                log::warn!(target: "Websocket", "Skipping invalid mDNS TXT entry '{entry}': {e}");
+            }
+        }
+        ResourceRecord::new(
+            Name::new_unchecked(&self.instance),
+            CLASS::IN,
+            self.ttl,
+            RData::TXT(txt),
+        )
+        .with_cache_flush(true)
+    }
+
+    fn address_records(&self) -> Vec<ResourceRecord<'_>> {
+        self.addresses
+            .iter()
+            .map(|addr| {
+                let rdata = match addr {
+                    IpAddr::V4(v4) => RData::A(A {
+                        address: u32::from(*v4),
+                    }),
+                    IpAddr::V6(v6) => RData::AAAA(AAAA {
+                        address: u128::from(*v6),
+                    }),
+                };
+                ResourceRecord::new(Name::new_unchecked(&self.host), CLASS::IN, self.ttl, rdata)
+                    .with_cache_flush(true)
+            })
+            .collect()
+    }
+
+    /// Answers and additional records for a single question.
+    fn answer(&self, question: &Question) -> (Vec<ResourceRecord<'_>>, Vec<ResourceRecord<'_>>) {
+        let qname = question.qname.to_string();
+        let qtype = question.qtype;
+        let mut answers = Vec::new();
+        let mut additional = Vec::new();
+
+        if qname.eq_ignore_ascii_case(MDNS_SERVICES_META_QUERY) {
+            answers.push(self.meta_ptr_record());
+        } else if qname.eq_ignore_ascii_case(MDNS_SERVICE_TYPE) {
+            if matches!(qtype, QTYPE::TYPE(TYPE::PTR) | QTYPE::ANY) {
+                answers.push(self.ptr_record());
+                additional.push(self.srv_record());
+                additional.push(self.txt_record());
+                additional.extend(self.address_records());
+            }
+        } else if qname.eq_ignore_ascii_case(&self.instance) {
+            let srv = self.srv_record();
+            let txt = self.txt_record();
+            let wants_srv = srv.match_qtype(qtype);
+            answers.extend(
+                [srv, txt]
+                    .into_iter()
+                    .filter(|r: &ResourceRecord| r.match_qtype(qtype)),
+            );
+            if wants_srv {
+                additional.extend(self.address_records());
+            }
+        } else if qname.eq_ignore_ascii_case(&self.host) {
+            answers.extend(
+                self.address_records()
+                    .into_iter()
+                    .filter(|r: &ResourceRecord| r.match_qtype(qtype)),
+            );
+        }
+
+        (answers, additional)
+    }
+
+    /// Builds a response to an incoming query packet, or `None` if the
+    /// packet is not a query or asks about names we do not own.
+    ///
+    /// `legacy_unicast` must be set for queries sent from a port other than
+    /// 5353; those get the query ID and questions echoed back (RFC 6762 §6.7).
+    pub fn handle_query(&self, data: &[u8], legacy_unicast: bool) -> Option<Vec<u8>> {
+        let query = Packet::parse(data).ok()?;
+        if query.has_flags(PacketFlag::RESPONSE) {
+            return None;
+        }
+
+        let mut reply = Packet::new_reply(if legacy_unicast { query.id() } else { 0 });
+        reply.set_flags(PacketFlag::AUTHORITATIVE_ANSWER);
+
+        for question in &query.questions {
+            let (answers, additional) = self.answer(question);
+            if answers.is_empty() {
+                continue;
+            }
+            if legacy_unicast {
+                reply.questions.push(question.clone());
+            }
+            for record in answers {
+                if !reply.answers.contains(&record) {
+                    reply.answers.push(record);
+                }
+            }
+            for record in additional {
+                if !reply.additional_records.contains(&record) {
+                    reply.additional_records.push(record);
+                }
+            }
+        }
+
+        if reply.answers.is_empty() {
+            return None;
+        }
+        if legacy_unicast {
+            // Legacy resolvers must not see the cache-flush bit.
+            for record in reply
+                .answers
+                .iter_mut()
+                .chain(reply.additional_records.iter_mut())
+            {
+                record.cache_flush = false;
+            }
+        }
+
+        reply.build_bytes_vec_compressed().ok()
+    }
+
+    /// Builds an unsolicited announcement carrying every record we own.
+    pub fn announcement(&self) -> Option<Vec<u8>> {
+        let mut packet = Packet::new_reply(0);
+        packet.set_flags(PacketFlag::AUTHORITATIVE_ANSWER);
+        packet.answers.push(self.ptr_record());
+        packet.answers.push(self.srv_record());
+        packet.answers.push(self.txt_record());
+        packet.answers.extend(self.address_records());
+        packet.build_bytes_vec_compressed().ok()
+    }
+}
+
+/// In-process mDNS responder answering queries for the PastePoint service.
+pub struct MdnsResponder {
+    service: Arc<MdnsService>,
+    socket: UdpSocket,
+    group: SocketAddr,
+}
+
+impl MdnsResponder {
+    /// Binds the responder socket on `listen_port` and joins the mDNS
+    /// multicast group on `interface`. Failing to join the group is logged
+    /// but not fatal, so unicast queries keep working.
+    pub fn bind(service: MdnsService, listen_port: u16, interface: Ipv4Addr) -> io::Result<Self> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        #[cfg(unix)]
+        socket.set_reuse_port(true)?;
+        socket.set_nonblocking(true)?;
+        socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, listen_port).into())?;
+
+        if let Err(e) = socket.join_multicast_v4(&MDNS_MULTICAST_V4, &interface) {
+            log::warn!(
+                target: "Websocket",
+                "Failed to join mDNS multicast group on {interface}: {e}"
+            );
+        }
+        socket.set_multicast_loop_v4(true)?;
+        socket.set_multicast_ttl_v4(255)?;
+
+        let socket = UdpSocket::from_std(socket.into())?;
+        Ok(MdnsResponder {
+            service: Arc::new(service),
+            socket,
+            group: SocketAddr::V4(SocketAddrV4::new(MDNS_MULTICAST_V4, MDNS_PORT)),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Announces the service and answers queries until the task is dropped,
+    /// or the socket keeps failing.
+    pub fn spawn(self) -> JoinHandle<()> {
+        actix_rt::spawn(async move {
+            log::info!(
+                target: "Websocket",
+                "Advertising mDNS service '{}' on {}:{}",
+                self.service.instance,
+                self.service.host,
+                self.service.port
+            );
+
+            if let Some(announcement) = self.service.announcement()
+                && let Err(e) = self.socket.send_to(&announcement, self.group).await
+            {
+                log::warn!(target: "Websocket", "Failed to send mDNS announcement: {e}");
+            }
+
+            let mut buf = vec![0u8; MAX_PACKET_SIZE];
+            let mut failures = 0;
+            loop {
+                let (len, src) = match self.socket.recv_from(&mut buf).await {
+                    Ok(received) => {
+                        failures = 0;
+                        received
+                    }
+                    Err(e) => {
+                        failures += 1;
+                        if failures >= MDNS_MAX_RECV_FAILURES {
+                            log::error!(
+                                target: "Websocket",
+                                "mDNS receive failed {failures} times in a row, stopping advertisement: {e}"
+                            );
+                            return;
+                        }
+                        // Back off so a dead socket does not spin a core.
+                        let delay = MDNS_RECV_RETRY_DELAY * 2u32.pow(failures - 1);
+                        log::warn!(
+                            target: "Websocket",
+                            "mDNS receive failed, retrying in {delay:?}: {e}"
+                        );
+                        sleep(delay).await;
+                        continue;
+                    }
+                };
+
+                let legacy_unicast = src.port() != MDNS_PORT;
+                let unicast_requested = Packet::parse(&buf[..len])
+                    .map(|p| p.questions.iter().any(|q| q.unicast_response))
+                    .unwrap_or(false);
+
+                if let Some(reply) = self.service.handle_query(&buf[..len], legacy_unicast) {
+                    let dest = if legacy_unicast || unicast_requested {
+                        src
+                    } else {
+                        self.group
+                    };
+                    log::debug!(target: "Websocket", "Answering mDNS query from {src} via {dest}");
+                    if let Err(e) = self.socket.send_to(&reply, dest).await {
+                        log::warn!(target: "Websocket", "Failed to send mDNS reply to {dest}: {e}");
+                    }
+                }
+            }
+        })
+    }
+}
+
+// Picks the first TCP listener to advertise, returning its port and whether
+// it serves TLS. Unix socket listeners cannot be reached over the network.
+fn advertised_listener(config: &ServerConfig) -> Option<(u16, bool)> {
+    config
+        .listeners()
+        .into_iter()
+        .find_map(|listener| match listener {
+            ListenerConfig::Tls { address } => Some((address, true)),
+            ListenerConfig::Tcp { address } => Some((address, false)),
+            ListenerConfig::Unix { .. } => None,
+        })
+        .and_then(|(address, tls)| Some((address.parse::<SocketAddr>().ok()?.port(), tls)))
+}
+
+// Reads the machine hostname without pulling in an extra dependency.
+fn system_hostname() -> String {
+    env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "pastepoint".to_string())
+}
+
+// Keeps a host label within the DNS label character set.
+fn sanitize_label(label: &str) -> String {
+    let label = label.split('.').next().unwrap_or(label);
+    let sanitized: String = label
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .take(63)
+        .collect();
+    if sanitized.is_empty() {
+        "pastepoint".to_string()
+    } else {
+        sanitized
+    }
+}
+
+// Finds the address of the interface used to reach the mDNS group.
+fn detect_local_address() -> Option<IpAddr> {
+    let socket = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((MDNS_MULTICAST_V4, MDNS_PORT)).ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_unspecified()).then_some(ip)
+}
@@ -1,6 +1,14 @@
-use crate::SessionStore;
+use crate::{
+    SessionStore, auth::Identity, avatar::Avatar, config::LimitsConfig, device::DeviceInfo,
+    outbound::Outbox, roster::Roster, throttle::SessionRateLimiter,
+};
 use actix::prelude::*;
-use std::{collections::HashMap, time::Instant};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    time::Instant,
+};
 
 pub type Client = Recipient<ChatMessage>;
 pub type Room = HashMap<usize, ClientMetadata>;
@@ -8,36 +16,140 @@ pub type Room = HashMap<usize, ClientMetadata>;
 #[derive(Default)]
 pub struct WsChatServer {
     pub rooms: HashMap<String, HashMap<String, Room>>, // session_id -> room_name -> clients
+    pub limits: LimitsConfig,                          // room and session limits
+    pub(crate) rosters: HashMap<String, Roster>,       // session_id -> published lists
+    pub(crate) changed: HashSet<String>,               // sessions with unpublished changes
+    pub(crate) publish_scheduled: bool,                // a publish is pending
 }
 
 pub struct WsChatSession {
-    pub session_id: String,              // session id
-    pub id: usize,                       // client id
-    pub room: String,                    // room name
-    pub name: String,                    // client name
-    pub auto_join: bool,                 // flag to control auto-join
-    pub session_store: SessionStore,     // reference to SessionStore
-    pub last_heartbeat: Option<Instant>, // last heartbeat time
-    pub message_count: usize,            // rate limiting: messages in current window
-    pub rate_limit_reset: Instant,       // rate limiting: when to reset counter
+    pub session_id: String,               // session id
+    pub id: usize,                        // client id
+    pub room: String,                     // room name
+    pub name: String,                     // client name
+    pub avatar: Avatar,                   // color, emoji and initials shown for the client
+    pub auto_join: bool,                  // flag to control auto-join
+    pub session_store: SessionStore,      // reference to SessionStore
+    pub last_heartbeat: Option<Instant>,  // last heartbeat time
+    pub rate_limiter: SessionRateLimiter, // per-message-type token buckets
+    pub peer: PeerInfo,                   // connection details
+    pub limits: LimitsConfig,             // message size, rate and heartbeat limits
+    pub outbox: Outbox,                   // server messages waiting to be sent
+    pub presence: Presence,               // presence last reported to the server
+    pub status: Option<Presence>,         // presence set with `/status`
+    pub last_activity: Instant,           // last message sent by the user
+}
+
+/// A WebSocket accepted only to be closed with `reason`, for clients over a
+/// connection limit.
+pub struct RejectedSession {
+    pub reason: String, // close frame description
+}
+
+/// What the server knows about a connecting client.
+#[derive(Clone, Debug, Default)]
+pub struct PeerInfo {
+    pub client_ip: Option<IpAddr>,    // resolved client address
+    pub identity: Option<Identity>,   // authenticated caller, if auth is enabled
+    pub cert_subject: Option<String>, // verified TLS client certificate subject
+    pub peer_id: Option<String>,      // stable identity (JWT, certificate or API key)
+    pub display_name: Option<String>, // name from a verified JWT
+    pub device: DeviceInfo,           // device details, from the User-Agent or `/device`
+    pub ip_cap_exempt: bool,          // let in by a bypass API key, exempt from the per-IP cap
 }
 
 pub struct ClientMetadata {
-    pub recipient: Client, // client
-    pub name: String,      // client name
+    pub recipient: Client,  // client
+    pub name: String,       // client name
+    pub outbox: Outbox,     // bounded queue of messages for the client
+    pub presence: Presence, // whether the client is in use
+    pub device: DeviceInfo, // device the client runs on
+    pub avatar: Avatar,     // how the client is drawn
+}
+
+/// Whether someone is at the device behind a client.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Presence {
+    /// The user sent something within the idle threshold.
+    #[default]
+    Active,
+    /// The connection is alive but the user has not done anything lately.
+    Idle,
+    /// The device stopped answering heartbeats, or the user said so.
+    Away,
+}
+
+impl Presence {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "active" => Some(Presence::Active),
+            "idle" => Some(Presence::Idle),
+            "away" => Some(Presence::Away),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Presence::Active => "active",
+            Presence::Idle => "idle",
+            Presence::Away => "away",
+        }
+    }
+}
+
+/// What a user is doing, shown to the rest of the room while the peer
+/// connections that would carry it are still being negotiated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Activity {
+    Typing,
+    SelectingFile,
+    Uploading,
+    /// The user stopped whatever they were doing.
+    None,
+}
+
+impl Activity {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "typing" => Some(Activity::Typing),
+            "selecting-file" => Some(Activity::SelectingFile),
+            "uploading" => Some(Activity::Uploading),
+            "none" => Some(Activity::None),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Activity::Typing => "typing",
+            Activity::SelectingFile => "selecting-file",
+            Activity::Uploading => "uploading",
+            Activity::None => "none",
+        }
+    }
 }
 
 #[derive(Clone, Message)]
 #[rtype(result = "()")]
 pub struct ChatMessage(pub String /* message */);
 
+/// Adds a client to a room. Answers with the client's id, and the name it
+/// goes by, which gets a suffix when another client in the session has it,
+/// or why the room or session turned the client away.
 #[derive(Clone, Message)]
-#[rtype(result = "usize")]
+#[rtype(result = "Result<(usize, String), String>")]
 pub struct JoinRoom(
     pub String,                 // session_id
     pub String,                 // room_name
     pub String,                 // client_name
     pub Recipient<ChatMessage>, // client
+    pub Outbox,                 // client's outbound queue
+    pub Presence,               // client's presence
+    pub DeviceInfo,             // client's device
+    pub Avatar,                 // client's avatar
 );
 
 #[derive(Clone, Message)]
@@ -48,6 +160,33 @@ pub struct LeaveRoom(
     pub usize,  // id
 );
 
+#[derive(Clone, Message)]
+#[rtype(result = "()")]
+pub struct SetPresence(
+    pub String,   // session_id
+    pub String,   // room_name
+    pub usize,    // id
+    pub Presence, // new presence
+);
+
+#[derive(Clone, Message)]
+#[rtype(result = "()")]
+pub struct SetDevice(
+    pub String,     // session_id
+    pub String,     // room_name
+    pub usize,      // id
+    pub DeviceInfo, // registered device
+);
+
+#[derive(Clone, Message)]
+#[rtype(result = "()")]
+pub struct RelayActivity(
+    pub String,   // session_id
+    pub String,   // room_name
+    pub usize,    // id
+    pub Activity, // what the client is doing
+);
+
 #[derive(Clone, Message)]
 #[rtype(result = "Vec<String>")]
 pub struct ListRooms(pub String /* session_id */);
@@ -61,6 +200,14 @@ pub struct RelaySignalMessage {
     pub(crate) message: ChatMessage, // signal message
 }
 
+/// Asks for the published room list, and the members of a room if given.
+#[derive(Clone, Message)]
+#[rtype(result = "Vec<String>")]
+pub struct Snapshot(
+    pub String,         // session_id
+    pub Option<String>, // room_name
+);
+
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct CleanupSession(pub String /* session_id */);
@@ -73,3 +220,7 @@ pub struct ValidateAndRelaySignal {
     pub to_user: String,
     pub payload: String,
 }
+
+#[derive(Clone, Copy, Message)]
+#[rtype(result = "()")]
+pub struct UpdateLimits(pub LimitsConfig);
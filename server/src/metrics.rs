@@ -0,0 +1,73 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{LazyLock, Mutex},
+};
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+/// Returns the process-wide metrics registry.
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+/// Minimal counter registry rendered in the Prometheus text format.
+#[derive(Default)]
+pub struct Metrics {
+    counters: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Metrics {
+    fn key(name: &str, labels: &[(&str, &str)]) -> String {
+        if labels.is_empty() {
+            return name.to_string();
+        }
+        let labels = labels
+            .iter()
+            .map(|(k, v)| format!("{k}=\"{}\"", v.replace('\\', "\\\\").replace('"', "\\\"")))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("{name}{{{labels}}}")
+    }
+
+    /// Adds `value` to the counter identified by `name` and `labels`.
+    pub fn add(&self, name: &str, labels: &[(&str, &str)], value: u64) {
+        match self.counters.lock() {
+            Ok(mut counters) => *counters.entry(Self::key(name, labels)).or_default() += value,
+            Err(e) => log::error!(target: "Websocket", "Failed to acquire lock on metrics: {e:?}"),
+        }
+    }
+
+    pub fn increment(&self, name: &str, labels: &[(&str, &str)]) {
+        self.add(name, labels, 1);
+    }
+
+    /// Returns the current value of a counter, or 0 if it was never touched.
+    pub fn counter(&self, name: &str, labels: &[(&str, &str)]) -> u64 {
+        self.counters
+            .lock()
+            .ok()
+            .and_then(|counters| counters.get(&Self::key(name, labels)).copied())
+            .unwrap_or(0)
+    }
+
+    /// Renders all counters in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let counters = match self.counters.lock() {
+            Ok(counters) => counters.clone(),
+            Err(_) => return String::new(),
+        };
+
+        let mut output = String::new();
+        let mut last_name = "";
+        for (key, value) in &counters {
+            let name = key.split('{').next().unwrap_or(key);
+            if name != last_name {
+                let _ = writeln!(output, "# TYPE {name} counter");
+                last_name = name;
+            }
+            let _ = writeln!(output, "{key} {value}");
+        }
+        output
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{
+    ServerError,
+    config::{ClientAuthConfig, ClientCertPolicy},
+    metrics::metrics,
+};
+use actix_http::Extensions;
+use actix_web::HttpRequest;
+use std::any::Any;
+
+pub const CLIENT_CERT_REJECTION_METRIC: &str = "pastepoint_client_cert_rejections_total";
+
+/// Details of the verified certificate a client presented during the TLS
+/// handshake, attached to every request on that connection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientCert {
+    /// Distinguished name, e.g. `CN=alice,O=Example Corp`.
+    pub subject: String,
+}
+
+#[cfg(feature = "openssl")]
+fn format_name(name: &openssl::x509::X509NameRef) -> String {
+    name.entries()
+        .filter_map(|entry| {
+            let key = entry.object().nid().short_name().ok()?;
+            let value = entry.data().as_utf8().ok()?;
+            Some(format!("{key}={value}"))
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// Formats the subject of a DER certificate the same way as `format_name`.
+#[cfg(feature = "rustls")]
+fn format_der_subject(der: &[u8]) -> Option<String> {
+    use x509_parser::{
+        objects::{oid_registry, oid2abbrev},
+        prelude::{FromDer, X509Certificate},
+    };
+
+    let (_, cert) = X509Certificate::from_der(der).ok()?;
+    let subject = cert
+        .subject()
+        .iter_attributes()
+        .filter_map(|attr| {
+            let key = oid2abbrev(attr.attr_type(), oid_registry()).ok()?;
+            let value = attr.as_str().ok()?;
+            Some(format!("{key}={value}"))
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    Some(subject)
+}
+
+/// Asks clients for a certificate signed by `ca_file`. Handshakes without a
+/// certificate still succeed so routes can apply their own policy; invalid
+/// certificates fail the handshake.
+#[cfg(feature = "openssl")]
+pub fn configure_client_auth(
+    builder: &mut openssl::ssl::SslAcceptorBuilder,
+    config: &ClientAuthConfig,
+) -> std::io::Result<()> {
+    use openssl::{ssl::SslVerifyMode, x509::X509};
+
+    let Some(ca_file) = config.enabled_ca_file()? else {
+        return Ok(());
+    };
+    builder.set_ca_file(ca_file)?;
+
+    let ca_pem = std::fs::read(ca_file)?;
+    for ca in X509::stack_from_pem(&ca_pem)? {
+        builder.add_client_ca(&ca)?;
+    }
+    builder.set_verify(SslVerifyMode::PEER);
+
+    log::info!(target: "Websocket", "Client certificate verification enabled with CA {ca_file}");
+    Ok(())
+}
+
+/// rustls equivalent of `configure_client_auth`: a verifier accepting
+/// anonymous clients and certificates signed by `ca_file`, or `None` when
+/// client authentication is disabled.
+#[cfg(feature = "rustls")]
+pub fn client_cert_verifier(
+    config: &ClientAuthConfig,
+    provider: std::sync::Arc<rustls::crypto::CryptoProvider>,
+) -> std::io::Result<Option<std::sync::Arc<dyn rustls::server::danger::ClientCertVerifier>>> {
+    use rustls::{
+        RootCertStore,
+        pki_types::{CertificateDer, pem::PemObject},
+        server::WebPkiClientVerifier,
+    };
+    use std::{io, sync::Arc};
+
+    let Some(ca_file) = config.enabled_ca_file()? else {
+        return Ok(None);
+    };
+
+    let mut roots = RootCertStore::empty();
+    for ca in CertificateDer::pem_file_iter(ca_file)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+    {
+        let ca = ca.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        roots
+            .add(ca)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    }
+
+    let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+        .allow_unauthenticated()
+        .build()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    log::info!(target: "Websocket", "Client certificate verification enabled with CA {ca_file}");
+    Ok(Some(verifier))
+}
+
+/// `HttpServer::on_connect` callback storing the peer certificate subject.
+pub fn extract_client_cert(connection: &dyn Any, data: &mut Extensions) {
+    if let Some(subject) = peer_subject(connection) {
+        log::debug!(target: "Websocket", "TLS client presented certificate: {subject}");
+        data.insert(ClientCert { subject });
+    }
+}
+
+// Helper function to read the verified client certificate subject from
+// whichever TLS backend accepted the connection
+fn peer_subject(connection: &dyn Any) -> Option<String> {
+    #[cfg(feature = "openssl")]
+    if let Some(stream) = connection
+        .downcast_ref::<actix_tls::accept::openssl::TlsStream<actix_web::rt::net::TcpStream>>()
+    {
+        return stream
+            .ssl()
+            .peer_certificate()
+            .map(|cert| format_name(cert.subject_name()));
+    }
+
+    #[cfg(feature = "rustls")]
+    if let Some(stream) = connection
+        .downcast_ref::<actix_tls::accept::rustls_0_23::TlsStream<actix_web::rt::net::TcpStream>>()
+    {
+        let (_, session) = stream.get_ref();
+        return session
+            .peer_certificates()
+            .and_then(|chain| chain.first())
+            .and_then(|cert| format_der_subject(cert));
+    }
+
+    None
+}
+
+impl ClientAuthConfig {
+    // Helper function to return the CA bundle path when client
+    // certificates are enabled
+    fn enabled_ca_file(&self) -> std::io::Result<Option<&str>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        self.ca_file.as_deref().map(Some).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "client_auth is enabled but ca_file is not set",
+            )
+        })
+    }
+
+    /// Applies `policy` to the request's client certificate. Returns the
+    /// certificate when one was presented and the policy is not `off`.
+    pub fn check(
+        &self,
+        req: &HttpRequest,
+        policy: ClientCertPolicy,
+        route: &str,
+    ) -> Result<Option<ClientCert>, ServerError> {
+        if !self.enabled || policy == ClientCertPolicy::Off {
+            return Ok(None);
+        }
+
+        match req.conn_data::<ClientCert>() {
+            Some(cert) => Ok(Some(cert.clone())),
+            None if policy == ClientCertPolicy::Required => {
+                log::warn!(
+                    target: "Websocket",
+                    "Rejected request to {} without a client certificate",
+                    req.path()
+                );
+                metrics().increment(CLIENT_CERT_REJECTION_METRIC, &[("route", route)]);
+                Err(ServerError::Forbidden)
+            }
+            None => Ok(None),
+        }
+    }
+}
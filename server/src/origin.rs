@@ -0,0 +1,198 @@
+use serde::{Deserialize, Deserializer, de::Error as _};
+use std::{fmt, str::FromStr};
+use url::Url;
+
+const LOCALHOST_SHORTCUT: &str = "localhost";
+const LOCALHOST_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "[::1]"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum HostPattern {
+    Exact(String),
+    /// `*.example.com`: any subdomain of `example.com`, but not the apex.
+    Subdomains(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum PortPattern {
+    Exact(u16),
+    Any,
+}
+
+/// An allowed browser origin.
+///
+/// Accepted forms:
+/// - `https://pastepoint.com` or `http://10.0.0.5:8080`: exact scheme, host and
+///   port (the scheme's default port when omitted)
+/// - `https://*.pastepoint.com`: any subdomain, same scheme and port
+/// - `https://pastepoint.com:*`: any port
+/// - `localhost`: `localhost`, `127.0.0.1` and `[::1]` over HTTP or HTTPS on
+///   any port, for development
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OriginPattern(PatternKind);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum PatternKind {
+    Localhost,
+    Origin {
+        scheme: String,
+        host: HostPattern,
+        port: PortPattern,
+    },
+}
+
+impl OriginPattern {
+    /// Returns whether `origin` (the raw `Origin` header value) matches.
+    pub fn matches(&self, origin: &str) -> bool {
+        let Some((scheme, host, port)) = Url::parse(origin).ok().and_then(|url| {
+            Some((
+                url.scheme().to_string(),
+                url.host_str()?.to_ascii_lowercase(),
+                url.port_or_known_default()?,
+            ))
+        }) else {
+            return false;
+        };
+
+        match &self.0 {
+            PatternKind::Localhost => {
+                (scheme == "http" || scheme == "https") && LOCALHOST_HOSTS.contains(&host.as_str())
+            }
+            PatternKind::Origin {
+                scheme: allowed_scheme,
+                host: allowed_host,
+                port: allowed_port,
+            } => {
+                let host_matches = match allowed_host {
+                    HostPattern::Exact(h) => host == *h,
+                    HostPattern::Subdomains(domain) => host
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|prefix| prefix.len() > 1 && prefix.ends_with('.')),
+                };
+                let port_matches = match allowed_port {
+                    PortPattern::Exact(p) => port == *p,
+                    PortPattern::Any => true,
+                };
+                scheme == *allowed_scheme && host_matches && port_matches
+            }
+        }
+    }
+}
+
+impl FromStr for OriginPattern {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        if input.eq_ignore_ascii_case(LOCALHOST_SHORTCUT) {
+            return Ok(OriginPattern(PatternKind::Localhost));
+        }
+        if input == "*" {
+            return Err("'*' is not allowed; list origins or use '*.domain' patterns".to_string());
+        }
+
+        let (scheme, rest) = input
+            .split_once("://")
+            .ok_or_else(|| format!("origin '{input}' must include a scheme"))?;
+        let scheme = scheme.to_ascii_lowercase();
+        if scheme != "http" && scheme != "https" {
+            return Err(format!("origin '{input}' must use http or https"));
+        }
+
+        let rest = rest.strip_suffix('/').unwrap_or(rest);
+        let (authority, any_port) = match rest.strip_suffix(":*") {
+            Some(authority) => (authority, true),
+            None => (rest, false),
+        };
+        let (authority, wildcard) = match authority.strip_prefix("*.") {
+            Some(authority) => (authority, true),
+            None => (authority, false),
+        };
+        if authority.contains('*') {
+            return Err(format!(
+                "origin '{input}' may only use '*' as the leftmost label or as the port"
+            ));
+        }
+
+        let url = Url::parse(&format!("{scheme}://{authority}"))
+            .map_err(|e| format!("invalid origin '{input}': {e}"))?;
+        if url.path() != "/" || url.query().is_some() || url.fragment().is_some() {
+            return Err(format!("origin '{input}' must not contain a path"));
+        }
+        if !url.username().is_empty() || url.password().is_some() {
+            return Err(format!("origin '{input}' must not contain credentials"));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| format!("origin '{input}' has no host"))?
+            .to_ascii_lowercase();
+
+        Ok(OriginPattern(PatternKind::Origin {
+            host: if wildcard {
+                HostPattern::Subdomains(host)
+            } else {
+                HostPattern::Exact(host)
+            },
+            port: match (any_port, url.port_or_known_default()) {
+                (true, _) => PortPattern::Any,
+                (false, Some(port)) => PortPattern::Exact(port),
+                (false, None) => return Err(format!("origin '{input}' has no port")),
+            },
+            scheme,
+        }))
+    }
+}
+
+impl fmt::Display for OriginPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            PatternKind::Localhost => f.write_str(LOCALHOST_SHORTCUT),
+            PatternKind::Origin { scheme, host, port } => {
+                match host {
+                    HostPattern::Exact(h) => write!(f, "{scheme}://{h}")?,
+                    HostPattern::Subdomains(h) => write!(f, "{scheme}://*.{h}")?,
+                }
+                match port {
+                    PortPattern::Exact(p) => write!(f, ":{p}"),
+                    PortPattern::Any => f.write_str(":*"),
+                }
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OriginPattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Accepts either a single origin string or a list of them, reporting every
+/// invalid entry at once.
+pub(crate) fn deserialize_origins<'de, D>(deserializer: D) -> Result<Vec<OriginPattern>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    let raw = match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(origin) => vec![origin],
+        OneOrMany::Many(origins) => origins,
+    };
+
+    let (origins, errors): (Vec<_>, Vec<_>) = raw
+        .iter()
+        .map(|origin| origin.parse::<OriginPattern>())
+        .partition(Result::is_ok);
+    if !errors.is_empty() {
+        let errors: Vec<_> = errors.into_iter().filter_map(Result::err).collect();
+        return Err(D::Error::custom(errors.join("; ")));
+    }
+    Ok(origins.into_iter().filter_map(Result::ok).collect())
+}
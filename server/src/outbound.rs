@@ -0,0 +1,244 @@
+use crate::{
+    WS_PREFIX_KEEP_ALIVE, WS_PREFIX_SYSTEM_ACTIVITY, WS_PREFIX_SYSTEM_MEMBERS,
+    WS_PREFIX_SYSTEM_MEMBERS_DELTA, WS_PREFIX_SYSTEM_ROOMS, WS_PREFIX_SYSTEM_ROOMS_DELTA,
+    WS_SLOW_CONSUMER_METRIC,
+    config::{LimitsConfig, OverflowPolicy},
+    metrics::metrics,
+};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+/// Categories of server messages, by how they may be handled under pressure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutboundKind {
+    /// Signals, errors and anything else that must be delivered.
+    Essential,
+    /// Room list snapshot, superseded by the next one.
+    Rooms,
+    /// Member list snapshot, superseded by the next one.
+    Members,
+    /// Versioned room or member change; the session asks for a snapshot
+    /// when one is dropped.
+    Delta,
+    /// Notice that someone joined a room. Its text starts with the member
+    /// name, so it is never inferred from the message and must be passed
+    /// with `Outbox::push_as`.
+    Join,
+    /// What someone in the room is doing, superseded by the next one.
+    Activity,
+    /// Keep-alive message.
+    KeepAlive,
+}
+
+impl OutboundKind {
+    /// Classifies a message by its prefix. Anything without a known prefix,
+    /// including relayed signals whatever their payload, is essential.
+    pub fn of(message: &str) -> Self {
+        if message.starts_with(WS_PREFIX_SYSTEM_ROOMS) {
+            OutboundKind::Rooms
+        } else if message.starts_with(WS_PREFIX_SYSTEM_MEMBERS) {
+            OutboundKind::Members
+        } else if message.starts_with(WS_PREFIX_SYSTEM_ROOMS_DELTA)
+            || message.starts_with(WS_PREFIX_SYSTEM_MEMBERS_DELTA)
+        {
+            OutboundKind::Delta
+        } else if message.starts_with(WS_PREFIX_SYSTEM_ACTIVITY) {
+            OutboundKind::Activity
+        } else if message.starts_with(WS_PREFIX_KEEP_ALIVE) {
+            OutboundKind::KeepAlive
+        } else {
+            OutboundKind::Essential
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutboundKind::Essential => "essential",
+            OutboundKind::Rooms => "rooms",
+            OutboundKind::Members => "members",
+            OutboundKind::Delta => "delta",
+            OutboundKind::Join => "join",
+            OutboundKind::Activity => "activity",
+            OutboundKind::KeepAlive => "keep_alive",
+        }
+    }
+
+    fn coalesces(&self) -> bool {
+        matches!(self, OutboundKind::Rooms | OutboundKind::Members)
+    }
+
+    fn droppable(&self) -> bool {
+        matches!(
+            self,
+            OutboundKind::Members
+                | OutboundKind::Delta
+                | OutboundKind::Join
+                | OutboundKind::Activity
+                | OutboundKind::KeepAlive
+        )
+    }
+}
+
+/// Result of pushing a message onto an `Outbox`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Push {
+    /// Nothing was pending, so the caller should send the message to the
+    /// session itself; handling it drains anything queued behind it.
+    Send(String),
+    /// The message is waiting for the session to catch up.
+    Queued,
+    /// The client is being disconnected and the message was discarded.
+    Dropped,
+    /// The queue went over its limits; the client will be disconnected
+    /// the next time its session drains the queue.
+    Overflow,
+}
+
+struct OutboundQueue {
+    messages: VecDeque<(OutboundKind, String)>,
+    bytes: usize,
+    limits: LimitsConfig,
+    notified: bool,
+    overflowed: bool,
+    resync: bool,
+}
+
+impl OutboundQueue {
+    fn push(&mut self, kind: OutboundKind, message: String) -> Push {
+        if self.overflowed {
+            return Push::Dropped;
+        }
+        if !self.notified {
+            self.notified = true;
+            return Push::Send(message);
+        }
+
+        if kind.coalesces()
+            && let Some(index) = self.messages.iter().position(|(k, _)| *k == kind)
+        {
+            self.remove(index);
+            metrics().increment(
+                WS_SLOW_CONSUMER_METRIC,
+                &[("kind", kind.as_str()), ("action", "coalesced")],
+            );
+        }
+        self.bytes += message.len();
+        self.messages.push_back((kind, message));
+
+        while self.messages.len() > self.limits.max_outbound_messages
+            || self.bytes > self.limits.max_outbound_bytes
+        {
+            let oldest = match self.limits.outbound_overflow {
+                OverflowPolicy::DropOldest => self.messages.iter().position(|(k, _)| k.droppable()),
+                OverflowPolicy::Disconnect => None,
+            };
+            let Some(index) = oldest else {
+                self.overflowed = true;
+                self.messages.clear();
+                self.bytes = 0;
+                metrics().increment(
+                    WS_SLOW_CONSUMER_METRIC,
+                    &[("kind", kind.as_str()), ("action", "disconnected")],
+                );
+                return Push::Overflow;
+            };
+            if let Some(dropped) = self.remove(index) {
+                self.resync |= dropped == OutboundKind::Delta;
+                metrics().increment(
+                    WS_SLOW_CONSUMER_METRIC,
+                    &[("kind", dropped.as_str()), ("action", "dropped")],
+                );
+            }
+        }
+        Push::Queued
+    }
+
+    fn remove(&mut self, index: usize) -> Option<OutboundKind> {
+        self.messages.remove(index).map(|(kind, message)| {
+            self.bytes -= message.len();
+            kind
+        })
+    }
+}
+
+/// Bounded queue of server messages for one session, shared between the
+/// chat server and the session.
+///
+/// A session only runs while its client reads from the socket, so a slow
+/// client would otherwise let messages pile up in its mailbox. Instead, at
+/// most one message is in the mailbox at a time and the rest wait here,
+/// within `max_outbound_messages` and `max_outbound_bytes`.
+#[derive(Clone)]
+pub struct Outbox(Arc<Mutex<OutboundQueue>>);
+
+impl Outbox {
+    pub fn new(limits: &LimitsConfig) -> Self {
+        Outbox(Arc::new(Mutex::new(OutboundQueue {
+            messages: VecDeque::new(),
+            bytes: 0,
+            limits: *limits,
+            notified: false,
+            overflowed: false,
+            resync: false,
+        })))
+    }
+
+    pub fn push(&self, message: String) -> Push {
+        self.push_as(OutboundKind::of(&message), message)
+    }
+
+    /// Like `push`, for messages whose kind can't be told from their prefix.
+    pub fn push_as(&self, kind: OutboundKind, message: String) -> Push {
+        match self.0.lock() {
+            Ok(mut queue) => queue.push(kind, message),
+            Err(e) => {
+                log::error!(target: "Websocket", "Failed to acquire lock on outbox: {e:?}");
+                Push::Dropped
+            }
+        }
+    }
+
+    /// Takes every queued message, or `None` if the client fell too far
+    /// behind and should be disconnected.
+    pub fn drain(&self) -> Option<Vec<String>> {
+        match self.0.lock() {
+            Ok(mut queue) => {
+                queue.notified = false;
+                if queue.overflowed {
+                    return None;
+                }
+                queue.bytes = 0;
+                Some(
+                    queue
+                        .messages
+                        .drain(..)
+                        .map(|(_, message)| message)
+                        .collect(),
+                )
+            }
+            Err(e) => {
+                log::error!(target: "Websocket", "Failed to acquire lock on outbox: {e:?}");
+                Some(Vec::new())
+            }
+        }
+    }
+
+    /// Whether room or member deltas were dropped since the last call.
+    pub fn take_resync(&self) -> bool {
+        self.0
+            .lock()
+            .map(|mut queue| std::mem::take(&mut queue.resync))
+            .unwrap_or(false)
+    }
+
+    /// Messages currently waiting for the session.
+    pub fn len(&self) -> usize {
+        self.0.lock().map(|queue| queue.messages.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{QR_DEFAULT_SIZE, QR_MAX_SIZE, QR_MIN_SIZE, ServerError};
+use image::{ImageFormat, Luma};
+use qrcode::{EcLevel, QrCode, render::svg};
+use serde::Deserialize;
+use std::io::Cursor;
+use url::Url;
+
+/// Query options accepted by the QR code routes.
+#[derive(Debug, Default, Deserialize)]
+pub struct QrOptions {
+    /// Minimum width/height of the rendered image in pixels.
+    pub size: Option<u32>,
+    /// Error-correction level: `L`, `M`, `Q` or `H`.
+    pub ec: Option<String>,
+}
+
+impl QrOptions {
+    /// Returns the requested size, rejecting values outside the allowed range.
+    pub fn size(&self) -> Result<u32, ServerError> {
+        match self.size {
+            None => Ok(QR_DEFAULT_SIZE),
+            Some(size) if (QR_MIN_SIZE..=QR_MAX_SIZE).contains(&size) => Ok(size),
+            Some(size) => Err(ServerError::BadRequest(format!(
+                "QR size {size} out of range ({QR_MIN_SIZE}-{QR_MAX_SIZE})"
+            ))),
+        }
+    }
+
+    /// Returns the requested error-correction level, defaulting to `M`.
+    pub fn ec_level(&self) -> Result<EcLevel, ServerError> {
+        match self.ec.as_deref().map(str::to_ascii_uppercase).as_deref() {
+            None | Some("M") => Ok(EcLevel::M),
+            Some("L") => Ok(EcLevel::L),
+            Some("Q") => Ok(EcLevel::Q),
+            Some("H") => Ok(EcLevel::H),
+            Some(other) => Err(ServerError::BadRequest(format!(
+                "Invalid error-correction level '{other}', expected L, M, Q or H"
+            ))),
+        }
+    }
+}
+
+/// Builds the URL a scanning device should open to join a private session.
+pub fn build_join_url(base_url: &str, code: &str) -> Result<String, ServerError> {
+    let mut url = Url::parse(base_url).map_err(|e| {
+        log::error!(target: "Websocket", "Invalid public base URL '{base_url}': {e}");
+        ServerError::InternalServerError
+    })?;
+    url.path_segments_mut()
+        .map_err(|_| ServerError::InternalServerError)?
+        .pop_if_empty()
+        .extend(["private", code]);
+    Ok(url.to_string())
+}
+
+fn encode(data: &str, options: &QrOptions) -> Result<(QrCode, u32), ServerError> {
+    let size = options.size()?;
+    let code = QrCode::with_error_correction_level(data, options.ec_level()?).map_err(|e| {
+        log::error!(target: "Websocket", "Failed to encode QR code: {e}");
+        ServerError::InternalServerError
+    })?;
+    Ok((code, size))
+}
+
+/// Renders `data` as an SVG document.
+pub fn render_svg(data: &str, options: &QrOptions) -> Result<String, ServerError> {
+    let (code, size) = encode(data, options)?;
+    Ok(code
+        .render::<svg::Color>()
+        .min_dimensions(size, size)
+        .build())
+}
+
+/// Renders `data` as a PNG image.
+pub fn render_png(data: &str, options: &QrOptions) -> Result<Vec<u8>, ServerError> {
+    let (code, size) = encode(data, options)?;
+    let image = code.render::<Luma<u8>>().min_dimensions(size, size).build();
+
+    let mut buffer = Cursor::new(Vec::new());
+    image.write_to(&mut buffer, ImageFormat::Png).map_err(|e| {
+        log::error!(target: "Websocket", "Failed to encode QR PNG: {e}");
+        ServerError::InternalServerError
+    })?;
+    Ok(buffer.into_inner())
+}
@@ -0,0 +1,150 @@
+use crate::{ServerConfig, listener::ClientIpKeyExtractor};
+use actix_governor::{
+    GovernorConfig, GovernorConfigBuilder, KeyExtractor,
+    governor::{
+        clock::{Clock, DefaultClock},
+        middleware::StateInformationMiddleware,
+    },
+};
+use actix_web::{
+    Error, HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    middleware::Next,
+    web::Data,
+};
+use std::sync::{Arc, RwLock};
+
+type LimiterConfig = GovernorConfig<ClientIpKeyExtractor, StateInformationMiddleware>;
+
+/// Per-client HTTP rate limiter whose quota can be changed at runtime.
+///
+/// `actix_governor::Governor` captures its limiter when the app is built, so
+/// this keeps the governor config behind a lock and `enforce_rate_limit`
+/// looks it up on every request instead.
+#[derive(Clone)]
+pub struct RateLimiter {
+    key_extractor: ClientIpKeyExtractor,
+    config: Arc<RwLock<LimiterConfig>>,
+}
+
+impl RateLimiter {
+    pub fn from_config(config: &ServerConfig) -> Result<Self, String> {
+        let key_extractor = ClientIpKeyExtractor {
+            trusted_proxies: config.trusted_proxies.clone(),
+            forwarded_header: config.forwarded_header,
+        };
+        let limiter = Self::build(
+            &key_extractor,
+            config.rate_limit_per_second,
+            config.rate_limit_burst_size,
+        )?;
+        log::debug!(target: "Websocket", "Rate limiting configured: {limiter:?}");
+        Ok(RateLimiter {
+            key_extractor,
+            config: Arc::new(RwLock::new(limiter)),
+        })
+    }
+
+    fn build(
+        key_extractor: &ClientIpKeyExtractor,
+        per_second: u64,
+        burst_size: u32,
+    ) -> Result<LimiterConfig, String> {
+        GovernorConfigBuilder::default()
+            .key_extractor(key_extractor.clone())
+            .requests_per_second(per_second)
+            .burst_size(burst_size)
+            .use_headers()
+            .finish()
+            .ok_or_else(|| {
+                format!("Invalid rate limit: {per_second} per second, burst {burst_size}")
+            })
+    }
+
+    /// Swaps in a new quota. Clients start again with a full burst.
+    pub fn update(&self, per_second: u64, burst_size: u32) -> Result<(), String> {
+        let limiter = Self::build(&self.key_extractor, per_second, burst_size)?;
+        let mut current = self
+            .config
+            .write()
+            .map_err(|e| format!("Failed to acquire lock on rate limiter: {e:?}"))?;
+        *current = limiter;
+        log::info!(
+            target: "Websocket",
+            "Rate limit updated: {per_second} per second, burst {burst_size}"
+        );
+        Ok(())
+    }
+
+    fn current(&self) -> Option<LimiterConfig> {
+        match self.config.read() {
+            Ok(config) => Some(config.clone()),
+            Err(e) => {
+                log::error!(target: "Websocket", "Failed to acquire lock on rate limiter: {e:?}");
+                None
+            }
+        }
+    }
+}
+
+/// Middleware applying the `RateLimiter` quota per client IP, with the same
+/// `x-ratelimit-*` headers `actix_governor` sends. Requests pass through
+/// untouched when no limiter is registered.
+pub async fn enforce_rate_limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let Some(limiter) = req.app_data::<Data<RateLimiter>>().cloned() else {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    };
+    let Some(config) = limiter.current() else {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    };
+
+    let key = limiter.key_extractor.extract(&req)?;
+    match config.limiter().check_key(&key) {
+        Ok(snapshot) => {
+            let burst_size = snapshot.quota().burst_size().get();
+            let remaining = snapshot.remaining_burst_capacity();
+            let mut response = next.call(req).await?;
+            let headers = response.headers_mut();
+            headers.insert(
+                HeaderName::from_static("x-ratelimit-limit"),
+                HeaderValue::from(burst_size),
+            );
+            headers.insert(
+                HeaderName::from_static("x-ratelimit-remaining"),
+                HeaderValue::from(remaining),
+            );
+            Ok(response.map_into_left_body())
+        }
+        Err(negative) => {
+            let wait_time = negative
+                .wait_time_from(DefaultClock::default().now())
+                .as_secs();
+            log::info!(
+                target: "Websocket",
+                "Rate limit exceeded for {key}, quota reset in {wait_time}s"
+            );
+
+            let mut response = HttpResponse::TooManyRequests();
+            response
+                .insert_header(("retry-after", wait_time))
+                .insert_header(("x-ratelimit-after", wait_time))
+                .insert_header(("x-ratelimit-limit", negative.quota().burst_size().get()))
+                .insert_header(("x-ratelimit-remaining", 0));
+            let response = limiter
+                .key_extractor
+                .exceed_rate_limit_response(&negative, response);
+            Ok(req.into_response(response).map_into_right_body())
+        }
+    }
+}
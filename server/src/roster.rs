@@ -0,0 +1,135 @@
+use crate::{
+    WS_PREFIX_SYSTEM_MEMBERS, WS_PREFIX_SYSTEM_MEMBERS_DELTA, WS_PREFIX_SYSTEM_ROOMS,
+    WS_PREFIX_SYSTEM_ROOMS_DELTA,
+    avatar::Avatar,
+    device::DeviceInfo,
+    message::{ClientMetadata, Presence, Room},
+};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// A member as listed to the rest of the room.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct Member {
+    name: String,
+    presence: Presence,
+    device: DeviceInfo,
+    avatar: Avatar,
+}
+
+impl Member {
+    fn of(client: &ClientMetadata) -> Self {
+        Member {
+            name: client.name.clone(),
+            presence: client.presence,
+            device: client.device.clone(),
+            avatar: client.avatar.clone(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct MemberList {
+    version: u64,
+    members: BTreeMap<usize, Member>, // client id -> member
+}
+
+/// Room and member lists of one session as last published to its clients.
+///
+/// Every change bumps the version of the list it touches, so a client that
+/// sees a delta skip a version knows it missed one and can ask for a
+/// snapshot with `/sync`.
+#[derive(Default)]
+pub struct Roster {
+    rooms_version: u64,
+    rooms: BTreeSet<String>,
+    members: HashMap<String, MemberList>,
+}
+
+/// Deltas produced by `Roster::update`.
+#[derive(Debug, Default)]
+pub struct RosterChanges {
+    pub rooms: Option<String>,          // for every client in the session
+    pub members: Vec<(String, String)>, // room name -> delta for its clients
+}
+
+impl Roster {
+    /// `[SystemRooms]` snapshot of the published room list.
+    pub fn rooms_snapshot(&self) -> String {
+        let snapshot = json!({ "version": self.rooms_version, "rooms": self.rooms });
+        format!("{WS_PREFIX_SYSTEM_ROOMS} {snapshot}")
+    }
+
+    /// `[SystemMembers]` snapshot of the published members of `room_name`.
+    pub fn members_snapshot(&self, room_name: &str) -> String {
+        let empty = MemberList::default();
+        let list = self.members.get(room_name).unwrap_or(&empty);
+        let members: Vec<&Member> = list.members.values().collect();
+        let snapshot = json!({ "room": room_name, "version": list.version, "members": members });
+        format!("{WS_PREFIX_SYSTEM_MEMBERS} {snapshot}")
+    }
+
+    /// Brings the roster in line with `rooms`, returning a delta for each
+    /// list that changed since the last update.
+    pub fn update(&mut self, rooms: Option<&HashMap<String, Room>>) -> RosterChanges {
+        let empty = HashMap::new();
+        let rooms = rooms.unwrap_or(&empty);
+        let mut changes = RosterChanges::default();
+
+        let current: BTreeSet<String> = rooms.keys().cloned().collect();
+        if current != self.rooms {
+            self.rooms_version += 1;
+            let delta = json!({
+                "version": self.rooms_version,
+                "added": current.difference(&self.rooms).collect::<Vec<_>>(),
+                "removed": self.rooms.difference(&current).collect::<Vec<_>>(),
+            });
+            changes.rooms = Some(format!("{WS_PREFIX_SYSTEM_ROOMS_DELTA} {delta}"));
+            self.rooms = current;
+        }
+
+        // Nobody is left to tell about rooms that are gone.
+        self.members.retain(|name, _| rooms.contains_key(name));
+        for (room_name, room) in rooms {
+            let list = self.members.entry(room_name.clone()).or_default();
+            let current: BTreeMap<usize, Member> = room
+                .iter()
+                .map(|(id, client)| (*id, Member::of(client)))
+                .collect();
+            let mut joined = Vec::new();
+            let mut updated = Vec::new();
+            for (id, member) in &current {
+                match list.members.get(id) {
+                    None => joined.push(member),
+                    Some(published) if published != member => updated.push(member),
+                    Some(_) => {}
+                }
+            }
+            let left: Vec<&String> = list
+                .members
+                .iter()
+                .filter(|(id, _)| !current.contains_key(id))
+                .map(|(_, member)| &member.name)
+                .collect();
+            if joined.is_empty() && left.is_empty() && updated.is_empty() {
+                continue;
+            }
+
+            list.version += 1;
+            let delta = json!({
+                "room": room_name,
+                "version": list.version,
+                "joined": joined,
+                "left": left,
+                "updated": updated,
+            });
+            changes.members.push((
+                room_name.clone(),
+                format!("{WS_PREFIX_SYSTEM_MEMBERS_DELTA} {delta}"),
+            ));
+            list.members = current;
+        }
+        changes
+    }
+}
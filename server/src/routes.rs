@@ -1,9 +1,27 @@
 use crate::{
-    CONTENT_TYPE_TEXT_PLAIN, MIN_USER_AGENT_LENGTH, SESSION_CODE_LENGTH, ServerConfig, ServerError,
-    SessionStore, consts::MAX_SESSIONS, session_store::SessionData,
+    CONTENT_TYPE_PNG, CONTENT_TYPE_PROMETHEUS, CONTENT_TYPE_SVG, CONTENT_TYPE_TEXT_PLAIN,
+    ORIGIN_REJECTION_METRIC, SAFE_CHARSET, SESSION_CODE_LENGTH, ServerConfig, ServerError,
+    SessionStore,
+    auth::{Identity, Scope},
+    client_ip::resolve_client_ip,
+    config::SessionGroupingConfig,
+    config_reload::ConfigReloader,
+    device::DeviceInfo,
+    ip_filter::{IpFilter, enforce_ip_filter},
+    jwt::{JwtIdentity, JwtVerifier},
+    listener::peer_ip,
+    message::PeerInfo,
+    metrics::metrics,
+    mtls::ClientCert,
+    qr::{QrOptions, build_join_url, render_png, render_svg},
+    screening::{ScreeningDecision, ScreeningRule},
+    session_store::SessionData,
+};
+use actix_web::{
+    Error, HttpRequest, HttpResponse, Responder, get, http::header, middleware::from_fn, post, web,
 };
-use actix_web::{Error, HttpRequest, HttpResponse, Responder, get, http::header, web};
 use serde_json::json;
+use std::net::{IpAddr, Ipv4Addr};
 use uuid::Uuid;
 
 // -----------------------------------------------------
@@ -29,8 +47,18 @@ pub async fn health() -> impl Responder {
 // -----------------------------------------------------
 // Create Session route
 // -----------------------------------------------------
-#[get("/create-session")]
-pub async fn create_session(store: web::Data<SessionStore>) -> Result<HttpResponse, ServerError> {
+#[get("/create-session", wrap = "from_fn(enforce_ip_filter)")]
+pub async fn create_session(
+    req: HttpRequest,
+    store: web::Data<SessionStore>,
+    config: web::Data<ServerConfig>,
+) -> Result<HttpResponse, ServerError> {
+    config
+        .client_auth
+        .check(&req, config.client_auth.create_session, "create_session")?;
+    if let Some(identity) = config.auth.authorize(&req, Scope::CreateSession)? {
+        log::debug!(target: "Websocket", "Session creation requested by '{identity}'");
+    }
     let code = SessionStore::generate_random_code(SESSION_CODE_LENGTH);
     let new_uuid = Uuid::new_v4();
     {
@@ -44,10 +72,11 @@ pub async fn create_session(store: web::Data<SessionStore>) -> Result<HttpRespon
                 return Err(ServerError::InternalServerError);
             }
         };
-        if map.len() >= MAX_SESSIONS {
+        let max_sessions = store.limits().max_sessions;
+        if map.len() >= max_sessions {
             log::warn!(
                 target: "Websocket",
-                "Max sessions limit reached ({MAX_SESSIONS}), rejecting session creation"
+                "Max sessions limit reached ({max_sessions}), rejecting session creation"
             );
             return Err(ServerError::BadRequest(
                 "Server capacity reached. Try again later.".to_string(),
@@ -66,10 +95,47 @@ pub async fn create_session(store: web::Data<SessionStore>) -> Result<HttpRespon
         .json(json!({ "code": code })))
 }
 
+// -----------------------------------------------------
+// Session QR code routes
+// -----------------------------------------------------
+#[get("/session/{code}/qr.svg")]
+pub async fn session_qr_svg(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<QrOptions>,
+    store: web::Data<SessionStore>,
+    config: web::Data<ServerConfig>,
+) -> Result<HttpResponse, ServerError> {
+    config.auth.authorize(&req, Scope::Join)?;
+    let join_url = session_join_url(&req, &path.into_inner(), &store, &config)?;
+    let svg = render_svg(&join_url, &query)?;
+    Ok(HttpResponse::Ok()
+        .content_type(CONTENT_TYPE_SVG)
+        .insert_header(header::CacheControl(vec![header::CacheDirective::NoStore]))
+        .body(svg))
+}
+
+#[get("/session/{code}/qr.png")]
+pub async fn session_qr_png(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<QrOptions>,
+    store: web::Data<SessionStore>,
+    config: web::Data<ServerConfig>,
+) -> Result<HttpResponse, ServerError> {
+    config.auth.authorize(&req, Scope::Join)?;
+    let join_url = session_join_url(&req, &path.into_inner(), &store, &config)?;
+    let png = render_png(&join_url, &query)?;
+    Ok(HttpResponse::Ok()
+        .content_type(CONTENT_TYPE_PNG)
+        .insert_header(header::CacheControl(vec![header::CacheDirective::NoStore]))
+        .body(png))
+}
+
 // -----------------------------------------------------
 // Chat WS route (non-private)
 // -----------------------------------------------------
-#[get("/ws")]
+#[get("/ws", wrap = "from_fn(enforce_ip_filter)")]
 pub async fn chat_ws(
     req: HttpRequest,
     stream: web::Payload,
@@ -78,27 +144,49 @@ pub async fn chat_ws(
 ) -> Result<HttpResponse, ServerError> {
     // Validate that this is a proper WebSocket connection
     validate_websocket_headers(&req)?;
+    check_websocket_origin(&req, &config, "public")?;
+    let identity = config.auth.authorize(&req, Scope::Join)?;
+    let jwt_identity = verify_jwt(&req)?;
+    let client_cert = config
+        .client_auth
+        .check(&req, config.client_auth.websocket, "websocket")?;
 
-    let is_dev_mode = ServerConfig::is_dev_env();
-
-    let ip_str = get_client_ip(&req, is_dev_mode)
+    let ip_str = get_client_ip(&req, &config)
         .map_err(|e| ServerError::BadRequest(format!("Failed to get client IP: {e}")))?;
-    if check_suspicious_connection(&req, &ip_str) {
-        return Err(ServerError::Forbidden);
-    }
+    let (client_ip, ip_cap_exempt) = screen_connection(&req, &ip_str, &store, &config)?;
 
-    let session_key = create_session_key(&req, &ip_str);
+    let session_key = create_session_key(&req, &ip_str, &config.session_grouping);
 
-    log::debug!(target: "Websocket", "Connection request - IP: {ip_str}, Session Key: {session_key}");
+    log::debug!(
+        target: "Websocket",
+        "Connection request - IP: {ip_str}, Session Key: {session_key}, Identity: {}",
+        identity.as_ref().map_or("anonymous", |i| i.subject.as_str())
+    );
+    let peer = peer_info(
+        &req,
+        client_ip,
+        ip_cap_exempt,
+        identity,
+        jwt_identity,
+        client_cert,
+    );
     store
-        .start_websocket(config.get_ref(), &req, stream, &session_key, false, false)
+        .start_websocket(
+            config.get_ref(),
+            &req,
+            stream,
+            &session_key,
+            false,
+            false,
+            peer,
+        )
         .map_err(|e| ServerError::BadRequest(format!("WebSocket connection failed: {e}")))
 }
 
 // -----------------------------------------------------
 // Private Chat WS route
 // -----------------------------------------------------
-#[get("/ws/{code}")]
+#[get("/ws/{code}", wrap = "from_fn(enforce_ip_filter)")]
 pub async fn private_chat_ws(
     req: HttpRequest,
     stream: web::Payload,
@@ -108,6 +196,12 @@ pub async fn private_chat_ws(
 ) -> Result<HttpResponse, ServerError> {
     // Validate that this is a proper WebSocket connection
     validate_websocket_headers(&req)?;
+    check_websocket_origin(&req, &config, "private")?;
+    let identity = config.auth.authorize(&req, Scope::Join)?;
+    let jwt_identity = verify_jwt(&req)?;
+    let client_cert = config
+        .client_auth
+        .check(&req, config.client_auth.websocket, "websocket")?;
 
     let code = path.into_inner();
     log::debug!(target: "Websocket", "Received session code: {code}");
@@ -118,70 +212,311 @@ pub async fn private_chat_ws(
         ));
     }
 
+    let ip_str = get_client_ip(&req, &config)
+        .map_err(|e| ServerError::BadRequest(format!("Failed to get client IP: {e}")))?;
+    let (client_ip, ip_cap_exempt) = screen_connection(&req, &ip_str, &store, &config)?;
+
+    log::debug!(
+        target: "Websocket",
+        "Private connection request - IP: {ip_str}, Identity: {}",
+        identity.as_ref().map_or("anonymous", |i| i.subject.as_str())
+    );
+    let peer = peer_info(
+        &req,
+        client_ip,
+        ip_cap_exempt,
+        identity,
+        jwt_identity,
+        client_cert,
+    );
     store
-        .start_websocket(config.get_ref(), &req, stream, &code, true, true)
+        .start_websocket(config.get_ref(), &req, stream, &code, true, true, peer)
         .map_err(|e| ServerError::BadRequest(format!("WebSocket connection failed: {e}")))
 }
 
+// -----------------------------------------------------
+// Admin routes
+// -----------------------------------------------------
+#[post("/admin/ip-rules/reload")]
+pub async fn reload_ip_rules(
+    req: HttpRequest,
+    config: web::Data<ServerConfig>,
+    filter: web::Data<IpFilter>,
+) -> Result<HttpResponse, ServerError> {
+    require_admin(&req, &config)?;
+
+    let rules = filter.reload().map_err(|e| {
+        log::error!(target: "Websocket", "{e}, keeping previous rules");
+        ServerError::BadRequest(e)
+    })?;
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::json())
+        .json(json!({ "allow": rules.allow, "deny": rules.deny })))
+}
+
+#[post("/admin/config/reload")]
+pub async fn reload_config(
+    req: HttpRequest,
+    config: web::Data<ServerConfig>,
+    reloader: web::Data<ConfigReloader>,
+) -> Result<HttpResponse, ServerError> {
+    require_admin(&req, &config)?;
+
+    let report = reloader.reload().map_err(ServerError::BadRequest)?;
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::json())
+        .json(report))
+}
+
+#[get("/admin/config")]
+pub async fn admin_config(
+    req: HttpRequest,
+    config: web::Data<ServerConfig>,
+    reloader: web::Data<ConfigReloader>,
+) -> Result<HttpResponse, ServerError> {
+    require_admin(&req, &config)?;
+
+    let current = reloader.current();
+    let origins: Vec<String> = current
+        .cors_allowed_origins
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::json())
+        .json(json!({
+            "log_level": current.log_level,
+            "rate_limit_per_second": current.rate_limit_per_second,
+            "rate_limit_burst_size": current.rate_limit_burst_size,
+            "cors_allowed_origins": origins,
+            "limits": current.limits,
+            "last_reload": reloader.last_report(),
+        })))
+}
+
+#[get("/admin/metrics")]
+pub async fn admin_metrics(
+    req: HttpRequest,
+    config: web::Data<ServerConfig>,
+) -> Result<HttpResponse, ServerError> {
+    require_admin(&req, &config)?;
+    Ok(HttpResponse::Ok()
+        .content_type(CONTENT_TYPE_PROMETHEUS)
+        .body(metrics().render()))
+}
+
 // -----------------------------------------------------
 // Helper functions for WebSocket connections
 // -----------------------------------------------------
-// Helper function to get client IP based on environment
-fn get_client_ip(req: &HttpRequest, is_dev_mode: bool) -> Result<String, Error> {
-    if !is_dev_mode {
-        log::info!(target: "Websocket", "Production mode detected, checking headers for IP");
-        req.headers()
-            .get("X-Forwarded-For")
-            .and_then(|v| v.to_str().ok())
-            .and_then(|s| s.split(',').next().map(str::trim))
-            .or_else(|| req.headers()
-                .get("X-Real-IP")
-                .and_then(|v| v.to_str().ok())
-                .map(str::trim))
-            .map(|ip| ip.to_string())
-            .ok_or_else(|| {
-                log::warn!(target: "Websocket", "Production connection attempt without proper headers");
-                actix_web::error::ErrorForbidden("Access denied: Missing required headers")
-            })
-    } else {
-        log::info!(target: "Websocket", "Development mode detected, using direct peer IP");
-        req.peer_addr()
-            .map(|peer| {
-                let peer_ip = peer.ip().to_string();
-                log::info!(target: "Websocket", "Using direct peer IP in dev mode: {peer_ip}");
-                peer_ip
-            })
-            .ok_or_else(|| {
-                log::warn!(target: "Websocket", "Development connection with no determinable IP");
-                actix_web::error::ErrorBadRequest("Client IP could not be determined")
-            })
+// Helper function to get the client IP, honouring only trusted proxies
+fn get_client_ip(req: &HttpRequest, config: &ServerConfig) -> Result<String, Error> {
+    let peer_ip = peer_ip(req);
+    resolve_client_ip(
+        peer_ip,
+        req.headers(),
+        &config.trusted_proxies,
+        config.forwarded_header,
+    )
+    .map(|ip| {
+        if Some(ip) != peer_ip {
+            log::debug!(
+                target: "Websocket",
+                "Resolved client IP {ip} via trusted proxy {peer_ip:?}"
+            );
+        }
+        ip.to_string()
+    })
+    .ok_or_else(|| {
+        log::warn!(target: "Websocket", "Connection with no determinable IP");
+        actix_web::error::ErrorBadRequest("Client IP could not be determined")
+    })
+}
+
+// Helper function to apply the admin client certificate policy, then restrict
+// admin routes to the `admin` scope when auth is enabled, or to callers on the
+// local machine otherwise
+fn require_admin(req: &HttpRequest, config: &ServerConfig) -> Result<(), ServerError> {
+    if let Some(cert) = config
+        .client_auth
+        .check(req, config.client_auth.admin, "admin")?
+    {
+        log::info!(target: "Websocket", "Admin request to {} from '{}'", req.path(), cert.subject);
+    }
+    if config.auth.enabled {
+        return config.auth.authorize(req, Scope::Admin).map(|_| ());
+    }
+
+    let peer_ip = peer_ip(req);
+    match resolve_client_ip(
+        peer_ip,
+        req.headers(),
+        &config.trusted_proxies,
+        config.forwarded_header,
+    ) {
+        Some(ip) if ip.is_loopback() => Ok(()),
+        ip => {
+            log::warn!(target: "Websocket", "Rejected admin request to {} from {ip:?}", req.path());
+            Err(ServerError::Forbidden)
+        }
     }
 }
 
-// Helper function to create a session key
-fn create_session_key(req: &HttpRequest, ip_str: &str) -> String {
+// Helper function to build the join URL for a private session code
+fn session_join_url(
+    req: &HttpRequest,
+    code: &str,
+    store: &SessionStore,
+    config: &ServerConfig,
+) -> Result<String, ServerError> {
+    if code.len() != SESSION_CODE_LENGTH || !code.bytes().all(|b| SAFE_CHARSET.contains(&b)) {
+        log::debug!(target: "Websocket", "Rejecting QR request for malformed code: {code}");
+        return Err(ServerError::BadRequest("Invalid session code".to_string()));
+    }
+    if !store.is_active_private_code(code) {
+        log::debug!(target: "Websocket", "QR requested for unknown session code: {code}");
+        return Err(ServerError::NotFound);
+    }
+
+    let base_url = match &config.public_base_url {
+        Some(base_url) => base_url.clone(),
+        None => {
+            let conn = req.connection_info();
+            format!("{}://{}", conn.scheme(), conn.host())
+        }
+    };
+    build_join_url(&base_url, code)
+}
+
+// Helper function to create a session key from the host and the client's address group
+fn create_session_key(req: &HttpRequest, ip_str: &str, grouping: &SessionGroupingConfig) -> String {
     let host = req
         .headers()
         .get("Host")
         .and_then(|h| h.to_str().ok())
         .unwrap_or("unknown_host");
 
-    format!("{host}:{ip_str}")
+    let group = match ip_str.parse::<IpAddr>() {
+        Ok(ip) => grouping.group_for(ip),
+        Err(_) => {
+            log::debug!(target: "Websocket", "Client IP '{ip_str}' is not an address, using it as-is");
+            ip_str.to_string()
+        }
+    };
+
+    format!("{host}:{group}")
 }
 
-// Helper function to check for suspicious connections
-fn check_suspicious_connection(req: &HttpRequest, ip_str: &str) -> bool {
-    let user_agent = req
-        .headers()
-        .get("User-Agent")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("unknown");
+// Helper function to verify a JWT when a verifier is registered
+fn verify_jwt(req: &HttpRequest) -> Result<Option<JwtIdentity>, ServerError> {
+    match req.app_data::<web::Data<JwtVerifier>>() {
+        Some(verifier) => verifier.identify(req),
+        None => Ok(None),
+    }
+}
+
+// Helper function to combine what is known about a connecting client
+fn peer_info(
+    req: &HttpRequest,
+    client_ip: Option<IpAddr>,
+    ip_cap_exempt: bool,
+    identity: Option<Identity>,
+    jwt_identity: Option<JwtIdentity>,
+    client_cert: Option<ClientCert>,
+) -> PeerInfo {
+    let cert_subject = client_cert.map(|cert| cert.subject);
+    let (peer_id, display_name) = match jwt_identity {
+        Some(jwt) => (Some(jwt.peer_id), jwt.display_name),
+        None => (
+            cert_subject
+                .clone()
+                .or_else(|| identity.as_ref().map(|i| i.subject.clone())),
+            None,
+        ),
+    };
+    PeerInfo {
+        client_ip,
+        identity,
+        cert_subject,
+        peer_id,
+        display_name,
+        device: req
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|ua| ua.to_str().ok())
+            .map(DeviceInfo::from_user_agent)
+            .unwrap_or_default(),
+        ip_cap_exempt,
+    }
+}
+
+// Helper function to reject cross-site WebSocket upgrades
+fn check_websocket_origin(
+    req: &HttpRequest,
+    config: &ServerConfig,
+    route: &str,
+) -> Result<(), ServerError> {
+    let policy = &config.websocket_origin;
+    if !policy.enforce {
+        return Ok(());
+    }
+
+    // Prefer the live origins when the config can be reloaded.
+    let allowed = |origin| match req.app_data::<web::Data<ConfigReloader>>() {
+        Some(reloader) => reloader.check_origin(origin),
+        None => config.check_origin(origin),
+    };
+    let reason = match req.headers().get(header::ORIGIN) {
+        Some(origin) if allowed(origin) => return Ok(()),
+        Some(origin) => {
+            log::warn!(target: "Websocket", "Rejected WebSocket upgrade from origin {origin:?}");
+            "mismatch"
+        }
+        None => {
+            let user_agent = req
+                .headers()
+                .get(header::USER_AGENT)
+                .and_then(|v| v.to_str().ok());
+            let native = user_agent
+                .is_some_and(|ua| policy.native_user_agents.iter().any(|p| p.0.is_match(ua)));
+            if policy.allow_missing || native {
+                return Ok(());
+            }
+            log::warn!(target: "Websocket", "Rejected WebSocket upgrade without Origin (UA: {user_agent:?})");
+            "missing"
+        }
+    };
+
+    metrics().increment(
+        ORIGIN_REJECTION_METRIC,
+        &[("route", route), ("reason", reason)],
+    );
+    Err(ServerError::Forbidden)
+}
+
+// Helper function to run the configured connection screening rules, returning
+// the client IP and whether a bypass key exempts it from the per-IP cap. Clients
+// over the cap are left to `SessionStore::start_websocket`, which closes them
+// with the reason.
+fn screen_connection(
+    req: &HttpRequest,
+    ip_str: &str,
+    store: &SessionStore,
+    config: &ServerConfig,
+) -> Result<(Option<IpAddr>, bool), ServerError> {
+    let ip = ip_str.parse::<IpAddr>().ok();
+    let active_connections = ip.map_or(0, |ip| store.connections_for_ip(ip));
+    let client_ip = ip.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
 
-    if user_agent.len() < MIN_USER_AGENT_LENGTH || user_agent.to_lowercase().contains("bot") {
-        log::warn!(target: "Websocket", "Suspicious connection rejected - IP: {ip_str}, UA: {user_agent}");
-        return true;
+    match config.screening.screen(
+        req,
+        client_ip,
+        active_connections,
+        store.limits().max_connections_per_ip,
+    ) {
+        ScreeningDecision::Allow(rule) => Ok((ip, rule == ScreeningRule::ApiKeyBypass)),
+        ScreeningDecision::Reject(ScreeningRule::IpConcurrency, _) => Ok((ip, false)),
+        ScreeningDecision::Reject(..) => Err(ServerError::Forbidden),
     }
-    false
 }
 
 // Helper function to validate WebSocket connection headers
@@ -0,0 +1,169 @@
+use crate::{config::ScreeningConfig, metrics::metrics};
+use actix_web::HttpRequest;
+use regex::Regex;
+use serde::{Deserialize, Deserializer, de::Error as _};
+use std::{fmt, net::IpAddr};
+
+pub const SCREENING_METRIC: &str = "pastepoint_screening_decisions_total";
+
+/// A User-Agent regular expression compiled when the configuration loads.
+#[derive(Clone, Debug)]
+pub struct UaPattern(pub Regex);
+
+impl<'de> Deserialize<'de> for UaPattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern)
+            .map(UaPattern)
+            .map_err(|e| D::Error::custom(format!("invalid User-Agent pattern '{pattern}': {e}")))
+    }
+}
+
+/// The screening rule that produced a decision.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScreeningRule {
+    ApiKeyBypass,
+    UserAgentAllow,
+    UserAgentLength,
+    UserAgentDeny,
+    RequiredHeader,
+    IpConcurrency,
+    Default,
+}
+
+impl ScreeningRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScreeningRule::ApiKeyBypass => "api_key_bypass",
+            ScreeningRule::UserAgentAllow => "user_agent_allow",
+            ScreeningRule::UserAgentLength => "user_agent_length",
+            ScreeningRule::UserAgentDeny => "user_agent_deny",
+            ScreeningRule::RequiredHeader => "required_header",
+            ScreeningRule::IpConcurrency => "ip_concurrency",
+            ScreeningRule::Default => "default",
+        }
+    }
+}
+
+impl fmt::Display for ScreeningRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Outcome of screening a connection attempt.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScreeningDecision {
+    Allow(ScreeningRule),
+    Reject(ScreeningRule, String),
+}
+
+impl ScreeningDecision {
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, ScreeningDecision::Allow(_))
+    }
+}
+
+// Compares secrets without short-circuiting on the first differing byte.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+impl ScreeningConfig {
+    /// Runs the configured rules against a connection attempt from `ip`,
+    /// which currently holds `active_connections` WebSockets out of
+    /// `max_connections_per_ip`. Every decision is logged and counted per
+    /// rule.
+    pub fn screen(
+        &self,
+        req: &HttpRequest,
+        ip: IpAddr,
+        active_connections: usize,
+        max_connections_per_ip: usize,
+    ) -> ScreeningDecision {
+        let decision = self.evaluate(req, active_connections, max_connections_per_ip);
+        match &decision {
+            ScreeningDecision::Allow(rule) => {
+                log::debug!(target: "Websocket", "Screening allowed {ip} (rule: {rule})");
+                metrics().increment(
+                    SCREENING_METRIC,
+                    &[("rule", rule.as_str()), ("decision", "allow")],
+                );
+            }
+            ScreeningDecision::Reject(rule, reason) => {
+                log::warn!(target: "Websocket", "Screening rejected {ip} (rule: {rule}): {reason}");
+                metrics().increment(
+                    SCREENING_METRIC,
+                    &[("rule", rule.as_str()), ("decision", "reject")],
+                );
+            }
+        }
+        decision
+    }
+
+    fn evaluate(
+        &self,
+        req: &HttpRequest,
+        active_connections: usize,
+        max_connections_per_ip: usize,
+    ) -> ScreeningDecision {
+        let headers = req.headers();
+
+        if !self.bypass_api_keys.is_empty()
+            && let Some(key) = headers
+                .get(self.api_key_header.as_str())
+                .and_then(|v| v.to_str().ok())
+            && self
+                .bypass_api_keys
+                .iter()
+                .any(|allowed| constant_time_eq(allowed.as_bytes(), key.as_bytes()))
+        {
+            return ScreeningDecision::Allow(ScreeningRule::ApiKeyBypass);
+        }
+
+        let user_agent = headers.get("User-Agent").and_then(|v| v.to_str().ok());
+        let ua_allowed =
+            user_agent.is_some_and(|ua| self.user_agent_allow.iter().any(|p| p.0.is_match(ua)));
+
+        if !ua_allowed && let Some(ua) = user_agent {
+            if ua.len() < self.min_user_agent_length {
+                return ScreeningDecision::Reject(
+                    ScreeningRule::UserAgentLength,
+                    format!("User-Agent '{ua}' is too short"),
+                );
+            }
+            if let Some(pattern) = self.user_agent_deny.iter().find(|p| p.0.is_match(ua)) {
+                return ScreeningDecision::Reject(
+                    ScreeningRule::UserAgentDeny,
+                    format!("User-Agent '{ua}' matches '{}'", pattern.0.as_str()),
+                );
+            }
+        }
+
+        if let Some(missing) = self
+            .required_headers
+            .iter()
+            .find(|name| !headers.contains_key(name.as_str()))
+        {
+            return ScreeningDecision::Reject(
+                ScreeningRule::RequiredHeader,
+                format!("Missing required header '{missing}'"),
+            );
+        }
+
+        if active_connections >= max_connections_per_ip {
+            return ScreeningDecision::Reject(
+                ScreeningRule::IpConcurrency,
+                format!(
+                    "{active_connections} concurrent connections (max {max_connections_per_ip})"
+                ),
+            );
+        }
+
+        if ua_allowed {
+            ScreeningDecision::Allow(ScreeningRule::UserAgentAllow)
+        } else {
+            ScreeningDecision::Allow(ScreeningRule::Default)
+        }
+    }
+}
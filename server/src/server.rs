@@ -1,13 +1,63 @@
 use crate::{
-    CLEANUP_INTERVAL, WS_PREFIX_SYSTEM_MEMBERS, WS_PREFIX_SYSTEM_ROOMS,
-    consts::{MAX_ROOMS_PER_SESSION, MAX_SESSIONS},
-    message::{ChatMessage, Client, ClientMetadata, Room, WsChatServer},
+    CLEANUP_INTERVAL, WS_CONNECTION_LIMIT_METRIC, WS_PREFIX_SYSTEM_ACTIVITY,
+    avatar::Avatar,
+    config::LimitsConfig,
+    device::DeviceInfo,
+    message::{Activity, ChatMessage, Client, ClientMetadata, Presence, Room, WsChatServer},
+    metrics::metrics,
+    outbound::{OutboundKind, Outbox, Push},
+    roster::Roster,
 };
 use actix::prelude::*;
 use rand::{RngExt, rng};
+use serde_json::json;
 use std::collections::{HashMap, hash_map::Entry::Vacant};
 
+impl ClientMetadata {
+    pub fn new(recipient: Client, name: String, outbox: Outbox) -> Self {
+        ClientMetadata {
+            recipient,
+            avatar: Avatar::new(&name, &name),
+            name,
+            outbox,
+            presence: Presence::default(),
+            device: DeviceInfo::default(),
+        }
+    }
+
+    /// Queues `message` on the client's outbox, waking its session if
+    /// nothing else was pending. Returns false if the client is gone.
+    pub fn deliver(&self, message: String) -> bool {
+        self.deliver_as(OutboundKind::of(&message), message)
+    }
+
+    /// Like `deliver`, for messages whose kind can't be told from their
+    /// prefix.
+    pub fn deliver_as(&self, kind: OutboundKind, message: String) -> bool {
+        if !self.recipient.connected() {
+            return false;
+        }
+        match self.outbox.push_as(kind, message) {
+            Push::Send(message) => self.recipient.do_send(ChatMessage(message)),
+            Push::Queued | Push::Dropped => {}
+            Push::Overflow => log::warn!(
+                target: "Websocket",
+                "Client {} is not keeping up with messages, disconnecting",
+                self.name
+            ),
+        }
+        true
+    }
+}
+
 impl WsChatServer {
+    pub fn new(limits: LimitsConfig) -> Self {
+        WsChatServer {
+            limits,
+            ..WsChatServer::default()
+        }
+    }
+
     pub fn is_valid_room_name(name: &str) -> bool {
         let trimmed = name.trim();
         !trimmed.is_empty()
@@ -25,6 +75,14 @@ impl WsChatServer {
         Some(room)
     }
 
+    /// Returns true if `room_name` cannot take another client.
+    pub fn is_room_full(&self, session_id: &str, room_name: &str) -> bool {
+        self.rooms
+            .get(session_id)
+            .and_then(|rooms| rooms.get(room_name))
+            .is_some_and(|room| room.len() >= self.limits.max_clients_per_room)
+    }
+
     pub fn add_client_to_room(
         &mut self,
         session_id: &str,
@@ -32,18 +90,44 @@ impl WsChatServer {
         id: Option<usize>,
         client: Client,
         name: String,
+    ) -> Option<usize> {
+        let outbox = Outbox::new(&self.limits);
+        self.add_member(
+            session_id,
+            room_name,
+            id,
+            ClientMetadata::new(client, name, outbox),
+        )
+    }
+
+    /// Like `add_client_to_room`, for a client that brings its own outbox.
+    pub fn add_member(
+        &mut self,
+        session_id: &str,
+        room_name: &str,
+        id: Option<usize>,
+        member: ClientMetadata,
     ) -> Option<usize> {
         let id = id.unwrap_or_else(|| rng().random_range(0..usize::MAX));
 
         if let Some(room) = self.rooms.get_mut(session_id)
             && let Some(existing_room) = room.get_mut(room_name)
         {
+            if !existing_room.contains_key(&id)
+                && existing_room.len() >= self.limits.max_clients_per_room
+            {
+                log::warn!(
+                    target: "Websocket",
+                    "Room '{room_name}' in session {session_id} is full ({} clients)",
+                    self.limits.max_clients_per_room
+                );
+                metrics().increment(WS_CONNECTION_LIMIT_METRIC, &[("limit", "room")]);
+                return None;
+            }
             return if let Vacant(e) = existing_room.entry(id) {
                 log::debug!(target: "Websocket", "Adding client to room: {}", room_name);
-                e.insert(ClientMetadata {
-                    recipient: client,
-                    name,
-                });
+                e.insert(member);
+                self.changed.insert(session_id.to_owned());
                 Some(id)
             } else {
                 log::debug!(
@@ -57,41 +141,58 @@ impl WsChatServer {
         }
 
         if let Some(rooms) = self.rooms.get(session_id)
-            && rooms.len() >= MAX_ROOMS_PER_SESSION
+            && rooms.len() >= self.limits.max_rooms_per_session
         {
             log::warn!(
                 target: "Websocket",
-                "Session {session_id} exceeded max rooms limit ({MAX_ROOMS_PER_SESSION})"
+                "Session {session_id} exceeded max rooms limit ({})",
+                self.limits.max_rooms_per_session
             );
             return None;
         }
 
-        if !self.rooms.contains_key(session_id) && self.rooms.len() >= MAX_SESSIONS {
+        if !self.rooms.contains_key(session_id) && self.rooms.len() >= self.limits.max_sessions {
             log::warn!(
                 target: "Websocket",
-                "Max sessions limit reached ({MAX_SESSIONS}), rejecting new session"
+                "Max sessions limit reached ({}), rejecting new session",
+                self.limits.max_sessions
             );
             return None;
         }
 
         let mut room: Room = HashMap::new();
-        room.insert(
-            id,
-            ClientMetadata {
-                recipient: client,
-                name,
-            },
-        );
+        room.insert(id, member);
 
         self.rooms
             .entry(session_id.to_string())
             .or_default()
             .insert(room_name.to_owned(), room);
 
-        self.broadcast_room_list(session_id);
+        self.mark_changed(session_id);
         Some(id)
     }
 
+    /// `name`, or `name (2)`, `name (3)` and so on if a client in the
+    /// session already goes by it. Signals are addressed by name, so two
+    /// devices of the same user must not share one.
+    pub fn unique_name(&self, session_id: &str, name: &str) -> String {
+        let taken = |candidate: &str| {
+            self.rooms.get(session_id).is_some_and(|rooms| {
+                rooms
+                    .values()
+                    .flat_map(|room| room.values())
+                    .any(|client| client.name == candidate)
+            })
+        };
+        if !taken(name) {
+            return name.to_owned();
+        }
+        (2..)
+            .map(|n| format!("{name} ({n})"))
+            .find(|candidate| !taken(candidate))
+            .expect("some suffix is free")
+    }
+
     pub fn send_join_message(
         &mut self,
         session_id: &str,
@@ -109,11 +210,7 @@ impl WsChatServer {
 
             for id in client_ids {
                 if let Some(client) = room.get(&id) {
-                    if client
-                        .recipient
-                        .try_send(ChatMessage(msg.to_owned()))
-                        .is_ok()
-                    {
+                    if client.deliver_as(OutboundKind::Join, msg.to_owned()) {
                         log::debug!(
                             target: "Websocket",
                             "Join Message sent to client {id}, staying in room: {room_name}"
@@ -138,51 +235,185 @@ impl WsChatServer {
         }
     }
 
-    pub fn broadcast_room_list(&self, session_id: &str) {
-        if let Some(users) = self.rooms.get(session_id) {
-            let room_list = users.keys().cloned().collect::<Vec<String>>().join(", ");
-            let message = format!("{WS_PREFIX_SYSTEM_ROOMS} {room_list}");
+    /// Updates the presence of client `id`, marking the session changed if
+    /// it differs. Returns false if the client is not in the room.
+    pub fn set_presence(
+        &mut self,
+        session_id: &str,
+        room_name: &str,
+        id: usize,
+        presence: Presence,
+    ) -> bool {
+        let Some(client) = self.member_mut(session_id, room_name, id) else {
+            return false;
+        };
+        if client.presence != presence {
+            log::debug!(
+                target: "Websocket",
+                "{} in {session_id} is now {}",
+                client.name,
+                presence.as_str()
+            );
+            client.presence = presence;
+            self.mark_changed(session_id);
+        }
+        true
+    }
 
-            for room in users.values() {
-                for client in room.values() {
-                    let _ = client.recipient.try_send(ChatMessage(message.clone()));
-                }
+    /// Updates the device of client `id`, marking the session changed if it
+    /// differs. Returns false if the client is not in the room.
+    pub fn set_device(
+        &mut self,
+        session_id: &str,
+        room_name: &str,
+        id: usize,
+        device: DeviceInfo,
+    ) -> bool {
+        let Some(client) = self.member_mut(session_id, room_name, id) else {
+            return false;
+        };
+        if client.device != device {
+            log::debug!(
+                target: "Websocket",
+                "{} in {session_id} registered device {device:?}",
+                client.name
+            );
+            client.device = device;
+            self.mark_changed(session_id);
+        }
+        true
+    }
+
+    /// Tells everyone else in the room what client `id` is doing. Returns
+    /// false if the client is not in the room.
+    pub fn relay_activity(
+        &self,
+        session_id: &str,
+        room_name: &str,
+        id: usize,
+        activity: Activity,
+    ) -> bool {
+        let Some(room) = self
+            .rooms
+            .get(session_id)
+            .and_then(|rooms| rooms.get(room_name))
+        else {
+            return false;
+        };
+        let Some(sender) = room.get(&id) else {
+            return false;
+        };
+
+        let message = format!(
+            "{WS_PREFIX_SYSTEM_ACTIVITY} {}",
+            json!({ "name": sender.name, "activity": activity })
+        );
+        for (client_id, client) in room {
+            if *client_id != id && !client.deliver(message.clone()) {
+                log::debug!(
+                    target: "Websocket",
+                    "Failed to relay activity from {} to client {client_id}",
+                    sender.name
+                );
             }
         }
+        true
     }
 
-    pub fn broadcast_room_members(&self, session_id: &str, room_name: &str) {
-        if let Some(users) = self.rooms.get(session_id)
-            && let Some(room) = users.get(room_name)
-        {
-            let member_list: Vec<String> = room
-                .values()
-                .map(|client_metadata| client_metadata.name.clone())
-                .collect();
+    // Helper function to look up a client by room and id
+    fn member_mut(
+        &mut self,
+        session_id: &str,
+        room_name: &str,
+        id: usize,
+    ) -> Option<&mut ClientMetadata> {
+        self.rooms
+            .get_mut(session_id)
+            .and_then(|rooms| rooms.get_mut(room_name))
+            .and_then(|room| room.get_mut(&id))
+    }
+
+    /// Marks the room and member lists of `session_id` as changed; they
+    /// go out with the next `publish_updates`.
+    pub fn mark_changed(&mut self, session_id: &str) {
+        self.changed.insert(session_id.to_owned());
+    }
+
+    /// Publishes pending changes once the debounce window has passed, so a
+    /// burst of joins and leaves goes out as one delta per list.
+    pub fn schedule_publish(&mut self, ctx: &mut Context<Self>) {
+        if self.publish_scheduled || self.changed.is_empty() {
+            return;
+        }
+        self.publish_scheduled = true;
+        ctx.run_later(self.limits.broadcast_debounce(), |act, _| {
+            act.publish_scheduled = false;
+            act.publish_updates();
+        });
+    }
+
+    /// Sends room and member deltas for every session changed since the
+    /// last call.
+    pub fn publish_updates(&mut self) {
+        for session_id in std::mem::take(&mut self.changed) {
+            let Some(rooms) = self.rooms.get(&session_id) else {
+                self.rosters.remove(&session_id);
+                continue;
+            };
+            let changes = self
+                .rosters
+                .entry(session_id.clone())
+                .or_default()
+                .update(Some(rooms));
             log::debug!(
                 target: "Websocket",
-                "Broadcasting members of room {}: {:?}",
-                room_name,
-                member_list
+                "Publishing changes for session {session_id}: {changes:?}"
             );
-            let member_message = format!("{} {}", WS_PREFIX_SYSTEM_MEMBERS, member_list.join(", "));
 
-            for client_metadata in room.values() {
-                if client_metadata
-                    .recipient
-                    .try_send(ChatMessage(member_message.clone()))
-                    .is_err()
+            if let Some(delta) = &changes.rooms {
+                for client in rooms.values().flat_map(|room| room.values()) {
+                    client.deliver(delta.clone());
+                }
+            }
+            for (room_name, delta) in &changes.members {
+                for client in rooms
+                    .get(room_name)
+                    .into_iter()
+                    .flat_map(|room| room.values())
                 {
-                    log::debug!(
-                        target: "Websocket",
-                        "Failed to send member list to client {}, client may have disconnected",
-                        client_metadata.name
-                    );
+                    client.deliver(delta.clone());
                 }
             }
         }
     }
 
+    /// The published room list of `session_id`, followed by the members of
+    /// `room_name` if given.
+    pub fn snapshot(&self, session_id: &str, room_name: Option<&str>) -> Vec<String> {
+        let empty = Roster::default();
+        let roster = self.rosters.get(session_id).unwrap_or(&empty);
+        let mut snapshot = vec![roster.rooms_snapshot()];
+        if let Some(room_name) = room_name {
+            snapshot.push(roster.members_snapshot(room_name));
+        }
+        snapshot
+    }
+
+    // Helper function to give a client that just joined a baseline for the
+    // deltas that follow
+    pub fn send_snapshot(&self, session_id: &str, room_name: &str, id: usize) {
+        if let Some(client) = self
+            .rooms
+            .get(session_id)
+            .and_then(|rooms| rooms.get(room_name))
+            .and_then(|room| room.get(&id))
+        {
+            for message in self.snapshot(session_id, Some(room_name)) {
+                client.deliver(message);
+            }
+        }
+    }
+
     pub fn remove_empty_rooms(&mut self, session_id: &str) {
         if let Some(rooms) = self.rooms.get_mut(session_id) {
             rooms.retain(|name, room| !room.is_empty() || name == "main");
@@ -196,7 +427,7 @@ impl WsChatServer {
                 );
             }
         }
-        self.broadcast_room_list(session_id);
+        self.mark_changed(session_id);
     }
 
     pub fn start_cleanup_interval(&self, ctx: &mut Context<Self>) {
@@ -216,6 +447,7 @@ impl WsChatServer {
         for session_id in empty_sessions {
             log::debug!(target: "Websocket","Cleanup: Removing empty session {session_id}");
             self.rooms.remove(&session_id);
+            self.rosters.remove(&session_id);
         }
 
         log::debug!(
@@ -258,10 +490,10 @@ impl WsChatServer {
             for room in rooms.values() {
                 for client in room.values() {
                     if client.name == to_user {
-                        if let Err(e) = client.recipient.try_send(message) {
+                        if !client.deliver(message.0) {
                             log::error!(
                                 target: "Websocket",
-                                "Failed to relay signal from {from_user} to {to_user}: {e:?}"
+                                "Failed to relay signal from {from_user} to {to_user}: client disconnected"
                             );
                         } else {
                             log::debug!(
@@ -1,12 +1,18 @@
 use crate::{
-    HEARTBEAT_INTERVAL, HEARTBEAT_TIMEOUT, SessionStore, WS_PREFIX_KEEP_ALIVE,
-    WS_PREFIX_SIGNAL_MESSAGE, WS_PREFIX_SYSTEM_ERROR, WS_PREFIX_SYSTEM_NAME,
-    WS_PREFIX_SYSTEM_ROOMS, WS_PREFIX_USER_COMMAND, WS_PREFIX_USER_DISCONNECTED,
-    consts::{MAX_SIGNAL_SIZE, MAX_WS_MESSAGES_PER_SEC},
+    SessionStore, WS_PREFIX_KEEP_ALIVE, WS_PREFIX_SIGNAL_MESSAGE, WS_PREFIX_SYSTEM_ERROR,
+    WS_PREFIX_SYSTEM_NAME, WS_PREFIX_SYSTEM_WARNING, WS_PREFIX_USER_COMMAND,
+    WS_PREFIX_USER_DISCONNECTED, WS_THROTTLE_METRIC,
+    avatar::Avatar,
+    consts::MAX_DISPLAY_NAME_LENGTH,
+    device::DeviceInfo,
     error::ServerError,
     message::{
-        JoinRoom, LeaveRoom, ListRooms, ValidateAndRelaySignal, WsChatServer, WsChatSession,
+        Activity, JoinRoom, LeaveRoom, PeerInfo, Presence, RejectedSession, RelayActivity,
+        SetDevice, SetPresence, Snapshot, ValidateAndRelaySignal, WsChatServer, WsChatSession,
     },
+    metrics::metrics,
+    outbound::Outbox,
+    throttle::{MessageKind, SessionRateLimiter, Throttle},
 };
 use actix::prelude::*;
 use actix_web_actors::ws;
@@ -16,28 +22,60 @@ use fake::{
 };
 use rand::{RngExt, rng};
 use serde_json::Value;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 impl WsChatSession {
-    pub fn new(session_id: &str, auto_join: bool, session_store: SessionStore) -> Self {
+    pub fn new(
+        session_id: &str,
+        auto_join: bool,
+        session_store: SessionStore,
+        peer: PeerInfo,
+    ) -> Self {
         let id = rng().random_range(0..usize::MAX);
-        let first_name = FirstName().fake::<String>();
-        let last_name = LastName().fake::<String>();
-        let name = format!("{first_name} {last_name}");
+        let name = peer
+            .display_name
+            .as_deref()
+            .map(Self::sanitize_display_name)
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| {
+                let first_name = FirstName().fake::<String>();
+                let last_name = LastName().fake::<String>();
+                format!("{first_name} {last_name}")
+            });
 
+        // Anonymous peers have no stable identity, so their generated name
+        // stands in and their avatar changes from one connection to the next.
+        let avatar = Avatar::new(peer.peer_id.as_deref().unwrap_or(&name), &name);
+        let limits = session_store.limits();
         WsChatSession {
             session_id: session_id.to_owned(),
             id,
             room: "".to_owned(),
             name,
+            avatar,
             auto_join,
             session_store,
             last_heartbeat: None,
-            message_count: 0,
-            rate_limit_reset: Instant::now() + Duration::from_secs(1),
+            rate_limiter: SessionRateLimiter::new(&limits),
+            peer,
+            outbox: Outbox::new(&limits),
+            presence: Presence::default(),
+            status: None,
+            last_activity: Instant::now(),
+            limits,
         }
     }
 
+    // Strips control characters and caps the length of names taken from tokens.
+    fn sanitize_display_name(name: &str) -> String {
+        name.chars()
+            .filter(|c| !c.is_control())
+            .take(MAX_DISPLAY_NAME_LENGTH)
+            .collect::<String>()
+            .trim()
+            .to_string()
+    }
+
     pub fn join_room(&mut self, room_name: &str, ctx: &mut ws::WebsocketContext<Self>) {
         if self.room == room_name {
             log::debug!(
@@ -50,7 +88,6 @@ impl WsChatSession {
         }
 
         let room_name = room_name.to_owned();
-        let name = self.name.clone();
         let leave_msg = LeaveRoom(self.session_id.clone(), self.room.clone(), self.id);
 
         WsChatServer::from_registry()
@@ -59,28 +96,75 @@ impl WsChatSession {
             .then(|_result, _act, _ctx| fut::ready(()))
             .wait(ctx);
 
+        let previous = Some(std::mem::take(&mut self.room)).filter(|room| !room.is_empty());
+        self.enter_room(room_name, previous, ctx);
+    }
+
+    // Helper function to add the client to `room_name`, going back to
+    // `fallback` if the room turns it away. An auto-joined client left in no
+    // room at all is disconnected with the reason.
+    fn enter_room(
+        &mut self,
+        room_name: String,
+        fallback: Option<String>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
         let join_msg = JoinRoom(
             self.session_id.clone(),
-            room_name.to_owned(),
+            room_name.clone(),
             self.name.clone(),
             ctx.address().recipient(),
+            self.outbox.clone(),
+            self.presence,
+            self.peer.device.clone(),
+            self.avatar.clone(),
         );
 
         WsChatServer::from_registry()
             .send(join_msg)
             .into_actor(self)
-            .then(|id, act, _ctx| {
-                if let Ok(id) = id {
-                    log::debug!(
-                        target: "Websocket",
-                        "{} successfully joined room '{}'",
-                        act.session_id,
-                        &room_name
-                    );
+            .then(move |res, act, ctx| {
+                match res.unwrap_or_else(|e| Err(format!("Failed to join room: {e}"))) {
+                    Ok((id, name)) => {
+                        log::debug!(
+                            target: "Websocket",
+                            "{} successfully joined room '{}'",
+                            act.session_id,
+                            &room_name
+                        );
 
-                    act.id = id;
-                    act.room = room_name;
-                    act.name = name
+                        act.id = id;
+                        act.room = room_name;
+                        if name != act.name {
+                            log::debug!(
+                                target: "Websocket",
+                                "'{}' is taken in {}, going by '{name}'",
+                                act.name,
+                                act.session_id
+                            );
+                            ctx.text(format!("{WS_PREFIX_SYSTEM_NAME} {name}"));
+                            act.name = name;
+                        }
+                    }
+                    Err(reason) => {
+                        log::warn!(
+                            target: "Websocket",
+                            "{} could not join room '{room_name}': {reason}",
+                            act.session_id
+                        );
+                        if fallback.is_none() && act.auto_join {
+                            ctx.close(Some(ws::CloseReason {
+                                code: ws::CloseCode::Again,
+                                description: Some(reason),
+                            }));
+                            ctx.stop();
+                        } else {
+                            ctx.text(format!("{WS_PREFIX_SYSTEM_ERROR} {reason}"));
+                            if let Some(previous) = fallback {
+                                act.enter_room(previous, None, ctx);
+                            }
+                        }
+                    }
                 }
                 fut::ready(())
             })
@@ -88,17 +172,25 @@ impl WsChatSession {
     }
 
     pub fn list_rooms(&mut self, ctx: &mut ws::WebsocketContext<Self>) {
+        self.send_snapshot(None, ctx);
+    }
+
+    /// Sends the client the current room list, and the members of `room`
+    /// if given, as versioned snapshots.
+    pub fn send_snapshot(&mut self, room: Option<String>, ctx: &mut ws::WebsocketContext<Self>) {
         WsChatServer::from_registry()
-            .send(ListRooms(self.session_id.clone()))
+            .send(Snapshot(self.session_id.clone(), room))
             .into_actor(self)
             .then(|res, _, ctx| {
-                if let Ok(rooms) = res {
-                    log::debug!(target: "Websocket", "{WS_PREFIX_SYSTEM_ROOMS} Rooms Available: {rooms:?}");
-
-                    let room_list = rooms.join(", ");
-                    ctx.text(format!("{WS_PREFIX_SYSTEM_ROOMS} {room_list}"));
+                if let Ok(snapshot) = res {
+                    log::debug!(target: "Websocket", "Sending snapshot: {snapshot:?}");
+                    for message in snapshot {
+                        ctx.text(message);
+                    }
                 } else {
-                    ctx.text(format!("{WS_PREFIX_SYSTEM_ERROR} Failed to retrieve room list."));
+                    ctx.text(format!(
+                        "{WS_PREFIX_SYSTEM_ERROR} Failed to retrieve room list."
+                    ));
                 }
                 fut::ready(())
             })
@@ -132,10 +224,72 @@ impl WsChatSession {
                     ctx.text(format!("{WS_PREFIX_SYSTEM_ERROR} Room name is required"));
                 }
             }
+            "/sync" => {
+                log::debug!(target: "Websocket","Received sync command");
+                let room = Some(self.room.clone()).filter(|room| !room.is_empty());
+                self.send_snapshot(room, ctx);
+            }
             "/name" => {
                 log::debug!(target: "Websocket","Received name command");
                 ctx.text(format!("{} {}", WS_PREFIX_SYSTEM_NAME, self.name));
             }
+            "/device" => match args.map(serde_json::from_str::<DeviceInfo>) {
+                Some(Ok(device)) => {
+                    log::debug!(target: "Websocket", "Received device command: {device:?}");
+                    self.peer.device.register(device);
+                    if !self.room.is_empty() {
+                        WsChatServer::from_registry().do_send(SetDevice(
+                            self.session_id.clone(),
+                            self.room.clone(),
+                            self.id,
+                            self.peer.device.clone(),
+                        ));
+                    }
+                }
+                _ => ctx.text(format!(
+                    "{WS_PREFIX_SYSTEM_ERROR} Device must be a JSON object with type, os and client_version"
+                )),
+            },
+            "/status" => match args.map(str::trim).and_then(Presence::parse) {
+                Some(presence) => {
+                    log::debug!(
+                        target: "Websocket",
+                        "Received status command: {}",
+                        presence.as_str()
+                    );
+                    // Idle and away stick until the user is back; active
+                    // hands presence back to activity and heartbeats.
+                    self.status = Some(presence).filter(|p| *p != Presence::Active);
+                    self.update_presence();
+                }
+                None => ctx.text(format!(
+                    "{WS_PREFIX_SYSTEM_ERROR} Status must be one of: active, idle, away"
+                )),
+            },
+            "/activity" => match args.map(str::trim).and_then(Activity::parse) {
+                Some(activity) => {
+                    log::debug!(
+                        target: "Websocket",
+                        "Received activity command: {}",
+                        activity.as_str()
+                    );
+                    if self.room.is_empty() {
+                        ctx.text(format!(
+                            "{WS_PREFIX_SYSTEM_ERROR} Join a room before sending activity"
+                        ));
+                    } else {
+                        WsChatServer::from_registry().do_send(RelayActivity(
+                            self.session_id.clone(),
+                            self.room.clone(),
+                            self.id,
+                            activity,
+                        ));
+                    }
+                }
+                None => ctx.text(format!(
+                    "{WS_PREFIX_SYSTEM_ERROR} Activity must be one of: typing, selecting-file, uploading, none"
+                )),
+            },
             _ => {
                 log::debug!(target: "Websocket", "Unknown command: '{cmd}'");
                 ctx.text(format!(
@@ -149,7 +303,7 @@ impl WsChatSession {
 
     fn handle_signal_message(&self, msg: &str, ctx: &mut ws::WebsocketContext<Self>) {
         // 1. Size validation
-        if msg.len() > MAX_SIGNAL_SIZE {
+        if msg.len() > self.limits.max_signal_size {
             log::warn!(
                 target: "Websocket",
                 "Oversize signaling message ({} bytes) from user {}",
@@ -194,6 +348,119 @@ impl WsChatSession {
         });
     }
 
+    // Helper function to charge a message to the client's budget, warning or
+    // disconnecting clients that go over it
+    fn allow_message(
+        &mut self,
+        kind: MessageKind,
+        bytes: usize,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) -> bool {
+        match self.rate_limiter.check(kind, bytes) {
+            Throttle::Allowed => true,
+            Throttle::Throttled { retry_after, first } => {
+                metrics().increment(
+                    WS_THROTTLE_METRIC,
+                    &[("kind", kind.as_str()), ("action", "dropped")],
+                );
+                if first {
+                    log::warn!(
+                        target: "Websocket",
+                        "Rate limit exceeded for user {} ({}), dropping messages",
+                        self.name,
+                        kind.as_str()
+                    );
+                    ctx.text(format!(
+                        "{WS_PREFIX_SYSTEM_WARNING} Rate limit exceeded for {}, retry in {}ms",
+                        kind.as_str(),
+                        retry_after.as_millis().max(1)
+                    ));
+                }
+                false
+            }
+            Throttle::Disconnect => {
+                metrics().increment(
+                    WS_THROTTLE_METRIC,
+                    &[("kind", kind.as_str()), ("action", "disconnected")],
+                );
+                log::warn!(
+                    target: "Websocket",
+                    "User {} kept exceeding the rate limit, disconnecting",
+                    self.name
+                );
+                self.handle_user_disconnect();
+                ctx.close(Some(ws::CloseReason {
+                    code: ws::CloseCode::Policy,
+                    description: Some("Rate limit exceeded".to_string()),
+                }));
+                ctx.stop();
+                false
+            }
+        }
+    }
+
+    // Helper function to write out server messages queued behind the one just
+    // handled, disconnecting clients that fell too far behind
+    pub fn flush_outbox(&mut self, ctx: &mut ws::WebsocketContext<Self>) {
+        match self.outbox.drain() {
+            Some(messages) => {
+                for message in messages {
+                    ctx.text(message);
+                }
+                // Deltas were dropped, so the client needs a fresh baseline.
+                if self.outbox.take_resync() {
+                    let room = Some(self.room.clone()).filter(|room| !room.is_empty());
+                    self.send_snapshot(room, ctx);
+                }
+            }
+            None => {
+                self.handle_user_disconnect();
+                ctx.close(Some(ws::CloseReason {
+                    code: ws::CloseCode::Again,
+                    description: Some("Too many pending messages".to_string()),
+                }));
+                ctx.stop();
+            }
+        }
+    }
+
+    // Helper function to work out presence from `/status`, the last message
+    // from the user and the last heartbeat
+    fn infer_presence(&self) -> Presence {
+        if let Some(status) = self.status {
+            return status;
+        }
+        let now = Instant::now();
+        // A client that missed a heartbeat is most likely asleep.
+        if self
+            .last_heartbeat
+            .is_some_and(|last| now.duration_since(last) > self.limits.heartbeat_interval() * 2)
+        {
+            Presence::Away
+        } else if now.duration_since(self.last_activity) >= self.limits.idle_threshold() {
+            Presence::Idle
+        } else {
+            Presence::Active
+        }
+    }
+
+    /// Tells the room about a change in presence.
+    pub fn update_presence(&mut self) {
+        let presence = self.infer_presence();
+        if presence == self.presence {
+            return;
+        }
+        self.presence = presence;
+        if !self.room.is_empty() {
+            WsChatServer::from_registry().do_send(SetPresence(
+                self.session_id.clone(),
+                self.room.clone(),
+                self.id,
+                presence,
+            ));
+        }
+    }
+
     fn handle_user_disconnect(&self) {
         let leave_msg = LeaveRoom(self.session_id.clone(), self.room.clone(), self.id);
         WsChatServer::from_registry().do_send(leave_msg);
@@ -201,9 +468,9 @@ impl WsChatSession {
     }
 
     pub fn start_heartbeat(&self, ctx: &mut ws::WebsocketContext<Self>) {
-        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
+        ctx.run_interval(self.limits.heartbeat_interval(), |act, ctx| {
             if let Some(last) = act.last_heartbeat
-                && Instant::now().duration_since(last) > HEARTBEAT_TIMEOUT
+                && Instant::now().duration_since(last) > act.limits.heartbeat_timeout()
             {
                 log::debug!(
                     target: "Websocket",
@@ -214,12 +481,28 @@ impl WsChatSession {
                 ctx.stop();
                 return;
             }
+            act.update_presence();
             log::debug!(target: "Websocket", "Sending heartbeat to user {}", act.name);
             ctx.ping(b"");
         });
     }
 }
 
+// Helper function to pick the budget a text message is charged to
+fn message_kind(text: &str) -> MessageKind {
+    let text = text.trim();
+    if text.starts_with(WS_PREFIX_SIGNAL_MESSAGE) {
+        MessageKind::Signal
+    } else if text
+        .strip_prefix(WS_PREFIX_USER_COMMAND)
+        .is_some_and(|command| command.trim_start().starts_with("/activity"))
+    {
+        MessageKind::Activity
+    } else {
+        MessageKind::Command
+    }
+}
+
 impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsChatSession {
     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
         let msg = match msg {
@@ -237,23 +520,20 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsChatSession {
 
         match msg {
             ws::Message::Text(text) => {
-                let now = Instant::now();
-                if now > self.rate_limit_reset {
-                    self.message_count = 0;
-                    self.rate_limit_reset = now + Duration::from_secs(1);
-                }
-                self.message_count += 1;
-                if self.message_count > MAX_WS_MESSAGES_PER_SEC {
-                    log::warn!(
-                        target: "Websocket",
-                        "Rate limit exceeded for user {}, dropping message",
-                        self.name
-                    );
+                if !self.allow_message(message_kind(&text), text.len(), ctx) {
                     return;
                 }
 
                 let msg = text.trim();
                 log::debug!(target: "Websocket", "Received message: '{msg}'");
+                // Anything shows the device is awake; only keep-alives are
+                // sent without the user doing something.
+                self.last_heartbeat = Some(Instant::now());
+                if !msg.starts_with(WS_PREFIX_KEEP_ALIVE) {
+                    self.last_activity = Instant::now();
+                }
+                self.update_presence();
+
                 if msg.starts_with(WS_PREFIX_SIGNAL_MESSAGE) {
                     self.handle_signal_message(msg, ctx);
                 } else if msg.starts_with(WS_PREFIX_USER_COMMAND) {
@@ -278,14 +558,21 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsChatSession {
                     ));
                 }
             }
+            ws::Message::Binary(data) => {
+                if self.allow_message(MessageKind::Binary, data.len(), ctx) {
+                    log::debug!(target: "Websocket", "Ignoring binary frame from {}", self.name);
+                }
+            }
             ws::Message::Ping(msg) => {
                 log::debug!(target: "Websocket", "Received ping message");
                 self.last_heartbeat = Some(Instant::now());
+                self.update_presence();
                 ctx.pong(&msg);
             }
             ws::Message::Pong(_) => {
                 log::debug!(target: "Websocket", "Received pong message");
                 self.last_heartbeat = Some(Instant::now());
+                self.update_presence();
             }
             ws::Message::Close(reason) => {
                 log::debug!(target: "Websocket", "Closing connection: {reason:?}");
@@ -297,3 +584,7 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsChatSession {
         }
     }
 }
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for RejectedSession {
+    fn handle(&mut self, _msg: Result<ws::Message, ws::ProtocolError>, _ctx: &mut Self::Context) {}
+}
@@ -1,6 +1,9 @@
 use crate::{
-    CONTENT_TYPE_TEXT_PLAIN, MAX_FRAME_SIZE, SAFE_CHARSET, SESSION_EXPIRATION_TIME, ServerConfig,
-    WsChatServer, WsChatSession, message::CleanupSession,
+    CONTENT_TYPE_TEXT_PLAIN, SAFE_CHARSET, ServerConfig, WS_CONNECTION_LIMIT_METRIC, WsChatServer,
+    WsChatSession,
+    config::LimitsConfig,
+    message::{CleanupSession, PeerInfo, RejectedSession},
+    metrics::metrics,
 };
 use actix::SystemService;
 use actix_rt::{spawn, task, time};
@@ -9,8 +12,9 @@ use actix_web_actors::ws as actix_actor_ws;
 use rand::{RngExt, rng};
 use std::{
     collections::{HashMap, HashSet},
+    net::IpAddr,
     sync::{
-        Arc, Mutex,
+        Arc, Mutex, RwLock,
         atomic::{AtomicUsize, Ordering},
     },
 };
@@ -23,6 +27,44 @@ pub struct SessionData {
     pub is_private: bool,
 }
 
+/// A concurrent connection cap that turned a client away.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionLimit {
+    /// `max_connections_per_ip` WebSockets are already open from the address.
+    PerIp(usize),
+    /// `max_connections_per_session` clients already joined the session.
+    PerSession(usize),
+    /// `max_clients_per_room` clients are already in the room.
+    PerRoom(usize),
+}
+
+impl ConnectionLimit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionLimit::PerIp(_) => "ip",
+            ConnectionLimit::PerSession(_) => "session",
+            ConnectionLimit::PerRoom(_) => "room",
+        }
+    }
+
+    /// Reason sent to the client in the WebSocket close frame.
+    pub fn close_reason(&self) -> String {
+        match self {
+            ConnectionLimit::PerIp(max) => {
+                format!("Too many connections from this address (max {max})")
+            }
+            ConnectionLimit::PerSession(max) => format!("Session is full (max {max} connections)"),
+            ConnectionLimit::PerRoom(max) => format!("Room is full (max {max} clients)"),
+        }
+    }
+}
+
+// Why a client could not join a session.
+enum JoinRejection {
+    UnknownSession,
+    Full,
+}
+
 #[derive(Default, Clone)]
 pub struct SessionStore {
     /// Maps a key (IP for public or generated code for private sessions)
@@ -34,9 +76,40 @@ pub struct SessionStore {
     pub expired_private_codes: Arc<Mutex<HashSet<String>>>,
     /// For private sessions, tracks scheduled expirations.
     pub scheduled_expirations: Arc<Mutex<HashMap<String, task::JoinHandle<()>>>>,
+    /// Tracks how many WebSocket clients are connected from each IP.
+    pub ip_connection_counts: Arc<Mutex<HashMap<IpAddr, usize>>>,
+    /// Limits applied to sessions and the WebSocket clients joining them.
+    /// Replaced on config reload; connected clients keep the limits they
+    /// joined with.
+    limits: Arc<RwLock<LimitsConfig>>,
 }
 
 impl SessionStore {
+    pub fn new(limits: LimitsConfig) -> Self {
+        SessionStore {
+            limits: Arc::new(RwLock::new(limits)),
+            ..SessionStore::default()
+        }
+    }
+
+    /// Returns the limits currently applied to new sessions and clients.
+    pub fn limits(&self) -> LimitsConfig {
+        match self.limits.read() {
+            Ok(limits) => *limits,
+            Err(e) => {
+                log::error!(target: "Websocket", "Failed to acquire lock on limits: {e:?}");
+                LimitsConfig::default()
+            }
+        }
+    }
+
+    pub fn set_limits(&self, limits: LimitsConfig) {
+        match self.limits.write() {
+            Ok(mut current) => *current = limits,
+            Err(e) => log::error!(target: "Websocket", "Failed to acquire lock on limits: {e:?}"),
+        }
+    }
+
     /// Returns true if the private session code has been marked expired.
     fn is_code_expired(&self, key: &str) -> bool {
         self.expired_private_codes
@@ -45,6 +118,23 @@ impl SessionStore {
             .contains(key)
     }
 
+    /// Returns true if the code belongs to a live (non-expired) private session.
+    pub fn is_active_private_code(&self, code: &str) -> bool {
+        if self.is_code_expired(code) {
+            return false;
+        }
+        match self.key_to_session.lock() {
+            Ok(map) => map.get(code).is_some_and(|data| data.is_private),
+            Err(e) => {
+                log::error!(
+                    target: "Websocket",
+                    "Failed to acquire lock on key_to_session: {e:?}"
+                );
+                false
+            }
+        }
+    }
+
     /// Looks up (or creates) a session UUID for the given key.
     /// The caller must indicate whether this is a private session.
     /// - If the session exists, its client count is incremented and its UUID returned.
@@ -56,10 +146,24 @@ impl SessionStore {
         strict_mode: bool,
         is_private: bool,
     ) -> Option<String> {
+        self.join_session(key, strict_mode, is_private, usize::MAX)
+            .ok()
+            .map(|uuid| uuid.to_string())
+    }
+
+    // Helper function to find or create the session for `key`, refusing to
+    // go over `max_clients` connected clients
+    fn join_session(
+        &self,
+        key: &str,
+        strict_mode: bool,
+        is_private: bool,
+        max_clients: usize,
+    ) -> Result<Uuid, JoinRejection> {
         // For private sessions, check if the code is expired.
         if is_private && self.is_code_expired(key) {
             log::debug!(target: "Websocket", "Private session code {key} is expired");
-            return None;
+            return Err(JoinRejection::UnknownSession);
         }
 
         // Make sure to always cancel any scheduled expiration when reconnecting
@@ -79,17 +183,20 @@ impl SessionStore {
                         target: "Websocket",
                         "Failed to acquire lock on key_to_session: {e:?}"
                     );
-                    return None;
+                    return Err(JoinRejection::UnknownSession);
                 }
             };
             if let Some(data) = map.get(key) {
-                self.increment_client_count(data.uuid);
-                return Some(data.uuid.to_string());
+                return if self.increment_client_count(data.uuid, max_clients) {
+                    Ok(data.uuid)
+                } else {
+                    Err(JoinRejection::Full)
+                };
             }
         }
 
         if strict_mode {
-            return None;
+            return Err(JoinRejection::UnknownSession);
         }
 
         let new_uuid = Uuid::new_v4();
@@ -105,16 +212,19 @@ impl SessionStore {
                         target: "Websocket",
                         "Failed to acquire lock on key_to_session: {e:?}"
                     );
-                    return None;
+                    return Err(JoinRejection::UnknownSession);
                 }
             };
             map.insert(key.to_string(), new_data);
         }
-        self.increment_client_count(new_uuid);
-        Some(new_uuid.to_string())
+        self.increment_client_count(new_uuid, max_clients);
+        Ok(new_uuid)
     }
 
-    /// Starts a WebSocket session using the stored session UUID.
+    /// Starts a WebSocket session using the stored session UUID. Clients
+    /// over the per-IP or per-session connection caps are accepted and then
+    /// closed straight away with the reason. Peers let in by a bypass key are
+    /// counted against their IP but never held to its cap.
     pub fn start_websocket(
         &self,
         config: &ServerConfig,
@@ -123,25 +233,56 @@ impl SessionStore {
         key: &str,
         strict_mode: bool,
         is_private: bool,
+        peer: PeerInfo,
     ) -> Result<HttpResponse, Error> {
-        match self.get_or_create_session_uuid(key, strict_mode, is_private) {
-            Some(uuid_str) => match Uuid::parse_str(&uuid_str) {
-                Ok(_) => actix_actor_ws::WsResponseBuilder::new(
-                    WsChatSession::new(&uuid_str, config.auto_join, self.clone()),
+        let limits = self.limits();
+        let client_ip = peer.client_ip;
+        let max_per_ip = if peer.ip_cap_exempt {
+            usize::MAX
+        } else {
+            limits.max_connections_per_ip
+        };
+        if let Some(ip) = client_ip
+            && !self.track_ip_connection(ip, max_per_ip)
+        {
+            let limit = ConnectionLimit::PerIp(limits.max_connections_per_ip);
+            return Self::reject_websocket(req, stream, limit);
+        }
+
+        let joined = self.join_session(
+            key,
+            strict_mode,
+            is_private,
+            limits.max_connections_per_session,
+        );
+        if joined.is_err()
+            && let Some(ip) = client_ip
+        {
+            self.release_ip_connection(ip);
+        }
+
+        match joined {
+            Ok(uuid) => {
+                let response = actix_actor_ws::WsResponseBuilder::new(
+                    WsChatSession::new(&uuid.to_string(), config.auto_join, self.clone(), peer),
                     req,
                     stream,
                 )
                 .codec(actix_http::ws::Codec::new())
-                .frame_size(MAX_FRAME_SIZE)
-                .start(),
-                Err(_) => {
-                    log::error!(target: "Websocket", "Invalid UUID returned: {uuid_str}");
-                    Ok(HttpResponse::InternalServerError()
-                        .content_type(CONTENT_TYPE_TEXT_PLAIN)
-                        .body("Server configuration error"))
+                .frame_size(limits.max_frame_size)
+                .start();
+                if response.is_err()
+                    && let Some(ip) = client_ip
+                {
+                    self.release_ip_connection(ip);
                 }
-            },
-            None => {
+                response
+            }
+            Err(JoinRejection::Full) => {
+                let limit = ConnectionLimit::PerSession(limits.max_connections_per_session);
+                Self::reject_websocket(req, stream, limit)
+            }
+            Err(JoinRejection::UnknownSession) => {
                 log::warn!(
                     target: "Websocket",
                     "Key '{key}' not found in strict mode, returning 404"
@@ -153,8 +294,72 @@ impl SessionStore {
         }
     }
 
-    /// Increments the client count for the session with the given UUID.
-    fn increment_client_count(&self, uuid: Uuid) {
+    // Helper function to complete the upgrade only to close it, since
+    // browsers cannot read why a plain HTTP rejection happened
+    fn reject_websocket(
+        req: &HttpRequest,
+        stream: Payload,
+        limit: ConnectionLimit,
+    ) -> Result<HttpResponse, Error> {
+        log::warn!(
+            target: "Websocket",
+            "Rejecting WebSocket connection: {}",
+            limit.close_reason()
+        );
+        metrics().increment(WS_CONNECTION_LIMIT_METRIC, &[("limit", limit.as_str())]);
+        actix_actor_ws::start(
+            RejectedSession {
+                reason: limit.close_reason(),
+            },
+            req,
+            stream,
+        )
+    }
+
+    /// Returns the number of WebSocket clients currently connected from `ip`.
+    pub fn connections_for_ip(&self, ip: IpAddr) -> usize {
+        self.ip_connection_counts
+            .lock()
+            .map(|counts| counts.get(&ip).copied().unwrap_or(0))
+            .unwrap_or(0)
+    }
+
+    /// Records a new WebSocket connection from `ip` unless `max` are
+    /// already open.
+    fn track_ip_connection(&self, ip: IpAddr, max: usize) -> bool {
+        match self.ip_connection_counts.lock() {
+            Ok(mut counts) => {
+                let count = counts.entry(ip).or_default();
+                if *count >= max {
+                    return false;
+                }
+                *count += 1;
+                true
+            }
+            Err(e) => {
+                log::error!(
+                    target: "Websocket",
+                    "Failed to acquire lock on ip_connection_counts: {e:?}"
+                );
+                true
+            }
+        }
+    }
+
+    /// Releases a WebSocket connection previously tracked for `ip`.
+    pub fn release_ip_connection(&self, ip: IpAddr) {
+        let mut counts = self.ip_connection_counts.lock().expect("lock poisoned");
+        if let Some(count) = counts.get_mut(&ip) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                counts.remove(&ip);
+            }
+        }
+    }
+
+    /// Increments the client count for the session with the given UUID,
+    /// unless it already has `max` clients.
+    fn increment_client_count(&self, uuid: Uuid, max: usize) -> bool {
         let mut counts = match self.uuid_client_counts.lock() {
             Ok(guard) => guard,
             Err(e) => {
@@ -162,12 +367,29 @@ impl SessionStore {
                     target: "Websocket",
                     "Failed to acquire lock on uuid_client_counts: {e:?}"
                 );
-                return;
+                return true;
             }
         };
         let counter = counts.entry(uuid).or_default();
+        if counter.load(Ordering::SeqCst) >= max {
+            log::warn!(target: "Websocket", "Session {uuid} is full ({max} clients)");
+            return false;
+        }
         let new_count = counter.fetch_add(1, Ordering::SeqCst) + 1;
         log::debug!(target: "Websocket", "Session {uuid} now has {new_count} clients");
+        true
+    }
+
+    /// Returns the number of WebSocket clients joined to the session.
+    pub fn clients_in_session(&self, uuid: &Uuid) -> usize {
+        self.uuid_client_counts
+            .lock()
+            .map(|counts| {
+                counts
+                    .get(uuid)
+                    .map_or(0, |count| count.load(Ordering::SeqCst))
+            })
+            .unwrap_or(0)
     }
 
     /// Decrements the client count. If it reaches zero for a private session,
@@ -207,9 +429,10 @@ impl SessionStore {
                     } else {
                         let store_clone = self.clone();
                         let key_clone = key.clone();
+                        let expiration = self.limits().session_expiration();
 
                         let handle = spawn(async move {
-                            time::sleep(SESSION_EXPIRATION_TIME).await;
+                            time::sleep(expiration).await;
 
                             let mut scheduled = store_clone
                                 .scheduled_expirations
@@ -0,0 +1,170 @@
+use crate::config::{LimitsConfig, MessageQuota};
+use std::time::{Duration, Instant};
+
+/// Categories of client messages, each with its own budget.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageKind {
+    /// Commands, keep-alives and any other text message.
+    Command,
+    /// WebRTC signaling relayed to another peer.
+    Signal,
+    /// Binary frames.
+    Binary,
+    /// Activity indicators fanned out to the room.
+    Activity,
+}
+
+impl MessageKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageKind::Command => "commands",
+            MessageKind::Signal => "signals",
+            MessageKind::Binary => "binary",
+            MessageKind::Activity => "activity",
+        }
+    }
+}
+
+/// Result of checking a message against the session's budgets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Throttle {
+    Allowed,
+    /// The message should be dropped. `first` is set for the first dropped
+    /// message since the last one allowed, so clients are warned once per
+    /// burst rather than for every message.
+    Throttled {
+        retry_after: Duration,
+        first: bool,
+    },
+    /// The client kept sending past its budget and should be disconnected.
+    Disconnect,
+}
+
+struct TokenBucket {
+    capacity: f64,
+    per_sec: f64,
+    tokens: f64,
+}
+
+impl TokenBucket {
+    fn new(per_sec: u64, capacity: u64) -> Self {
+        TokenBucket {
+            capacity: capacity as f64,
+            per_sec: per_sec as f64,
+            tokens: capacity as f64,
+        }
+    }
+
+    fn refill(&mut self, elapsed: Duration) {
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.per_sec).min(self.capacity);
+    }
+
+    // Helper function to compute how long until `cost` tokens are available
+    fn wait(&self, cost: f64) -> Duration {
+        if self.tokens >= cost {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((cost - self.tokens) / self.per_sec)
+        }
+    }
+}
+
+// Message and byte buckets for one kind of message; both must have room.
+struct QuotaBuckets {
+    messages: TokenBucket,
+    bytes: TokenBucket,
+    updated: Instant,
+    throttled: bool,
+}
+
+impl QuotaBuckets {
+    fn new(quota: &MessageQuota, now: Instant) -> Self {
+        QuotaBuckets {
+            messages: TokenBucket::new(quota.messages_per_sec.into(), quota.message_burst.into()),
+            bytes: TokenBucket::new(quota.bytes_per_sec, quota.byte_burst),
+            updated: now,
+            throttled: false,
+        }
+    }
+
+    fn take(&mut self, bytes: usize, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated);
+        self.updated = now;
+        self.messages.refill(elapsed);
+        self.bytes.refill(elapsed);
+
+        let wait = self.messages.wait(1.0).max(self.bytes.wait(bytes as f64));
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+        self.messages.tokens -= 1.0;
+        self.bytes.tokens -= bytes as f64;
+        Ok(())
+    }
+}
+
+/// Per-session token-bucket limiter with separate message and byte budgets
+/// for commands, signals, binary frames and activity indicators.
+///
+/// Dropped messages are counted over `abuse_window_secs`; a client going
+/// over `max_throttled_messages` in one window is to be disconnected.
+pub struct SessionRateLimiter {
+    commands: QuotaBuckets,
+    signals: QuotaBuckets,
+    binary: QuotaBuckets,
+    activity: QuotaBuckets,
+    max_throttled: u32,
+    window: Duration,
+    window_start: Instant,
+    throttled_in_window: u32,
+}
+
+impl SessionRateLimiter {
+    pub fn new(limits: &LimitsConfig) -> Self {
+        let now = Instant::now();
+        SessionRateLimiter {
+            commands: QuotaBuckets::new(&limits.commands, now),
+            signals: QuotaBuckets::new(&limits.signals, now),
+            binary: QuotaBuckets::new(&limits.binary, now),
+            activity: QuotaBuckets::new(&limits.activity, now),
+            max_throttled: limits.max_throttled_messages,
+            window: limits.abuse_window(),
+            window_start: now,
+            throttled_in_window: 0,
+        }
+    }
+
+    /// Charges a message of `bytes` bytes to the budget for `kind`.
+    pub fn check(&mut self, kind: MessageKind, bytes: usize) -> Throttle {
+        self.check_at(kind, bytes, Instant::now())
+    }
+
+    /// Like `check`, at a given point in time.
+    pub fn check_at(&mut self, kind: MessageKind, bytes: usize, now: Instant) -> Throttle {
+        let buckets = match kind {
+            MessageKind::Command => &mut self.commands,
+            MessageKind::Signal => &mut self.signals,
+            MessageKind::Binary => &mut self.binary,
+            MessageKind::Activity => &mut self.activity,
+        };
+        let retry_after = match buckets.take(bytes, now) {
+            Ok(()) => {
+                buckets.throttled = false;
+                return Throttle::Allowed;
+            }
+            Err(retry_after) => retry_after,
+        };
+        let first = !buckets.throttled;
+        buckets.throttled = true;
+
+        if now.saturating_duration_since(self.window_start) > self.window {
+            self.window_start = now;
+            self.throttled_in_window = 0;
+        }
+        self.throttled_in_window += 1;
+        if self.throttled_in_window > self.max_throttled {
+            return Throttle::Disconnect;
+        }
+        Throttle::Throttled { retry_after, first }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{ServerConfig, metrics::metrics, watch::watch_files};
+use actix_http::{Request, Response};
+use actix_rt::task::JoinHandle;
+use actix_service::IntoServiceFactory;
+use actix_web::{
+    Error, HttpServer,
+    body::MessageBody,
+    dev::{AppConfig, Service, ServiceFactory},
+};
+use std::{fmt, io, path::PathBuf, time::Duration};
+
+#[cfg(feature = "openssl")]
+use crate::tls_openssl::TlsCertificates;
+#[cfg(feature = "rustls")]
+use crate::tls_rustls::RustlsCertificates;
+
+pub const TLS_RELOAD_METRIC: &str = "pastepoint_tls_reloads_total";
+
+/// Server certificates for whichever TLS backend the binary was built with.
+/// rustls takes precedence when both the `openssl` and `rustls` features
+/// are enabled.
+#[derive(Clone)]
+pub enum ServerCertificates {
+    #[cfg(feature = "openssl")]
+    Openssl(TlsCertificates),
+    #[cfg(feature = "rustls")]
+    Rustls(RustlsCertificates),
+}
+
+impl ServerCertificates {
+    pub fn from_config(config: &ServerConfig) -> Result<Self, String> {
+        #[cfg(feature = "rustls")]
+        return RustlsCertificates::from_config(config).map(ServerCertificates::Rustls);
+
+        #[cfg(all(feature = "openssl", not(feature = "rustls")))]
+        return TlsCertificates::from_config(config).map(ServerCertificates::Openssl);
+    }
+
+    fn paths(&self) -> Vec<PathBuf> {
+        match self {
+            #[cfg(feature = "openssl")]
+            ServerCertificates::Openssl(certificates) => certificates.paths(),
+            #[cfg(feature = "rustls")]
+            ServerCertificates::Rustls(certificates) => certificates.paths(),
+        }
+    }
+
+    /// Re-reads the key and certificate, keeping the current pair if the new
+    /// one is invalid.
+    pub fn reload(&self) -> Result<(), String> {
+        let result = match self {
+            #[cfg(feature = "openssl")]
+            ServerCertificates::Openssl(certificates) => certificates.reload(),
+            #[cfg(feature = "rustls")]
+            ServerCertificates::Rustls(certificates) => certificates.reload(),
+        };
+        let outcome = if result.is_ok() { "success" } else { "failure" };
+        metrics().increment(TLS_RELOAD_METRIC, &[("result", outcome)]);
+        result
+    }
+
+    /// Reloads the certificate whenever the key or certificate file changes.
+    pub fn watch(&self, interval: Duration) -> JoinHandle<()> {
+        let certificates = self.clone();
+        watch_files(self.paths(), interval, move || {
+            if let Err(e) = certificates.reload() {
+                log::error!(target: "Websocket", "{e}, keeping previous certificate");
+            }
+        })
+    }
+
+    /// Reloads the certificate whenever the process receives SIGHUP.
+    #[cfg(unix)]
+    pub fn reload_on_hangup(&self) -> io::Result<JoinHandle<()>> {
+        use actix_rt::signal::unix::{SignalKind, signal};
+
+        let mut hangup = signal(SignalKind::hangup())?;
+        let certificates = self.clone();
+        Ok(actix_rt::spawn(async move {
+            while hangup.recv().await.is_some() {
+                log::info!(target: "Websocket", "Received SIGHUP, reloading TLS certificate");
+                if let Err(e) = certificates.reload() {
+                    log::error!(target: "Websocket", "{e}, keeping previous certificate");
+                }
+            }
+        }))
+    }
+
+    /// Binds `server` to `address` with this backend.
+    pub fn bind<F, I, S, B>(
+        &self,
+        server: HttpServer<F, I, S, B>,
+        address: &str,
+    ) -> io::Result<HttpServer<F, I, S, B>>
+    where
+        F: Fn() -> I + Send + Clone + 'static,
+        I: IntoServiceFactory<S, Request>,
+        S: ServiceFactory<Request, Config = AppConfig> + 'static,
+        S::Error: Into<Error> + 'static,
+        S::InitError: fmt::Debug,
+        S::Response: Into<Response<B>> + 'static,
+        <S::Service as Service<Request>>::Future: 'static,
+        S::Service: 'static,
+        B: MessageBody + 'static,
+    {
+        match self {
+            #[cfg(feature = "openssl")]
+            ServerCertificates::Openssl(certificates) => {
+                let acceptor = certificates
+                    .acceptor()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                server.bind_openssl(address, acceptor)
+            }
+            #[cfg(feature = "rustls")]
+            ServerCertificates::Rustls(certificates) => {
+                let config = certificates
+                    .server_config()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                server.bind_rustls_0_23(address, config)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl From<TlsCertificates> for ServerCertificates {
+    fn from(certificates: TlsCertificates) -> Self {
+        ServerCertificates::Openssl(certificates)
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl From<RustlsCertificates> for ServerCertificates {
+    fn from(certificates: RustlsCertificates) -> Self {
+        ServerCertificates::Rustls(certificates)
+    }
+}
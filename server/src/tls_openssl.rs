@@ -0,0 +1,98 @@
+use crate::{ServerConfig, config::ClientAuthConfig, mtls::configure_client_auth};
+use openssl::ssl::{SniError, SslAcceptor, SslAcceptorBuilder, SslContext, SslFiletype, SslMethod};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+/// OpenSSL server key and certificate shared by all workers.
+///
+/// Every new handshake is switched to the most recently loaded pair, so
+/// certificates can be rotated without a restart. Established connections
+/// keep the certificate they were accepted with.
+#[derive(Clone)]
+pub struct TlsCertificates {
+    key_file: PathBuf,
+    cert_file: PathBuf,
+    client_auth: ClientAuthConfig,
+    context: Arc<RwLock<SslContext>>,
+}
+
+// Helper function to build an acceptor serving the given key and certificate
+fn build_acceptor(
+    key_file: &Path,
+    cert_file: &Path,
+    client_auth: &ClientAuthConfig,
+) -> Result<SslAcceptorBuilder, String> {
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())
+        .map_err(|e| format!("Failed to create TLS acceptor: {e}"))?;
+    builder
+        .set_private_key_file(key_file, SslFiletype::PEM)
+        .map_err(|e| format!("Failed to load private key {}: {e}", key_file.display()))?;
+    builder.set_certificate_chain_file(cert_file).map_err(|e| {
+        format!(
+            "Failed to load certificate chain {}: {e}",
+            cert_file.display()
+        )
+    })?;
+    builder
+        .check_private_key()
+        .map_err(|e| format!("Private key does not match certificate: {e}"))?;
+    configure_client_auth(&mut builder, client_auth)
+        .map_err(|e| format!("Failed to configure client certificates: {e}"))?;
+    Ok(builder)
+}
+
+impl TlsCertificates {
+    pub fn from_config(config: &ServerConfig) -> Result<Self, String> {
+        let key_file = PathBuf::from(&config.key_file_path);
+        let cert_file = PathBuf::from(&config.cert_file_path);
+        let context = build_acceptor(&key_file, &cert_file, &config.client_auth)?
+            .build()
+            .into_context();
+
+        Ok(TlsCertificates {
+            key_file,
+            cert_file,
+            client_auth: config.client_auth.clone(),
+            context: Arc::new(RwLock::new(context)),
+        })
+    }
+
+    pub(crate) fn paths(&self) -> Vec<PathBuf> {
+        vec![self.key_file.clone(), self.cert_file.clone()]
+    }
+
+    /// Re-reads the key and certificate. The current pair stays in use if
+    /// the new one cannot be loaded.
+    pub fn reload(&self) -> Result<(), String> {
+        let context = build_acceptor(&self.key_file, &self.cert_file, &self.client_auth)?
+            .build()
+            .into_context();
+
+        let mut current = self
+            .context
+            .write()
+            .map_err(|e| format!("Failed to acquire lock on TLS context: {e:?}"))?;
+        *current = context;
+        log::info!(
+            target: "Websocket",
+            "TLS certificate reloaded from {}",
+            self.cert_file.display()
+        );
+        Ok(())
+    }
+
+    /// Acceptor for `bind_openssl` that hands each new connection the
+    /// current certificate.
+    pub fn acceptor(&self) -> Result<SslAcceptorBuilder, String> {
+        let mut builder = build_acceptor(&self.key_file, &self.cert_file, &self.client_auth)?;
+        let context = self.context.clone();
+        builder.set_servername_callback(move |ssl, _alert| {
+            let current = context.read().map_err(|_| SniError::ALERT_FATAL)?;
+            ssl.set_ssl_context(&current)
+                .map_err(|_| SniError::ALERT_FATAL)
+        });
+        Ok(builder)
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{
+    ServerConfig, config::ClientAuthConfig, consts::TLS_SESSION_CACHE_SIZE,
+    mtls::client_cert_verifier,
+};
+use rustls::{
+    crypto::{CryptoProvider, ring},
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    server::{ClientHello, ResolvesServerCert, ServerSessionMemoryCache},
+    sign::CertifiedKey,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+/// rustls server key and certificate shared by all workers.
+///
+/// Every new handshake is resolved to the most recently loaded pair, so
+/// certificates can be rotated without a restart. Established connections
+/// keep the certificate they were accepted with.
+#[derive(Clone)]
+pub struct RustlsCertificates {
+    key_file: PathBuf,
+    cert_file: PathBuf,
+    client_auth: ClientAuthConfig,
+    provider: Arc<CryptoProvider>,
+    current: Arc<RwLock<Arc<CertifiedKey>>>,
+}
+
+#[derive(Debug)]
+struct CertificateResolver(Arc<RwLock<Arc<CertifiedKey>>>);
+
+impl ResolvesServerCert for CertificateResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.0.read().ok().map(|current| current.clone())
+    }
+}
+
+// Helper function to load a PEM certificate chain and private key and check
+// that they belong together
+fn load_certified_key(
+    key_file: &Path,
+    cert_file: &Path,
+    provider: &CryptoProvider,
+) -> Result<CertifiedKey, String> {
+    let chain = CertificateDer::pem_file_iter(cert_file)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| {
+            format!(
+                "Failed to load certificate chain {}: {e}",
+                cert_file.display()
+            )
+        })?;
+    if chain.is_empty() {
+        return Err(format!("No certificates found in {}", cert_file.display()));
+    }
+
+    let key = PrivateKeyDer::from_pem_file(key_file)
+        .map_err(|e| format!("Failed to load private key {}: {e}", key_file.display()))?;
+
+    CertifiedKey::from_der(chain, key, provider)
+        .map_err(|e| format!("Private key does not match certificate: {e}"))
+}
+
+impl RustlsCertificates {
+    pub fn from_config(config: &ServerConfig) -> Result<Self, String> {
+        let key_file = PathBuf::from(&config.key_file_path);
+        let cert_file = PathBuf::from(&config.cert_file_path);
+        let provider = Arc::new(ring::default_provider());
+        let current = load_certified_key(&key_file, &cert_file, &provider)?;
+
+        Ok(RustlsCertificates {
+            key_file,
+            cert_file,
+            client_auth: config.client_auth.clone(),
+            provider,
+            current: Arc::new(RwLock::new(Arc::new(current))),
+        })
+    }
+
+    pub(crate) fn paths(&self) -> Vec<PathBuf> {
+        vec![self.key_file.clone(), self.cert_file.clone()]
+    }
+
+    /// Re-reads the key and certificate. The current pair stays in use if
+    /// the new one cannot be loaded.
+    pub fn reload(&self) -> Result<(), String> {
+        let certified_key = load_certified_key(&self.key_file, &self.cert_file, &self.provider)?;
+
+        let mut current = self
+            .current
+            .write()
+            .map_err(|e| format!("Failed to acquire lock on TLS certificate: {e:?}"))?;
+        *current = Arc::new(certified_key);
+        log::info!(
+            target: "Websocket",
+            "TLS certificate reloaded from {}",
+            self.cert_file.display()
+        );
+        Ok(())
+    }
+
+    /// Server configuration for `bind_rustls_0_23` offering HTTP/2 and
+    /// HTTP/1.1 via ALPN, with session IDs and tickets for resumption.
+    pub fn server_config(&self) -> Result<rustls::ServerConfig, String> {
+        let builder = rustls::ServerConfig::builder_with_provider(self.provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| format!("Failed to create TLS configuration: {e}"))?;
+        let builder = match client_cert_verifier(&self.client_auth, self.provider.clone())
+            .map_err(|e| format!("Failed to configure client certificates: {e}"))?
+        {
+            Some(verifier) => builder.with_client_cert_verifier(verifier),
+            None => builder.with_no_client_auth(),
+        };
+
+        let mut config =
+            builder.with_cert_resolver(Arc::new(CertificateResolver(self.current.clone())));
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        config.session_storage = ServerSessionMemoryCache::new(TLS_SESSION_CACHE_SIZE);
+        config.ticketer =
+            ring::Ticketer::new().map_err(|e| format!("Failed to create ticketer: {e}"))?;
+        Ok(config)
+    }
+}
@@ -0,0 +1,37 @@
+use actix_rt::{task::JoinHandle, time};
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+// Returns the modification time and length of a file, if it exists.
+fn fingerprint(path: &PathBuf) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Polls `paths` every `interval` and calls `on_change` once whenever any of
+/// them is modified, created or removed. Polling keeps this dependency-free
+/// and works for bind-mounted files (e.g. Kubernetes secrets) where inotify
+/// events are unreliable.
+pub fn watch_files<F>(paths: Vec<PathBuf>, interval: Duration, mut on_change: F) -> JoinHandle<()>
+where
+    F: FnMut() + 'static,
+{
+    let mut last: Vec<_> = paths.iter().map(fingerprint).collect();
+    actix_rt::spawn(async move {
+        let mut ticker = time::interval(interval);
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            let current: Vec<_> = paths.iter().map(fingerprint).collect();
+            if current != last {
+                log::debug!(target: "Websocket", "Detected change in watched files: {paths:?}");
+                last = current;
+                on_change();
+            }
+        }
+    })
+}
@@ -0,0 +1,189 @@
+use actix::prelude::*;
+use actix_test::{TestServer, start};
+use actix_web::{App, web};
+use awc::{
+    Client,
+    error::WsProtocolError,
+    ws::{Frame, Message},
+};
+use futures_util::{SinkExt, Stream, StreamExt};
+use serde_json::{Value, json};
+use server::{
+    Activity, ChatMessage, ClientMetadata, LimitsConfig, MessageKind, MessageQuota, Outbox,
+    ServerConfig, SessionRateLimiter, SessionStore, Throttle, WS_PREFIX_SYSTEM_ACTIVITY,
+    WS_PREFIX_SYSTEM_ERROR, WS_PREFIX_SYSTEM_NAME, WS_PREFIX_SYSTEM_WARNING, WsChatServer, chat_ws,
+};
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+
+struct DummyActor;
+
+impl Actor for DummyActor {
+    type Context = Context<Self>;
+}
+
+impl Handler<ChatMessage> for DummyActor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: ChatMessage, _ctx: &mut Context<Self>) {}
+}
+
+fn member(name: &str) -> (ClientMetadata, Outbox) {
+    let outbox = Outbox::new(&LimitsConfig::default());
+    // Take the wake-up slot so every later message stays in the outbox.
+    outbox.push(String::new());
+    let client = ClientMetadata::new(
+        DummyActor.start().recipient(),
+        name.to_string(),
+        outbox.clone(),
+    );
+    (client, outbox)
+}
+
+fn start_server(limits: LimitsConfig) -> TestServer {
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.limits = limits;
+    let config = web::Data::new(config);
+    let store = web::Data::new(SessionStore::new(limits));
+
+    start(move || {
+        App::new()
+            .app_data(store.clone())
+            .app_data(config.clone())
+            .service(chat_ws)
+    })
+}
+
+// Collects text frames until nothing arrives for `quiet`.
+async fn texts<S>(framed: &mut S, quiet: Duration) -> Vec<String>
+where
+    S: Stream<Item = Result<Frame, WsProtocolError>> + Unpin,
+{
+    let mut texts = Vec::new();
+    while let Ok(Some(Ok(frame))) = timeout(quiet, framed.next()).await {
+        if let Frame::Text(text) = frame {
+            texts.push(String::from_utf8_lossy(&text).into_owned());
+        }
+    }
+    texts
+}
+
+fn activities(texts: &[String]) -> Vec<Value> {
+    texts
+        .iter()
+        .filter_map(|t| t.strip_prefix(WS_PREFIX_SYSTEM_ACTIVITY))
+        .map(|body| serde_json::from_str(body).unwrap())
+        .collect()
+}
+
+#[actix_rt::test]
+async fn test_activity_reaches_the_rest_of_the_room() {
+    let mut server = WsChatServer::new(LimitsConfig::default());
+    let (alice, alice_outbox) = member("Alice");
+    let (bob, bob_outbox) = member("Bob");
+    let (carol, carol_outbox) = member("Carol");
+    server.add_member("session", "main", Some(1), alice);
+    server.add_member("session", "main", Some(2), bob);
+    server.add_member("session", "other", Some(3), carol);
+
+    assert!(server.relay_activity("session", "main", 1, Activity::SelectingFile));
+    assert_eq!(
+        bob_outbox.drain().unwrap(),
+        [format!(
+            r#"{WS_PREFIX_SYSTEM_ACTIVITY} {{"activity":"selecting-file","name":"Alice"}}"#
+        )]
+    );
+    assert!(alice_outbox.drain().unwrap().is_empty());
+    assert!(carol_outbox.drain().unwrap().is_empty());
+
+    assert!(!server.relay_activity("session", "main", 3, Activity::Typing));
+    assert!(!server.relay_activity("session", "missing", 1, Activity::Typing));
+}
+
+#[test]
+fn test_activity_has_its_own_budget() {
+    let limits = LimitsConfig {
+        activity: MessageQuota::new(1, 2, 1024, 64 * 1024),
+        ..LimitsConfig::default()
+    };
+    let mut limiter = SessionRateLimiter::new(&limits);
+    let start = Instant::now();
+
+    for _ in 0..2 {
+        assert_eq!(
+            limiter.check_at(MessageKind::Activity, 30, start),
+            Throttle::Allowed
+        );
+    }
+    assert!(matches!(
+        limiter.check_at(MessageKind::Activity, 30, start),
+        Throttle::Throttled { first: true, .. }
+    ));
+    // Commands are unaffected by a client chattering about what it does.
+    assert_eq!(
+        limiter.check_at(MessageKind::Command, 30, start),
+        Throttle::Allowed
+    );
+    assert_eq!(
+        limiter.check_at(MessageKind::Activity, 30, start + Duration::from_secs(1)),
+        Throttle::Allowed
+    );
+}
+
+#[actix_rt::test]
+async fn test_activity_command() {
+    let srv = start_server(LimitsConfig {
+        activity: MessageQuota::new(1, 3, 1024, 64 * 1024),
+        ..LimitsConfig::default()
+    });
+    let quiet = Duration::from_millis(300);
+    let (_resp, mut first) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+    let (_resp, mut second) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+
+    for framed in [&mut first, &mut second] {
+        framed
+            .send(Message::Text("[UserCommand]/join lobby".into()))
+            .await
+            .unwrap();
+    }
+    first
+        .send(Message::Text("[UserCommand]/name".into()))
+        .await
+        .unwrap();
+    let first_name = texts(&mut first, quiet)
+        .await
+        .iter()
+        .find_map(|t| t.strip_prefix(WS_PREFIX_SYSTEM_NAME))
+        .map(|name| name.trim().to_string())
+        .expect("name reply");
+    texts(&mut second, quiet).await;
+
+    for state in ["typing", "uploading", "dancing", "none"] {
+        first
+            .send(Message::Text(
+                format!("[UserCommand] /activity {state}").into(),
+            ))
+            .await
+            .unwrap();
+    }
+    let received = texts(&mut first, quiet).await;
+    assert!(activities(&received).is_empty(), "{received:?}");
+    assert!(
+        received
+            .iter()
+            .any(|t| t.starts_with(WS_PREFIX_SYSTEM_ERROR) && t.contains("Activity must be one of"))
+    );
+    // The burst of three is spent before the last update.
+    assert!(
+        received
+            .iter()
+            .any(|t| t.starts_with(WS_PREFIX_SYSTEM_WARNING))
+    );
+    assert_eq!(
+        activities(&texts(&mut second, quiet).await),
+        [
+            json!({ "name": first_name, "activity": "typing" }),
+            json!({ "name": first_name, "activity": "uploading" }),
+        ]
+    );
+}
@@ -0,0 +1,209 @@
+use actix_web::{App, http::StatusCode, test, web};
+use server::{
+    AUTH_FAILURE_METRIC, ApiKey, Scope, ServerConfig, SessionStore, TokenClaims, admin_metrics,
+    chat_ws, create_session, issue_token, metrics,
+};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+fn auth_config() -> ServerConfig {
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.auth.enabled = true;
+    config.auth.token_secret = Some(SECRET.to_string());
+    config.auth.api_keys = vec![ApiKey {
+        name: "ci-runner".to_string(),
+        key: "ci-runner-key-0001".to_string(),
+        scopes: vec![Scope::CreateSession],
+    }];
+    config
+}
+
+fn token(scopes: Vec<Scope>, expires_in: i64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let claims = TokenClaims {
+        sub: "alice@example.com".to_string(),
+        scopes,
+        exp: (now + expires_in) as u64,
+    };
+    issue_token(SECRET, &claims).unwrap()
+}
+
+fn ws_request(uri: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(uri)
+        .insert_header(("Upgrade", "websocket"))
+        .insert_header(("Connection", "Upgrade"))
+        .insert_header(("Sec-WebSocket-Version", "13"))
+        .insert_header(("Sec-WebSocket-Key", "test_key"))
+        .insert_header(("User-Agent", "PastePoint Test Client"))
+        .peer_addr("192.0.2.30:5000".parse().unwrap())
+}
+
+#[actix_rt::test]
+async fn test_create_session_requires_credentials() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(SessionStore::default()))
+            .app_data(web::Data::new(auth_config()))
+            .service(create_session),
+    )
+    .await;
+
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get().uri("/create-session").to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(resp.headers().get("WWW-Authenticate").unwrap(), "Bearer");
+
+    let req = test::TestRequest::get()
+        .uri("/create-session")
+        .insert_header(("Authorization", "Bearer ci-runner-key-0001"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri("/create-session")
+        .insert_header(("Authorization", "Bearer wrong-key"))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[actix_rt::test]
+async fn test_tokens_checked_for_scope_and_expiry() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(SessionStore::default()))
+            .app_data(web::Data::new(auth_config()))
+            .service(create_session),
+    )
+    .await;
+
+    let call = |token: String| {
+        test::TestRequest::get()
+            .uri("/create-session")
+            .insert_header(("Authorization", format!("Bearer {token}")))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, call(token(vec![Scope::CreateSession], 60))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = test::call_service(&app, call(token(vec![Scope::Join], 60))).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = test::call_service(&app, call(token(vec![Scope::CreateSession], -3600))).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // An expiry at the end of time must not overflow with the clock skew.
+    let never = TokenClaims {
+        sub: "alice@example.com".to_string(),
+        scopes: vec![Scope::CreateSession],
+        exp: u64::MAX,
+    };
+    let resp = test::call_service(&app, call(issue_token(SECRET, &never).unwrap())).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // A valid payload paired with another token's signature must fail.
+    let privileged = token(vec![Scope::CreateSession], 60);
+    let limited = token(vec![Scope::Join], 60);
+    let forged = format!(
+        "{}.{}",
+        privileged.split('.').next().unwrap(),
+        limited.split('.').nth(1).unwrap()
+    );
+    let resp = test::call_service(&app, call(forged)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    assert!(
+        metrics().counter(
+            AUTH_FAILURE_METRIC,
+            &[("scope", "create-session"), ("reason", "expired_token")],
+        ) > 0
+    );
+}
+
+#[actix_rt::test]
+async fn test_websocket_token_in_query_parameter() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(SessionStore::default()))
+            .app_data(web::Data::new(auth_config()))
+            .service(chat_ws),
+    )
+    .await;
+
+    let resp = test::call_service(&app, ws_request("/ws").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let uri = format!("/ws?token={}", token(vec![Scope::Join], 60));
+    let resp = test::call_service(&app, ws_request(&uri).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+
+    let req = ws_request("/ws")
+        .insert_header(("Authorization", "Bearer ci-runner-key-0001"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_rt::test]
+async fn test_admin_scope_replaces_loopback_check() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(SessionStore::default()))
+            .app_data(web::Data::new(auth_config()))
+            .service(admin_metrics),
+    )
+    .await;
+
+    let local = test::TestRequest::get()
+        .uri("/admin/metrics")
+        .peer_addr("127.0.0.1:9000".parse().unwrap())
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, local).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    let remote = test::TestRequest::get()
+        .uri("/admin/metrics")
+        .peer_addr("192.0.2.40:9000".parse().unwrap())
+        .insert_header((
+            "Authorization",
+            format!("Bearer {}", token(vec![Scope::Admin], 60)),
+        ))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, remote).await.status(),
+        StatusCode::OK
+    );
+}
+
+#[actix_rt::test]
+async fn test_auth_disabled_by_default() {
+    let config = ServerConfig::load(Some(false)).expect("load config");
+    assert!(!config.auth.enabled);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(SessionStore::default()))
+            .app_data(web::Data::new(config))
+            .service(create_session),
+    )
+    .await;
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get().uri("/create-session").to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
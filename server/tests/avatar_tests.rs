@@ -0,0 +1,121 @@
+use actix_test::{TestServer, start};
+use actix_web::{App, web};
+use awc::{
+    Client,
+    ws::{Frame, Message},
+};
+use futures_util::{SinkExt, StreamExt};
+use jsonwebtoken::{Algorithm, EncodingKey, Header, encode};
+use serde_json::{Value, json};
+use server::{
+    AVATAR_COLORS, AVATAR_EMOJI, Avatar, JwtConfig, JwtVerifier, ServerConfig, SessionStore,
+    WS_PREFIX_SYSTEM_MEMBERS_DELTA, chat_ws,
+};
+use std::collections::HashSet;
+use tokio::time::{Duration, timeout};
+
+const SECRET: &str = "avatar-test-secret-avatar-test!!";
+
+fn start_server() -> TestServer {
+    let jwt = JwtConfig {
+        enabled: true,
+        hmac_secret: Some(SECRET.to_string()),
+        ..JwtConfig::default()
+    };
+    let config = web::Data::new(ServerConfig::load(Some(false)).expect("load config"));
+    let session_manager = web::Data::new(SessionStore::default());
+    let verifier = web::Data::new(JwtVerifier::from_config(&jwt).expect("valid jwt config"));
+
+    start(move || {
+        App::new()
+            .app_data(session_manager.clone())
+            .app_data(config.clone())
+            .app_data(verifier.clone())
+            .service(chat_ws)
+    })
+}
+
+// Joins a room and returns the member entry the room was told about.
+async fn joined_member(srv: &TestServer, path: &str) -> Value {
+    let (_resp, mut framed) = Client::new().ws(srv.url(path)).connect().await.unwrap();
+    framed
+        .send(Message::Text("[UserCommand]/join lobby".into()))
+        .await
+        .unwrap();
+
+    timeout(Duration::from_secs(5), async {
+        while let Some(Ok(frame)) = framed.next().await {
+            if let Frame::Text(text) = frame
+                && let Some(body) = std::str::from_utf8(&text)
+                    .unwrap()
+                    .strip_prefix(WS_PREFIX_SYSTEM_MEMBERS_DELTA)
+            {
+                let delta: Value = serde_json::from_str(body).unwrap();
+                return delta["joined"][0].clone();
+            }
+        }
+        panic!("connection closed before the member delta");
+    })
+    .await
+    .expect("member delta")
+}
+
+#[test]
+fn test_avatar_is_deterministic() {
+    let avatar = Avatar::new("user-42", "Ada King Lovelace");
+    assert_eq!(avatar, Avatar::new("user-42", "Ada King Lovelace"));
+    // Pinned so that a change to the derivation, which would give every
+    // peer a new avatar, does not go unnoticed.
+    assert_eq!(
+        serde_json::to_value(&avatar).unwrap(),
+        json!({"color": "#9575CD", "emoji": "🐸", "initials": "AL"})
+    );
+
+    let colors: HashSet<_> = (0..100)
+        .map(|n| Avatar::new(&format!("user-{n}"), "").color)
+        .collect();
+    assert!(colors.len() > AVATAR_COLORS.len() / 2, "{colors:?}");
+    assert!(colors.iter().all(|color| AVATAR_COLORS.contains(color)));
+    assert!(AVATAR_EMOJI.contains(&avatar.emoji));
+}
+
+#[test]
+fn test_avatar_initials() {
+    for (name, initials) in [
+        ("Ada King Lovelace", "AL"),
+        ("grace", "G"),
+        ("  élan   vital ", "ÉV"),
+        ("", ""),
+    ] {
+        assert_eq!(Avatar::new("id", name).initials, initials, "{name:?}");
+    }
+}
+
+#[actix_rt::test]
+async fn test_same_peer_gets_the_same_avatar() {
+    let srv = start_server();
+    let exp = jsonwebtoken::get_current_timestamp() + 300;
+    let token = encode(
+        &Header::new(Algorithm::HS256),
+        &json!({ "sub": "device-1", "name": "Grace Hopper", "exp": exp }),
+        &EncodingKey::from_secret(SECRET.as_bytes()),
+    )
+    .unwrap();
+
+    let first = joined_member(&srv, &format!("/ws?jwt={token}")).await;
+    let again = joined_member(&srv, &format!("/ws?jwt={token}")).await;
+    assert_eq!(first["name"], "Grace Hopper");
+    assert_eq!(
+        first["avatar"],
+        serde_json::to_value(Avatar::new("device-1", "Grace Hopper")).unwrap()
+    );
+    assert_eq!(again["avatar"], first["avatar"]);
+
+    // Anonymous peers are drawn from their generated name.
+    let anonymous = joined_member(&srv, "/ws").await;
+    let name = anonymous["name"].as_str().unwrap();
+    assert_eq!(
+        anonymous["avatar"],
+        serde_json::to_value(Avatar::new(name, name)).unwrap()
+    );
+}
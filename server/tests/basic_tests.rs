@@ -194,10 +194,13 @@ async fn test_cors_origin_checking() {
     let session_manager = web::Data::new(SessionStore::default());
     let mut config_value =
         ServerConfig::load(Some(false)).expect("Failed to load server configuration");
-    config_value.cors_allowed_origins = "https://pastepoint.com".to_string();
+    config_value.cors_allowed_origins = vec![
+        "https://pastepoint.com".parse().unwrap(),
+        "https://*.pastepoint.com".parse().unwrap(),
+    ];
     let config = web::Data::new(config_value);
     let config_for_test = config.clone();
-    let allowed_domain = config_for_test.cors_allowed_origins.clone();
+    let allowed_domain = "https://pastepoint.com".to_string();
 
     let app = test::init_service(
         App::new()
@@ -0,0 +1,169 @@
+use actix_http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::{App, http::StatusCode, test, web};
+use ipnet::IpNet;
+use server::{ForwardedHeader, ServerConfig, SessionStore, chat_ws, resolve_client_ip};
+use std::net::IpAddr;
+
+fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+    let mut map = HeaderMap::new();
+    for (name, value) in pairs {
+        map.append(
+            HeaderName::from_static(name),
+            HeaderValue::from_str(value).unwrap(),
+        );
+    }
+    map
+}
+
+fn trusted() -> Vec<IpNet> {
+    vec!["10.0.0.0/8".parse().unwrap(), "::1/128".parse().unwrap()]
+}
+
+const XFF: ForwardedHeader = ForwardedHeader::XForwardedFor;
+
+fn ip(s: &str) -> Option<IpAddr> {
+    Some(s.parse().unwrap())
+}
+
+#[actix_rt::test]
+async fn test_untrusted_peer_ignores_forwarding_headers() {
+    let h = headers(&[("x-forwarded-for", "1.2.3.4"), ("x-real-ip", "5.6.7.8")]);
+    assert_eq!(
+        resolve_client_ip(ip("203.0.113.9"), &h, &trusted(), XFF),
+        ip("203.0.113.9")
+    );
+    assert_eq!(
+        resolve_client_ip(ip("10.0.0.1"), &h, &[], XFF),
+        ip("10.0.0.1")
+    );
+}
+
+#[actix_rt::test]
+async fn test_x_forwarded_for_walked_right_to_left() {
+    // The left-most entry is attacker controlled; the right-most untrusted hop wins.
+    let h = headers(&[("x-forwarded-for", "6.6.6.6, 198.51.100.7, 10.0.0.2")]);
+    assert_eq!(
+        resolve_client_ip(ip("10.0.0.1"), &h, &trusted(), XFF),
+        ip("198.51.100.7")
+    );
+
+    // Multiple header lines are treated as one list
+    let h = headers(&[
+        ("x-forwarded-for", "6.6.6.6"),
+        ("x-forwarded-for", "198.51.100.7"),
+    ]);
+    assert_eq!(
+        resolve_client_ip(ip("10.0.0.1"), &h, &trusted(), XFF),
+        ip("198.51.100.7")
+    );
+
+    // All hops trusted: the left-most one is the client
+    let h = headers(&[("x-forwarded-for", "10.1.1.1, 10.0.0.2")]);
+    assert_eq!(
+        resolve_client_ip(ip("10.0.0.1"), &h, &trusted(), XFF),
+        ip("10.1.1.1")
+    );
+}
+
+#[actix_rt::test]
+async fn test_only_configured_header_is_read() {
+    let h = headers(&[
+        (
+            "forwarded",
+            "for=6.6.6.6, for=\"[2001:db8:cafe::17]:4711\";proto=https, for=10.0.0.2",
+        ),
+        ("x-forwarded-for", "7.7.7.7"),
+    ]);
+    // A client can send its own `Forwarded`, which nginx passes through.
+    assert_eq!(
+        resolve_client_ip(ip("::1"), &h, &trusted(), XFF),
+        ip("7.7.7.7")
+    );
+    assert_eq!(
+        resolve_client_ip(ip("::1"), &h, &trusted(), ForwardedHeader::Forwarded),
+        ip("2001:db8:cafe::17")
+    );
+
+    let h = headers(&[("forwarded", "proto=https;for=192.0.2.60:8080;by=10.0.0.1")]);
+    assert_eq!(
+        resolve_client_ip(ip("10.0.0.1"), &h, &trusted(), ForwardedHeader::Forwarded),
+        ip("192.0.2.60")
+    );
+    assert_eq!(
+        resolve_client_ip(ip("10.0.0.1"), &h, &trusted(), XFF),
+        ip("10.0.0.1")
+    );
+}
+
+#[actix_rt::test]
+async fn test_unparseable_hop_stops_walk() {
+    let h = headers(&[("forwarded", "for=6.6.6.6, for=_hidden, for=10.0.0.2")]);
+    assert_eq!(
+        resolve_client_ip(ip("10.0.0.1"), &h, &trusted(), ForwardedHeader::Forwarded),
+        ip("10.0.0.2")
+    );
+}
+
+#[actix_rt::test]
+async fn test_x_real_ip_and_missing_headers() {
+    let h = headers(&[("x-real-ip", "198.51.100.7")]);
+    assert_eq!(
+        resolve_client_ip(ip("10.0.0.1"), &h, &trusted(), ForwardedHeader::XRealIp),
+        ip("198.51.100.7")
+    );
+    assert_eq!(
+        resolve_client_ip(ip("10.0.0.1"), &h, &trusted(), XFF),
+        ip("10.0.0.1")
+    );
+    assert_eq!(
+        resolve_client_ip(ip("10.0.0.1"), &HeaderMap::new(), &trusted(), XFF),
+        ip("10.0.0.1")
+    );
+    assert_eq!(
+        resolve_client_ip(None, &HeaderMap::new(), &trusted(), XFF),
+        None
+    );
+}
+
+#[actix_rt::test]
+async fn test_spoofed_header_does_not_change_public_session() {
+    let session_manager = web::Data::new(SessionStore::default());
+    let mut config_value = ServerConfig::load(Some(false)).expect("load config");
+    config_value.trusted_proxies = vec!["10.0.0.0/8".parse().unwrap()];
+    config_value.session_grouping = Default::default();
+    let config = web::Data::new(config_value);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(session_manager.clone())
+            .app_data(config.clone())
+            .service(chat_ws),
+    )
+    .await;
+
+    let mut responses = Vec::new();
+    for (peer, forwarded_for) in [
+        ("203.0.113.9:5000", "198.51.100.7"),
+        ("10.0.0.1:5000", "198.51.100.7"),
+    ] {
+        let req = test::TestRequest::get()
+            .uri("/ws")
+            .insert_header(("Host", "pastepoint.local"))
+            .insert_header(("Upgrade", "websocket"))
+            .insert_header(("Connection", "Upgrade"))
+            .insert_header(("Sec-WebSocket-Version", "13"))
+            .insert_header(("Sec-WebSocket-Key", "test_key"))
+            .insert_header(("User-Agent", "PastePoint Test Client"))
+            .insert_header(("X-Forwarded-For", forwarded_for))
+            .insert_header(("Forwarded", "for=6.6.6.6"))
+            .peer_addr(peer.parse().unwrap())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+        responses.push(resp);
+    }
+
+    let map = session_manager.key_to_session.lock().unwrap();
+    assert!(map.contains_key("pastepoint.local:203.0.113.9"));
+    assert!(map.contains_key("pastepoint.local:198.51.100.7"));
+}
@@ -0,0 +1,251 @@
+use actix_web::{
+    App, HttpResponse,
+    http::{StatusCode, header},
+    middleware::from_fn,
+    test, web,
+};
+use server::{
+    ConfigReloader, ConfigSources, RateLimiter, ServerConfig, SessionStore, admin_config,
+    enforce_rate_limit, reload_config,
+};
+use std::{fs, path::PathBuf, time::Duration};
+
+const DEVELOPMENT: &str = include_str!("../config/development.toml");
+
+fn write_config(path: &PathBuf, replacements: &[(&str, &str)]) {
+    let contents = replacements
+        .iter()
+        .fold(DEVELOPMENT.to_string(), |contents, (from, to)| {
+            assert!(contents.contains(from), "missing '{from}'");
+            contents.replace(from, to)
+        });
+    fs::write(path, contents).unwrap();
+}
+
+fn reloader(name: &str, replacements: &[(&str, &str)]) -> (ConfigReloader, SessionStore, PathBuf) {
+    let path = std::env::temp_dir().join(format!(
+        "pastepoint-reload-{name}-{}.toml",
+        std::process::id()
+    ));
+    write_config(&path, replacements);
+
+    let sources = ConfigSources {
+        file: Some(path.to_string_lossy().into_owned()),
+        ..ConfigSources::default()
+    };
+    let config = ServerConfig::load_from(&sources).expect("load config");
+    let store = SessionStore::new(config.limits);
+    let rate_limiter = RateLimiter::from_config(&config).unwrap();
+    let reloader = ConfigReloader::new(sources, config, rate_limiter, store.clone());
+    (reloader, store, path)
+}
+
+#[actix_rt::test]
+async fn test_reload_applies_safe_fields() {
+    let (reloader, store, path) = reloader("safe", &[]);
+
+    write_config(
+        &path,
+        &[
+            ("log_level = \"debug\"", "log_level = \"info\""),
+            (
+                "cors_allowed_origins = [\"localhost\"]",
+                "cors_allowed_origins = [\"https://pastepoint.com\"]",
+            ),
+            ("max_sessions = 1000", "max_sessions = 5"),
+            (
+                "bind_address = \"0.0.0.0:9000\"",
+                "bind_address = \"0.0.0.0:9100\"",
+            ),
+        ],
+    );
+    let report = reloader.reload().unwrap();
+    assert_eq!(
+        report.applied,
+        ["log_level", "cors_allowed_origins", "limits"]
+    );
+    assert_eq!(report.restart_required, ["bind_address"]);
+
+    let current = reloader.current();
+    assert_eq!(current.log_level, "info");
+    assert_eq!(current.limits.max_sessions, 5);
+    assert_eq!(store.limits().max_sessions, 5);
+    // Listener settings keep their startup value until a restart.
+    assert_eq!(current.bind_address, "0.0.0.0:9000");
+
+    let origin = header::HeaderValue::from_static("https://pastepoint.com");
+    assert!(reloader.check_origin(&origin));
+    assert!(!reloader.check_origin(&header::HeaderValue::from_static("http://localhost:4200")));
+}
+
+#[actix_rt::test]
+async fn test_invalid_reload_keeps_previous_config() {
+    let (reloader, store, path) = reloader("invalid", &[]);
+
+    write_config(
+        &path,
+        &[
+            ("max_sessions = 1000", "max_sessions = 0"),
+            ("rate_limit_per_second = 100", "rate_limit_per_second = 0"),
+        ],
+    );
+    let err = reloader.reload().unwrap_err();
+    assert!(err.contains("limits.max_sessions"), "{err}");
+    assert!(err.contains("rate_limit_per_second"), "{err}");
+
+    assert_eq!(reloader.current().rate_limit_per_second, 100);
+    assert_eq!(store.limits().max_sessions, 1000);
+    let report = reloader.last_report().unwrap();
+    assert!(report.applied.is_empty());
+    assert_eq!(report.error.as_deref(), Some(err.as_str()));
+}
+
+#[actix_rt::test]
+async fn test_rate_limit_updated_live() {
+    let (reloader, _store, path) = reloader(
+        "rate-limit",
+        &[("rate_limit_burst_size = 200", "rate_limit_burst_size = 2")],
+    );
+    let rate_limiter = RateLimiter::from_config(&reloader.current()).unwrap();
+    let app = test::init_service(
+        App::new()
+            .wrap(from_fn(enforce_rate_limit))
+            .app_data(web::Data::new(rate_limiter.clone()))
+            .route("/", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+    let request = || {
+        test::TestRequest::get()
+            .uri("/")
+            .peer_addr("10.0.0.1:1234".parse().unwrap())
+            .to_request()
+    };
+
+    for remaining in ["1", "0"] {
+        let resp = test::call_service(&app, request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("x-ratelimit-remaining").unwrap(),
+            remaining
+        );
+    }
+    let resp = test::call_service(&app, request()).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp.headers().contains_key("retry-after"));
+
+    // The new quota applies straight away, without rebuilding the app.
+    write_config(
+        &path,
+        &[("rate_limit_burst_size = 200", "rate_limit_burst_size = 5")],
+    );
+    let reloaded = ServerConfig::load_from(&ConfigSources {
+        file: Some(path.to_string_lossy().into_owned()),
+        ..ConfigSources::default()
+    })
+    .unwrap();
+    rate_limiter
+        .update(
+            reloaded.rate_limit_per_second,
+            reloaded.rate_limit_burst_size,
+        )
+        .unwrap();
+    let resp = test::call_service(&app, request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("x-ratelimit-limit").unwrap(), "5");
+}
+
+#[actix_rt::test]
+async fn test_admin_config_endpoints() {
+    let (reloader, _store, path) = reloader("admin", &[]);
+    let config = reloader.current();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new((*config).clone()))
+            .app_data(web::Data::new(reloader))
+            .service(reload_config)
+            .service(admin_config),
+    )
+    .await;
+    let request =
+        |req: test::TestRequest, peer: &str| req.peer_addr(peer.parse().unwrap()).to_request();
+
+    write_config(
+        &path,
+        &[("rate_limit_per_second = 100", "rate_limit_per_second = 20")],
+    );
+    let resp = test::call_service(
+        &app,
+        request(
+            test::TestRequest::post().uri("/admin/config/reload"),
+            "10.0.0.1:1234",
+        ),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = test::call_service(
+        &app,
+        request(
+            test::TestRequest::post().uri("/admin/config/reload"),
+            "127.0.0.1:1234",
+        ),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let report: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(
+        report["applied"],
+        serde_json::json!(["rate_limit_per_second"])
+    );
+
+    let resp = test::call_service(
+        &app,
+        request(
+            test::TestRequest::get().uri("/admin/config"),
+            "127.0.0.1:1234",
+        ),
+    )
+    .await;
+    let current: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(current["rate_limit_per_second"], 20);
+    assert_eq!(
+        current["cors_allowed_origins"],
+        serde_json::json!(["localhost"])
+    );
+    assert_eq!(current["limits"]["max_sessions"], 1000);
+    assert_eq!(
+        current["last_reload"]["applied"],
+        serde_json::json!(["rate_limit_per_second"])
+    );
+
+    write_config(
+        &path,
+        &[("rate_limit_burst_size = 200", "rate_limit_burst_size = 0")],
+    );
+    let resp = test::call_service(
+        &app,
+        request(
+            test::TestRequest::post().uri("/admin/config/reload"),
+            "127.0.0.1:1234",
+        ),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+async fn test_watch_reloads_on_file_change() {
+    let (reloader, store, path) = reloader("watch", &[]);
+    let handle = reloader.watch(Duration::from_millis(100));
+
+    actix_rt::time::sleep(Duration::from_millis(250)).await;
+    write_config(
+        &path,
+        &[("max_rooms_per_session = 50", "max_rooms_per_session = 3")],
+    );
+    actix_rt::time::sleep(Duration::from_millis(500)).await;
+
+    assert_eq!(store.limits().max_rooms_per_session, 3);
+    assert_eq!(reloader.current().limits.max_rooms_per_session, 3);
+    handle.abort();
+}
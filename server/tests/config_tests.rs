@@ -0,0 +1,161 @@
+use server::{CliCommand, ConfigSources, ListenerConfig, ServerConfig, parse_args};
+use std::{fs, path::PathBuf};
+
+const BASE: &str = r#"
+[server]
+bind_address = "127.0.0.1:9000"
+key_file_path = "key.pem"
+cert_file_path = "cert.pem"
+auto_join = false
+rate_limit_per_second = 10
+rate_limit_burst_size = 20
+log_level = "info"
+cors_allowed_origins = ["localhost"]
+"#;
+
+fn write_config(name: &str, contents: &str) -> String {
+    let path: PathBuf = std::env::temp_dir().join(format!(
+        "pastepoint-config-{name}-{}.toml",
+        std::process::id()
+    ));
+    fs::write(&path, contents).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+fn sources(file: &str, env: &[(&str, &str)], overrides: &[(&str, &str)]) -> ConfigSources {
+    let pairs = |pairs: &[(&str, &str)]| {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    };
+    ConfigSources {
+        file: Some(file.to_string()),
+        env: pairs(env),
+        overrides: pairs(overrides),
+    }
+}
+
+fn args(args: &[&str]) -> Result<CliCommand, String> {
+    parse_args(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn test_env_and_cli_override_file() {
+    let file = write_config("layers", BASE);
+    let env = [
+        ("PASTEPOINT_BIND_ADDRESS", "127.0.0.1:9100"),
+        ("PASTEPOINT_LOG_LEVEL", "warn"),
+        ("PASTEPOINT_MDNS__ENABLED", "true"),
+        ("PASTEPOINT_TRUSTED_PROXIES", r#"["10.0.0.0/8", "::1/128"]"#),
+        (
+            "PASTEPOINT_LISTENERS",
+            r#"[{"kind": "tcp", "address": "127.0.0.1:9200"}]"#,
+        ),
+    ];
+    let overrides = [("bind_address", "127.0.0.1:9300")];
+
+    let config = ServerConfig::load_from(&sources(&file, &env, &overrides)).unwrap();
+    assert_eq!(config.bind_address, "127.0.0.1:9300");
+    assert_eq!(config.log_level, "warn");
+    assert!(config.mdns.enabled);
+    assert_eq!(config.trusted_proxies.len(), 2);
+    assert_eq!(
+        config.listeners,
+        vec![ListenerConfig::Tcp {
+            address: "127.0.0.1:9200".to_string()
+        }]
+    );
+    // Untouched settings come from the file.
+    assert_eq!(config.rate_limit_per_second, 10);
+}
+
+#[test]
+fn test_config_file_selection() {
+    let env_file = write_config("env-file", &BASE.replace("9000", "9001"));
+    let cli_file = write_config("cli-file", &BASE.replace("9000", "9002"));
+
+    let mut from_env = sources(&env_file, &[], &[]);
+    from_env.file = None;
+    from_env
+        .env
+        .push(("PASTEPOINT_CONFIG".to_string(), env_file.clone()));
+    let config = ServerConfig::load_from(&from_env).unwrap();
+    assert_eq!(config.bind_address, "127.0.0.1:9001");
+
+    let mut from_cli = from_env.clone();
+    from_cli.file = Some(cli_file);
+    let config = ServerConfig::load_from(&from_cli).unwrap();
+    assert_eq!(config.bind_address, "127.0.0.1:9002");
+
+    let missing = sources("/nonexistent/pastepoint", &[], &[]);
+    assert!(ServerConfig::load_from(&missing).is_err());
+}
+
+#[test]
+fn test_validation_reports_every_invalid_field() {
+    let file = write_config("invalid", BASE);
+    let env = [
+        ("PASTEPOINT_RATE_LIMIT_PER_SECOND", "fast"),
+        ("PASTEPOINT_MDNS__INTERFACE", "eth0"),
+        ("PASTEPOINT_CORS_ALLOWED_ORIGINS", r#"["pastepoint.com"]"#),
+        ("PASTEPOINT_IP_FILTER__ALLOW", "[not json"),
+    ];
+    let overrides = [
+        ("bind_address", "not-an-address"),
+        ("key_file_path", ""),
+        ("public_base_url", "ftp://pastepoint.com"),
+    ];
+
+    let err = ServerConfig::load_from(&sources(&file, &env, &overrides))
+        .unwrap_err()
+        .to_string();
+    for expected in [
+        "bind_address 'not-an-address' is not a valid socket address",
+        "key `rate_limit_per_second`",
+        "key `mdns.interface`",
+        "'pastepoint.com' must include a scheme",
+        "PASTEPOINT_IP_FILTER__ALLOW: invalid JSON",
+        "key_file_path is required for tls listeners",
+        "public_base_url 'ftp://pastepoint.com' is not an http(s) URL",
+    ] {
+        assert!(err.contains(expected), "missing '{expected}' in: {err}");
+    }
+}
+
+#[test]
+fn test_parse_args() {
+    let Ok(CliCommand::Run(sources)) = args(&[
+        "--config",
+        "custom.toml",
+        "--bind=0.0.0.0:8443",
+        "--log-level",
+        "info",
+        "--no-auto-join",
+        "-s",
+        "mdns.enabled=true",
+    ]) else {
+        panic!("expected run command");
+    };
+    assert_eq!(sources.file.as_deref(), Some("custom.toml"));
+    assert_eq!(
+        sources.overrides,
+        [
+            ("bind_address", "0.0.0.0:8443"),
+            ("log_level", "info"),
+            ("auto_join", "false"),
+            ("mdns.enabled", "true"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+    );
+
+    assert_eq!(args(&["--help"]), Ok(CliCommand::Help));
+    assert_eq!(args(&["-V"]), Ok(CliCommand::Version));
+    assert!(args(&["--bind"]).unwrap_err().contains("requires a value"));
+    assert!(args(&["--set", "mdns"]).unwrap_err().contains("KEY=VALUE"));
+    assert!(
+        args(&["--verbose"])
+            .unwrap_err()
+            .contains("unknown argument")
+    );
+}
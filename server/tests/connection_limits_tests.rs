@@ -0,0 +1,230 @@
+use actix::prelude::*;
+use actix_test::{TestServer, start};
+use actix_web::{App, web};
+use awc::{
+    Client,
+    error::WsProtocolError,
+    ws::{CloseCode, CloseReason, Frame, Message},
+};
+use futures_util::{SinkExt, Stream, StreamExt};
+use serde_json::Value;
+use server::{
+    ChatMessage, ConnectionLimit, LimitsConfig, ServerConfig, SessionStore, UpdateLimits,
+    WS_CONNECTION_LIMIT_METRIC, WS_PREFIX_SYSTEM_ERROR, WS_PREFIX_SYSTEM_MEMBERS,
+    WS_PREFIX_SYSTEM_NAME, WsChatServer, chat_ws, metrics,
+};
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+
+struct DummyActor;
+
+impl Actor for DummyActor {
+    type Context = Context<Self>;
+}
+
+impl Handler<ChatMessage> for DummyActor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: ChatMessage, _ctx: &mut Context<Self>) {}
+}
+
+fn start_server(limits: LimitsConfig) -> (TestServer, SessionStore) {
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.limits = limits;
+    let config = web::Data::new(config);
+    let store = SessionStore::new(limits);
+    let data = web::Data::new(store.clone());
+
+    let srv = start(move || {
+        // The chat server is a registry service of the test server's system.
+        WsChatServer::from_registry().do_send(UpdateLimits(limits));
+        App::new()
+            .app_data(data.clone())
+            .app_data(config.clone())
+            .service(chat_ws)
+    });
+    (srv, store)
+}
+
+// Waits for the server to close the connection and returns its reason.
+async fn close_reason<S>(framed: &mut S) -> Option<CloseReason>
+where
+    S: Stream<Item = Result<Frame, WsProtocolError>> + Unpin,
+{
+    timeout(Duration::from_secs(2), async {
+        while let Some(Ok(frame)) = framed.next().await {
+            if let Frame::Close(reason) = frame {
+                return reason;
+            }
+        }
+        None
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+// Collects text frames until nothing arrives for a while.
+async fn texts<S>(framed: &mut S) -> Vec<String>
+where
+    S: Stream<Item = Result<Frame, WsProtocolError>> + Unpin,
+{
+    let mut texts = Vec::new();
+    while let Ok(Some(Ok(frame))) = timeout(Duration::from_millis(300), framed.next()).await {
+        if let Frame::Text(text) = frame {
+            texts.push(String::from_utf8_lossy(&text).into_owned());
+        }
+    }
+    texts
+}
+
+#[actix_rt::test]
+async fn test_per_ip_connection_cap() {
+    let (srv, store) = start_server(LimitsConfig {
+        max_connections_per_ip: 2,
+        ..LimitsConfig::default()
+    });
+    let rejected_before = metrics().counter(WS_CONNECTION_LIMIT_METRIC, &[("limit", "ip")]);
+
+    let (_, first) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+    let (_, _second) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+    assert_eq!(store.connections_for_ip("127.0.0.1".parse().unwrap()), 2);
+
+    let (_, mut third) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+    let reason = close_reason(&mut third)
+        .await
+        .expect("third connection closed");
+    assert_eq!(reason.code, CloseCode::Again);
+    assert_eq!(
+        reason.description,
+        Some(ConnectionLimit::PerIp(2).close_reason())
+    );
+    assert_eq!(store.connections_for_ip("127.0.0.1".parse().unwrap()), 2);
+    assert!(metrics().counter(WS_CONNECTION_LIMIT_METRIC, &[("limit", "ip")]) > rejected_before);
+
+    // Closing a connection frees up a slot.
+    drop(first);
+    sleep(Duration::from_millis(500)).await;
+    let (_, mut fourth) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+    assert!(close_reason(&mut fourth).await.is_none());
+}
+
+#[actix_rt::test]
+async fn test_per_session_connection_cap() {
+    let (srv, _store) = start_server(LimitsConfig {
+        max_connections_per_session: 2,
+        ..LimitsConfig::default()
+    });
+
+    let (_, _first) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+    let (_, _second) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+    let (_, mut third) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+
+    let reason = close_reason(&mut third)
+        .await
+        .expect("third connection closed");
+    assert_eq!(reason.code, CloseCode::Again);
+    assert_eq!(
+        reason.description.as_deref(),
+        Some("Session is full (max 2 connections)")
+    );
+}
+
+#[actix_rt::test]
+async fn test_per_room_client_cap() {
+    let mut server = WsChatServer::new(LimitsConfig {
+        max_clients_per_room: 2,
+        ..LimitsConfig::default()
+    });
+    let client = DummyActor.start().recipient();
+
+    for name in ["a", "b"] {
+        let added = server.add_client_to_room("session", "main", None, client.clone(), name.into());
+        assert!(added.is_some());
+    }
+    assert!(server.is_room_full("session", "main"));
+    let third = server.add_client_to_room("session", "main", None, client.clone(), "c".into());
+    assert!(third.is_none());
+
+    // Other rooms in the session still have space.
+    let other = server.add_client_to_room("session", "other", None, client, "c".into());
+    assert!(other.is_some());
+    assert!(!server.is_room_full("session", "other"));
+}
+
+#[actix_rt::test]
+async fn test_full_room_keeps_client_in_its_room() {
+    let (srv, _store) = start_server(LimitsConfig {
+        max_clients_per_room: 2,
+        ..LimitsConfig::default()
+    });
+    let room_full = ConnectionLimit::PerRoom(2).close_reason();
+
+    let mut lobby = Vec::new();
+    for _ in 0..2 {
+        let (_, mut framed) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+        framed
+            .send(Message::Text("[UserCommand] /join lobby".into()))
+            .await
+            .unwrap();
+        lobby.push(framed);
+    }
+    let (_, mut client) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+    for command in ["/join main", "/name"] {
+        client
+            .send(Message::Text(format!("[UserCommand] {command}").into()))
+            .await
+            .unwrap();
+    }
+    let name = texts(&mut client)
+        .await
+        .iter()
+        .find_map(|t| t.strip_prefix(WS_PREFIX_SYSTEM_NAME))
+        .map(|name| name.trim().to_string())
+        .expect("name reply");
+
+    client
+        .send(Message::Text("[UserCommand] /join lobby".into()))
+        .await
+        .unwrap();
+    let received = texts(&mut client).await;
+    assert!(
+        received
+            .iter()
+            .any(|t| t.starts_with(WS_PREFIX_SYSTEM_ERROR) && t.contains(&room_full)),
+        "{received:?}"
+    );
+
+    // Still in main under the same name, and counted there.
+    client
+        .send(Message::Text("[UserCommand] /sync".into()))
+        .await
+        .unwrap();
+    let snapshot: Value = texts(&mut client)
+        .await
+        .iter()
+        .find_map(|t| t.strip_prefix(WS_PREFIX_SYSTEM_MEMBERS))
+        .map(|body| serde_json::from_str(body).unwrap())
+        .expect("members snapshot");
+    assert_eq!(snapshot["room"], "main");
+    let members: Vec<&str> = snapshot["members"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|m| m["name"].as_str())
+        .collect();
+    assert_eq!(members, [name.as_str()]);
+
+    // A client in no room is told why and stays connected.
+    let (_, mut roomless) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+    roomless
+        .send(Message::Text("[UserCommand] /join lobby".into()))
+        .await
+        .unwrap();
+    let received = texts(&mut roomless).await;
+    assert!(
+        received.iter().any(|t| t.contains(&room_full)),
+        "{received:?}"
+    );
+    assert!(close_reason(&mut roomless).await.is_none());
+}
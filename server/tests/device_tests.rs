@@ -0,0 +1,170 @@
+use awc::{
+    Client,
+    error::WsProtocolError,
+    ws::{Frame, Message},
+};
+use futures_util::{SinkExt, Stream, StreamExt};
+use serde_json::{Value, json};
+use server::{DeviceInfo, DeviceType, WS_PREFIX_SYSTEM_ERROR, WS_PREFIX_SYSTEM_MEMBERS_DELTA};
+use std::time::Duration;
+use tokio::time::timeout;
+
+mod common;
+use common::init_test_server;
+
+const IPHONE_SAFARI: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_4 like Mac OS X) \
+    AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Mobile/15E148 Safari/604.1";
+const WINDOWS_CHROME: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) \
+    AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+
+// Collects text frames until nothing arrives for half a second.
+async fn texts<S>(framed: &mut S) -> Vec<String>
+where
+    S: Stream<Item = Result<Frame, WsProtocolError>> + Unpin,
+{
+    let mut texts = Vec::new();
+    while let Ok(Some(Ok(frame))) = timeout(Duration::from_millis(500), framed.next()).await {
+        if let Frame::Text(text) = frame {
+            texts.push(String::from_utf8_lossy(&text).into_owned());
+        }
+    }
+    texts
+}
+
+// Joined and updated members of the member deltas among `texts`.
+fn delta_members(texts: &[String]) -> Vec<Value> {
+    texts
+        .iter()
+        .filter_map(|t| t.strip_prefix(WS_PREFIX_SYSTEM_MEMBERS_DELTA))
+        .flat_map(|body| {
+            let delta: Value = serde_json::from_str(body).unwrap();
+            [delta["joined"].clone(), delta["updated"].clone()]
+        })
+        .flat_map(|list| list.as_array().unwrap().clone())
+        .collect()
+}
+
+#[test]
+fn test_device_from_user_agent() {
+    for (user_agent, device_type, os, version) in [
+        (IPHONE_SAFARI, DeviceType::Mobile, Some("iOS 17.4"), None),
+        (
+            "Mozilla/5.0 (Linux; Android 14; Pixel 8) Chrome/124.0 Mobile Safari/537.36",
+            DeviceType::Mobile,
+            Some("Android 14"),
+            None,
+        ),
+        (
+            "Mozilla/5.0 (Linux; Android 13; SM-X710) Chrome/124.0 Safari/537.36",
+            DeviceType::Tablet,
+            Some("Android 13"),
+            None,
+        ),
+        (WINDOWS_CHROME, DeviceType::Desktop, Some("Windows"), None),
+        (
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) Firefox/125.0",
+            DeviceType::Desktop,
+            Some("macOS"),
+            None,
+        ),
+        (
+            "PastePoint/1.4.0 CFNetwork/1494.0.7 Darwin/23.4.0",
+            DeviceType::Unknown,
+            None,
+            Some("1.4.0"),
+        ),
+        ("curl/8.5.0", DeviceType::Unknown, None, None),
+    ] {
+        let device = DeviceInfo::from_user_agent(user_agent);
+        assert_eq!(device.device_type, device_type, "{user_agent}");
+        assert_eq!(device.os.as_deref(), os, "{user_agent}");
+        assert_eq!(device.client_version.as_deref(), version, "{user_agent}");
+    }
+}
+
+#[test]
+fn test_registered_device_overrides_user_agent() {
+    let mut device = DeviceInfo::from_user_agent("PastePoint/1.4.0 CFNetwork/1494.0.7");
+    device.register(DeviceInfo {
+        device_type: DeviceType::Tablet,
+        os: Some(" iPadOS\u{7} 17.4 ".into()),
+        client_version: None,
+    });
+    assert_eq!(
+        device,
+        DeviceInfo {
+            device_type: DeviceType::Tablet,
+            os: Some("iPadOS 17.4".into()),
+            client_version: Some("1.4.0".into()),
+        }
+    );
+
+    device.register(DeviceInfo {
+        os: Some("x".repeat(500)),
+        ..DeviceInfo::default()
+    });
+    assert_eq!(device.device_type, DeviceType::Tablet);
+    assert_eq!(device.os.unwrap().len(), 64);
+}
+
+#[actix_rt::test]
+async fn test_member_lists_include_devices() {
+    let srv = init_test_server(false);
+    let (_resp, mut first) = Client::new()
+        .ws(srv.url("/ws"))
+        .header("User-Agent", WINDOWS_CHROME)
+        .connect()
+        .await
+        .unwrap();
+    let (_resp, mut second) = Client::new()
+        .ws(srv.url("/ws"))
+        .header("User-Agent", IPHONE_SAFARI)
+        .connect()
+        .await
+        .unwrap();
+
+    first
+        .send(Message::Text("[UserCommand]/join lobby".into()))
+        .await
+        .unwrap();
+    texts(&mut first).await;
+    second
+        .send(Message::Text("[UserCommand]/join lobby".into()))
+        .await
+        .unwrap();
+    texts(&mut second).await;
+
+    let members = delta_members(&texts(&mut first).await);
+    assert_eq!(members.len(), 1, "{members:?}");
+    assert_eq!(
+        members[0]["device"],
+        json!({"type": "mobile", "os": "iOS 17.4"})
+    );
+
+    // A client can describe itself better than its User-Agent does.
+    second
+        .send(Message::Text(
+            r#"[UserCommand]/device {"type":"tablet","client_version":"2.0.1"}"#.into(),
+        ))
+        .await
+        .unwrap();
+    let members = delta_members(&texts(&mut first).await);
+    assert_eq!(members.len(), 1, "{members:?}");
+    assert_eq!(
+        members[0]["device"],
+        json!({"type": "tablet", "os": "iOS 17.4", "client_version": "2.0.1"})
+    );
+
+    second
+        .send(Message::Text(
+            r#"[UserCommand]/device {"type":"fridge"}"#.into(),
+        ))
+        .await
+        .unwrap();
+    assert!(
+        texts(&mut second)
+            .await
+            .iter()
+            .any(|t| t.starts_with(WS_PREFIX_SYSTEM_ERROR))
+    );
+}
@@ -0,0 +1,171 @@
+use actix_web::{App, http::StatusCode, test, web};
+use server::{
+    IpFilter, IpFilterConfig, IpRules, ServerConfig, SessionStore, chat_ws, create_session,
+    reload_ip_rules,
+};
+use std::{fs, path::PathBuf, time::Duration};
+
+fn rules_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "pastepoint-ip-rules-{name}-{}.toml",
+        std::process::id()
+    ));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn ws_request(peer: &str) -> actix_http::Request {
+    test::TestRequest::get()
+        .uri("/ws")
+        .insert_header(("Upgrade", "websocket"))
+        .insert_header(("Connection", "Upgrade"))
+        .insert_header(("Sec-WebSocket-Version", "13"))
+        .insert_header(("Sec-WebSocket-Key", "test_key"))
+        .insert_header(("User-Agent", "PastePoint Test Client"))
+        .peer_addr(peer.parse().unwrap())
+        .to_request()
+}
+
+#[actix_rt::test]
+async fn test_ip_rules_allow_and_deny() {
+    let rules = IpRules {
+        allow: vec![
+            "10.0.0.0/8".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ],
+        deny: vec!["10.0.5.0/24".parse().unwrap()],
+    };
+
+    assert!(rules.is_allowed("10.1.2.3".parse().unwrap()));
+    assert!(rules.is_allowed("2001:db8::1".parse().unwrap()));
+    assert!(rules.is_allowed("::ffff:10.1.2.3".parse().unwrap()));
+    assert!(!rules.is_allowed("10.0.5.9".parse().unwrap()));
+    assert!(!rules.is_allowed("192.168.1.1".parse().unwrap()));
+
+    let deny_only = IpRules {
+        allow: vec![],
+        deny: vec!["203.0.113.0/24".parse().unwrap()],
+    };
+    assert!(deny_only.is_allowed("192.168.1.1".parse().unwrap()));
+    assert!(!deny_only.is_allowed("203.0.113.7".parse().unwrap()));
+}
+
+#[actix_rt::test]
+async fn test_filter_enforced_on_routes() {
+    let session_manager = web::Data::new(SessionStore::default());
+    let config = web::Data::new(ServerConfig::load(Some(false)).expect("load config"));
+    let filter = IpFilter::from_config(&IpFilterConfig {
+        deny: vec!["192.168.66.0/24".parse().unwrap()],
+        ..IpFilterConfig::default()
+    })
+    .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(session_manager.clone())
+            .app_data(config.clone())
+            .app_data(web::Data::new(filter))
+            .service(chat_ws)
+            .service(create_session),
+    )
+    .await;
+
+    let resp = test::call_service(&app, ws_request("192.168.66.5:4000")).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get()
+        .uri("/create-session")
+        .peer_addr("192.168.66.5:4000".parse().unwrap())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get()
+        .uri("/create-session")
+        .peer_addr("192.168.1.5:4000".parse().unwrap())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = test::call_service(&app, ws_request("192.168.1.5:4000")).await;
+    assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+}
+
+#[actix_rt::test]
+async fn test_admin_reload_applies_rules_file() {
+    let path = rules_file("admin", "deny = []\n");
+    let filter = IpFilter::from_config(&IpFilterConfig {
+        rules_file: Some(path.to_string_lossy().into_owned()),
+        ..IpFilterConfig::default()
+    })
+    .unwrap();
+    let config = web::Data::new(ServerConfig::load(Some(false)).expect("load config"));
+    let filter_data = web::Data::new(filter.clone());
+
+    let app = test::init_service(
+        App::new()
+            .app_data(config.clone())
+            .app_data(filter_data.clone())
+            .service(reload_ip_rules),
+    )
+    .await;
+
+    assert!(filter.is_allowed("198.51.100.7".parse().unwrap()));
+    fs::write(&path, "deny = [\"198.51.100.0/24\"]\n").unwrap();
+
+    // Non-local callers may not trigger a reload
+    let req = test::TestRequest::post()
+        .uri("/admin/ip-rules/reload")
+        .peer_addr("198.51.100.7:4000".parse().unwrap())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    assert!(filter.is_allowed("198.51.100.7".parse().unwrap()));
+
+    let req = test::TestRequest::post()
+        .uri("/admin/ip-rules/reload")
+        .peer_addr("127.0.0.1:4000".parse().unwrap())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(!filter.is_allowed("198.51.100.7".parse().unwrap()));
+
+    // An invalid file is rejected and the previous rules stay in effect
+    fs::write(&path, "deny = [\"not-a-cidr\"]\n").unwrap();
+    let req = test::TestRequest::post()
+        .uri("/admin/ip-rules/reload")
+        .peer_addr("127.0.0.1:4000".parse().unwrap())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert!(!filter.is_allowed("198.51.100.7".parse().unwrap()));
+
+    fs::remove_file(&path).ok();
+}
+
+#[actix_rt::test]
+async fn test_rules_file_watch_reloads() {
+    let path = rules_file("watch", "allow = []\n");
+    let filter = IpFilter::from_config(&IpFilterConfig {
+        rules_file: Some(path.to_string_lossy().into_owned()),
+        ..IpFilterConfig::default()
+    })
+    .unwrap();
+    let _watcher = filter.watch(Duration::from_millis(20));
+
+    assert!(filter.is_allowed("192.168.1.5".parse().unwrap()));
+    fs::write(&path, "allow = [\"10.0.0.0/8\"]\ndeny = []\n").unwrap();
+
+    let mut reloaded = false;
+    for _ in 0..100 {
+        actix_rt::time::sleep(Duration::from_millis(20)).await;
+        if !filter.is_allowed("192.168.1.5".parse().unwrap()) {
+            reloaded = true;
+            break;
+        }
+    }
+    assert!(reloaded, "Rules file change was not picked up");
+    assert!(filter.is_allowed("10.1.1.1".parse().unwrap()));
+
+    fs::remove_file(&path).ok();
+}
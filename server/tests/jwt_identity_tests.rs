@@ -0,0 +1,301 @@
+use actix_test::{TestServer, start};
+use actix_web::{App, web};
+use awc::{
+    Client,
+    ws::{Frame, Message},
+};
+use futures_util::{SinkExt, StreamExt};
+use jsonwebtoken::{Algorithm, EncodingKey, Header, encode, jwk::Jwk};
+use openssl::{pkey::PKey, rsa::Rsa};
+use serde_json::json;
+use server::{JwtConfig, JwtVerifier, ServerConfig, SessionStore, chat_ws};
+use std::{fs, path::PathBuf};
+use tokio::time::{Duration, timeout};
+
+const SECRET: &str = "jwt-test-secret-jwt-test-secret!";
+
+fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("pastepoint-jwt-{name}-{}", std::process::id()));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn claims(sub: &str, name: Option<&str>) -> serde_json::Value {
+    let exp = jsonwebtoken::get_current_timestamp() + 300;
+    match name {
+        Some(name) => json!({ "sub": sub, "name": name, "exp": exp }),
+        None => json!({ "sub": sub, "exp": exp }),
+    }
+}
+
+fn hs256_token(claims: &serde_json::Value) -> String {
+    encode(
+        &Header::new(Algorithm::HS256),
+        claims,
+        &EncodingKey::from_secret(SECRET.as_bytes()),
+    )
+    .unwrap()
+}
+
+fn hmac_config() -> JwtConfig {
+    JwtConfig {
+        enabled: true,
+        hmac_secret: Some(SECRET.to_string()),
+        ..JwtConfig::default()
+    }
+}
+
+fn start_server(jwt: JwtConfig) -> TestServer {
+    let config = web::Data::new(ServerConfig::load(Some(false)).expect("load config"));
+    let session_manager = web::Data::new(SessionStore::default());
+    let verifier = web::Data::new(JwtVerifier::from_config(&jwt).expect("valid jwt config"));
+
+    start(move || {
+        App::new()
+            .app_data(session_manager.clone())
+            .app_data(config.clone())
+            .app_data(verifier.clone())
+            .service(chat_ws)
+    })
+}
+
+// Connects and asks the server for the session's display name.
+async fn display_name(srv: &TestServer, path: &str) -> String {
+    let (_resp, mut framed) = Client::new()
+        .ws(srv.url(path))
+        .connect()
+        .await
+        .expect("Failed to connect");
+    framed
+        .send(Message::Text("[UserCommand]/name".into()))
+        .await
+        .unwrap();
+
+    let name = timeout(Duration::from_secs(5), async {
+        while let Some(Ok(frame)) = framed.next().await {
+            if let Frame::Text(text) = frame
+                && let Some(name) = std::str::from_utf8(&text)
+                    .unwrap()
+                    .strip_prefix("[SystemName] ")
+            {
+                return name.to_string();
+            }
+        }
+        panic!("connection closed before a name was received");
+    })
+    .await
+    .expect("Timed out waiting for the session name");
+
+    framed.close().await.unwrap();
+    name
+}
+
+async fn connect_status(srv: &TestServer, path: &str) -> Option<u16> {
+    match Client::new().ws(srv.url(path)).connect().await {
+        Ok(_) => None,
+        Err(awc::error::WsClientError::InvalidResponseStatus(status)) => Some(status.as_u16()),
+        Err(e) => panic!("unexpected error: {e}"),
+    }
+}
+
+#[actix_rt::test]
+async fn test_hs256_name_claim_used() {
+    let srv = start_server(hmac_config());
+    let token = hs256_token(&claims("u-1001", Some("Alice Example")));
+
+    assert_eq!(
+        display_name(&srv, &format!("/ws?jwt={token}")).await,
+        "Alice Example"
+    );
+}
+
+// Reads frames until a text starting with `prefix` arrives and returns the
+// rest of it.
+async fn next_text<S>(framed: &mut S, prefix: &str) -> String
+where
+    S: futures_util::Stream<Item = Result<Frame, awc::error::WsProtocolError>> + Unpin,
+{
+    timeout(Duration::from_secs(5), async {
+        while let Some(Ok(frame)) = framed.next().await {
+            if let Frame::Text(text) = frame
+                && let Some(rest) = std::str::from_utf8(&text).unwrap().strip_prefix(prefix)
+            {
+                return rest.trim().to_string();
+            }
+        }
+        panic!("connection closed before '{prefix}'");
+    })
+    .await
+    .expect("Timed out waiting for a message")
+}
+
+#[actix_rt::test]
+async fn test_devices_sharing_a_name_stay_addressable() {
+    let srv = start_server(hmac_config());
+    let path = format!(
+        "/ws?jwt={}",
+        hs256_token(&claims("u-1001", Some("Alice Example")))
+    );
+    let (_resp, mut laptop) = Client::new().ws(srv.url(&path)).connect().await.unwrap();
+    let (_resp, mut phone) = Client::new().ws(srv.url(&path)).connect().await.unwrap();
+
+    laptop
+        .send(Message::Text("[UserCommand]/join lobby".into()))
+        .await
+        .unwrap();
+    next_text(&mut laptop, "[SystemMembers]").await;
+    phone
+        .send(Message::Text("[UserCommand]/join lobby".into()))
+        .await
+        .unwrap();
+    // The second device is told the name it goes by.
+    assert_eq!(
+        next_text(&mut phone, "[SystemName]").await,
+        "Alice Example (2)"
+    );
+
+    let offer = json!({ "type": "offer", "to": "Alice Example (2)" }).to_string();
+    laptop
+        .send(Message::Text(format!("[SignalMessage] {offer}").into()))
+        .await
+        .unwrap();
+    assert_eq!(next_text(&mut phone, "[SignalMessage]").await, offer);
+}
+
+#[actix_rt::test]
+async fn test_missing_token_falls_back_to_random_name() {
+    let srv = start_server(hmac_config());
+    let name = display_name(&srv, "/ws").await;
+    assert!(!name.is_empty());
+    assert_ne!(name, "Alice Example");
+
+    // A token without the name claim keeps the random name too.
+    let token = hs256_token(&claims("u-1002", None));
+    assert!(
+        !display_name(&srv, &format!("/ws?jwt={token}"))
+            .await
+            .is_empty()
+    );
+}
+
+#[actix_rt::test]
+async fn test_invalid_or_required_tokens_rejected() {
+    let srv = start_server(hmac_config());
+    let forged = encode(
+        &Header::new(Algorithm::HS256),
+        &claims("u-1003", Some("Mallory")),
+        &EncodingKey::from_secret(b"some-other-secret-some-other-secret"),
+    )
+    .unwrap();
+    assert_eq!(
+        connect_status(&srv, &format!("/ws?jwt={forged}")).await,
+        Some(401)
+    );
+
+    let srv = start_server(JwtConfig {
+        required: true,
+        ..hmac_config()
+    });
+    assert_eq!(connect_status(&srv, "/ws").await, Some(401));
+}
+
+#[actix_rt::test]
+async fn test_rs256_and_eddsa_public_keys() {
+    let rsa = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    let ed = PKey::generate_ed25519().unwrap();
+    let rsa_pub = temp_file("rsa.pem", &rsa.public_key_to_pem().unwrap());
+    let ed_pub = temp_file("ed.pem", &ed.public_key_to_pem().unwrap());
+
+    let srv = start_server(JwtConfig {
+        enabled: true,
+        rsa_public_key_file: Some(rsa_pub.to_string_lossy().into_owned()),
+        ed25519_public_key_file: Some(ed_pub.to_string_lossy().into_owned()),
+        name_claim: "preferred_username".to_string(),
+        ..JwtConfig::default()
+    });
+
+    let rs_token = encode(
+        &Header::new(Algorithm::RS256),
+        &json!({ "sub": "u-2001", "preferred_username": "bob", "exp": jsonwebtoken::get_current_timestamp() + 300 }),
+        &EncodingKey::from_rsa_pem(&rsa.private_key_to_pem_pkcs8().unwrap()).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        display_name(&srv, &format!("/ws?jwt={rs_token}")).await,
+        "bob"
+    );
+
+    let ed_token = encode(
+        &Header::new(Algorithm::EdDSA),
+        &json!({ "sub": "u-2002", "preferred_username": "carol", "exp": jsonwebtoken::get_current_timestamp() + 300 }),
+        &EncodingKey::from_ed_pem(&ed.private_key_to_pem_pkcs8().unwrap()).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        display_name(&srv, &format!("/ws?jwt={ed_token}")).await,
+        "carol"
+    );
+
+    // HS256 is allowed by default but no secret is configured here.
+    let hs_token = hs256_token(&claims("u-2003", Some("dave")));
+    assert_eq!(
+        connect_status(&srv, &format!("/ws?jwt={hs_token}")).await,
+        Some(401)
+    );
+
+    fs::remove_file(rsa_pub).ok();
+    fs::remove_file(ed_pub).ok();
+}
+
+#[actix_rt::test]
+async fn test_jwks_key_selected_by_kid() {
+    let rsa = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    let encoding_key = EncodingKey::from_rsa_pem(&rsa.private_key_to_pem_pkcs8().unwrap()).unwrap();
+    let mut jwk = Jwk::from_encoding_key(&encoding_key, Algorithm::RS256).unwrap();
+    jwk.common.key_id = Some("intranet-2026".to_string());
+    let jwks = temp_file(
+        "jwks.json",
+        serde_json::to_string(&json!({ "keys": [jwk] }))
+            .unwrap()
+            .as_bytes(),
+    );
+
+    let verifier = JwtVerifier::from_config(&JwtConfig {
+        enabled: true,
+        jwks_file: Some(jwks.to_string_lossy().into_owned()),
+        issuer: Some("https://sso.example".to_string()),
+        ..JwtConfig::default()
+    })
+    .unwrap();
+
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some("intranet-2026".to_string());
+    let exp = jsonwebtoken::get_current_timestamp() + 300;
+    let token = encode(
+        &header,
+        &json!({ "sub": "u-3001", "name": "Erin", "iss": "https://sso.example", "exp": exp }),
+        &encoding_key,
+    )
+    .unwrap();
+    let identity = verifier.verify(&token).unwrap();
+    assert_eq!(identity.peer_id, "u-3001");
+    assert_eq!(identity.display_name.as_deref(), Some("Erin"));
+
+    let wrong_issuer = encode(
+        &header,
+        &json!({ "sub": "u-3001", "iss": "https://evil.example", "exp": exp }),
+        &encoding_key,
+    )
+    .unwrap();
+    assert!(verifier.verify(&wrong_issuer).is_err());
+
+    assert!(
+        JwtVerifier::from_config(&JwtConfig {
+            enabled: true,
+            ..JwtConfig::default()
+        })
+        .is_err()
+    );
+
+    fs::remove_file(jwks).ok();
+}
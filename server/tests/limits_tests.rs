@@ -0,0 +1,240 @@
+use actix::prelude::*;
+use actix_test::{TestServer, start};
+use actix_web::{App, http::StatusCode, test, web};
+use awc::{
+    Client,
+    error::WsProtocolError,
+    ws::{Frame, Message},
+};
+use futures_util::{SinkExt, Stream, StreamExt};
+use server::{
+    ChatMessage, ConfigSources, LimitsConfig, MAX_FRAME_SIZE, MessageQuota, SESSION_CODE_LENGTH,
+    ServerConfig, SessionStore, WsChatServer, chat_ws, create_session,
+};
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+use uuid::Uuid;
+
+struct DummyActor;
+
+impl Actor for DummyActor {
+    type Context = Context<Self>;
+}
+
+impl Handler<ChatMessage> for DummyActor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: ChatMessage, _ctx: &mut Context<Self>) {}
+}
+
+fn start_server(limits: LimitsConfig) -> TestServer {
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.limits = limits;
+    let config = web::Data::new(config);
+    let store = web::Data::new(SessionStore::new(limits));
+
+    start(move || {
+        App::new()
+            .app_data(store.clone())
+            .app_data(config.clone())
+            .service(chat_ws)
+    })
+}
+
+// Collects text frames until nothing arrives for half a second.
+async fn texts<S>(framed: &mut S) -> Vec<String>
+where
+    S: Stream<Item = Result<Frame, WsProtocolError>> + Unpin,
+{
+    let mut texts = Vec::new();
+    while let Ok(Some(Ok(frame))) = timeout(Duration::from_millis(500), framed.next()).await {
+        if let Frame::Text(text) = frame {
+            texts.push(String::from_utf8_lossy(&text).into_owned());
+        }
+    }
+    texts
+}
+
+#[actix_rt::test]
+async fn test_limits_loaded_per_environment() {
+    assert_eq!(LimitsConfig::default().max_frame_size, MAX_FRAME_SIZE);
+
+    let development = ServerConfig::load(Some(false)).expect("load config");
+    assert_eq!(development.limits.max_sessions, 1000);
+
+    let production = ServerConfig::load_from(&ConfigSources {
+        file: Some("config/production".to_string()),
+        ..ConfigSources::default()
+    })
+    .expect("load production config");
+    assert_eq!(production.limits.max_sessions, 100_000);
+    assert_eq!(
+        production.limits.heartbeat_timeout(),
+        Duration::from_secs(90)
+    );
+
+    let err = ServerConfig::load_from(&ConfigSources {
+        overrides: vec![
+            ("limits.max_frame_size".to_string(), "0".to_string()),
+            (
+                "limits.heartbeat_timeout_secs".to_string(),
+                "10".to_string(),
+            ),
+        ],
+        ..ConfigSources::default()
+    })
+    .unwrap_err()
+    .to_string();
+    assert!(
+        err.contains("limits.max_frame_size must be greater than 0"),
+        "{err}"
+    );
+    assert!(
+        err.contains("heartbeat_timeout_secs must be greater"),
+        "{err}"
+    );
+}
+
+#[actix_rt::test]
+async fn test_max_rooms_and_sessions() {
+    let mut server = WsChatServer::new(LimitsConfig {
+        max_rooms_per_session: 2,
+        max_sessions: 1,
+        ..LimitsConfig::default()
+    });
+    let client = DummyActor.start().recipient();
+
+    for room in ["main", "second"] {
+        let added = server.add_client_to_room("session", room, None, client.clone(), "a".into());
+        assert!(added.is_some());
+    }
+    let third = server.add_client_to_room("session", "third", None, client.clone(), "a".into());
+    assert!(third.is_none());
+
+    let other = server.add_client_to_room("other", "main", None, client, "b".into());
+    assert!(other.is_none());
+}
+
+#[actix_rt::test]
+async fn test_create_session_respects_max_sessions() {
+    let limits = LimitsConfig {
+        max_sessions: 1,
+        ..LimitsConfig::default()
+    };
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(SessionStore::new(limits)))
+            .app_data(web::Data::new(
+                ServerConfig::load(Some(false)).expect("load config"),
+            ))
+            .service(create_session),
+    )
+    .await;
+
+    let request = || test::TestRequest::get().uri("/create-session").to_request();
+    let resp = test::call_service(&app, request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = test::call_service(&app, request()).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+async fn test_frame_and_signal_size() {
+    let srv = start_server(LimitsConfig {
+        max_frame_size: 1024,
+        max_signal_size: 256,
+        ..LimitsConfig::default()
+    });
+    let (_resp, mut framed) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+
+    let signal = format!(
+        "[SignalMessage] {{\"to\":\"x\",\"data\":\"{}\"}}",
+        "a".repeat(300)
+    );
+    framed.send(Message::Text(signal.into())).await.unwrap();
+    let received = texts(&mut framed).await;
+    assert!(
+        received
+            .iter()
+            .any(|t| t.contains("Signal message too large")),
+        "{received:?}"
+    );
+
+    framed
+        .send(Message::Text("a".repeat(2048).into()))
+        .await
+        .unwrap();
+    let closed = timeout(Duration::from_secs(5), async {
+        while let Some(Ok(Frame::Text(_) | Frame::Ping(_))) = framed.next().await {}
+    })
+    .await;
+    assert!(
+        closed.is_ok(),
+        "oversized frame should close the connection"
+    );
+}
+
+#[actix_rt::test]
+async fn test_message_rate_limit() {
+    let srv = start_server(LimitsConfig {
+        commands: MessageQuota::new(1, 2, 1024, MAX_FRAME_SIZE as u64),
+        ..LimitsConfig::default()
+    });
+    let (_resp, mut framed) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+
+    for _ in 0..5 {
+        framed
+            .send(Message::Text("[UserCommand]/name".into()))
+            .await
+            .unwrap();
+    }
+    let names = texts(&mut framed)
+        .await
+        .into_iter()
+        .filter(|t| t.starts_with("[SystemName]"))
+        .count();
+    assert_eq!(names, 2);
+}
+
+#[actix_rt::test]
+async fn test_heartbeat_interval_and_timeout() {
+    let srv = start_server(LimitsConfig {
+        heartbeat_interval_secs: 1,
+        heartbeat_timeout_secs: 2,
+        ..LimitsConfig::default()
+    });
+    let (_resp, mut framed) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+
+    // Pings are never answered, so the server gives up after the timeout.
+    let mut pings = 0;
+    let closed = timeout(Duration::from_secs(6), async {
+        while let Some(Ok(frame)) = framed.next().await {
+            match frame {
+                Frame::Ping(_) => pings += 1,
+                Frame::Close(_) => break,
+                _ => {}
+            }
+        }
+    })
+    .await;
+    assert!(closed.is_ok(), "silent client should be disconnected");
+    assert!(pings >= 1);
+}
+
+#[actix_rt::test]
+async fn test_private_session_expiration() {
+    let store = SessionStore::new(LimitsConfig {
+        session_expiration_secs: 1,
+        ..LimitsConfig::default()
+    });
+    let code = SessionStore::generate_random_code(SESSION_CODE_LENGTH);
+    let uuid = store
+        .get_or_create_session_uuid(&code, false, true)
+        .expect("Failed to create private session");
+
+    store.remove_client(&Uuid::parse_str(&uuid).unwrap());
+    assert!(store.is_active_private_code(&code));
+
+    sleep(Duration::from_millis(1500)).await;
+    assert!(!store.is_active_private_code(&code));
+}
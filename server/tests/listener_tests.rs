@@ -0,0 +1,259 @@
+use actix_governor::{Governor, GovernorConfigBuilder};
+use actix_web::{
+    App, HttpRequest, HttpResponse, HttpServer, get,
+    http::{StatusCode, header},
+    test, web,
+};
+use config::{Config, File, FileFormat};
+use server::{
+    ClientIpKeyExtractor, ForwardedHeader, ListenerConfig, MdnsService, ServerCertificates,
+    ServerConfig, bind_listeners, bind_unix_socket, health, on_connect, peer_ip, redirect_to_https,
+};
+use std::{
+    fs,
+    io::{Read, Write},
+    os::unix::{fs::PermissionsExt, net::UnixStream},
+    path::{Path, PathBuf},
+};
+
+mod tls_support;
+
+use tls_support::{ca, https_client, server_cert, temp_dir, write_pair};
+
+#[get("/peer")]
+async fn peer(req: HttpRequest) -> HttpResponse {
+    HttpResponse::Ok().body(format!("{:?}", peer_ip(&req)))
+}
+
+fn config_with(listeners: Vec<ListenerConfig>) -> ServerConfig {
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.listeners = listeners;
+    config
+}
+
+fn tcp(address: &str) -> ListenerConfig {
+    ListenerConfig::Tcp {
+        address: address.to_string(),
+    }
+}
+
+fn unix(path: &Path, mode: u32) -> ListenerConfig {
+    ListenerConfig::Unix {
+        path: path.to_string_lossy().into_owned(),
+        mode,
+    }
+}
+
+// Starts the configured listeners, returning the bound TCP addresses.
+fn start(
+    config: &ServerConfig,
+    certificates: Option<&ServerCertificates>,
+) -> Vec<std::net::SocketAddr> {
+    let governor = GovernorConfigBuilder::default()
+        .key_extractor(ClientIpKeyExtractor {
+            trusted_proxies: Vec::new(),
+            forwarded_header: ForwardedHeader::default(),
+        })
+        .finish()
+        .unwrap();
+    let server = HttpServer::new(move || {
+        App::new()
+            .wrap(Governor::new(&governor))
+            .service(health)
+            .service(peer)
+    })
+    .workers(1)
+    .on_connect(on_connect);
+
+    let server = bind_listeners(server, config, certificates).unwrap();
+    let addrs = server.addrs();
+    actix_rt::spawn(server.run());
+    addrs
+}
+
+// Sends a plain HTTP/1.1 request over a Unix socket.
+async fn unix_get(path: PathBuf, uri: &'static str) -> String {
+    web::block(move || {
+        let mut stream = UnixStream::connect(path).unwrap();
+        write!(
+            stream,
+            "GET {uri} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    })
+    .await
+    .unwrap()
+}
+
+#[actix_rt::test]
+async fn test_plain_tcp_listener() {
+    let config = config_with(vec![tcp("127.0.0.1:0")]);
+    assert!(!config.uses_tls());
+    let addrs = start(&config, None);
+
+    let mut resp = awc::Client::new()
+        .get(format!("http://{}/peer", addrs[0]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.body().await.unwrap(), "Some(127.0.0.1)");
+}
+
+#[actix_rt::test]
+async fn test_unix_socket_listener() {
+    let dir = temp_dir("uds");
+    let path = dir.join("server.sock");
+    start(&config_with(vec![unix(&path, 0o600)]), None);
+
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    let response = unix_get(path.clone(), "/health").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+
+    // Socket peers count as loopback so rate limiting and admin checks work.
+    let response = unix_get(path, "/peer").await;
+    assert!(response.ends_with("Some(127.0.0.1)"), "{response}");
+}
+
+#[actix_rt::test]
+async fn test_unix_socket_replaces_stale_socket_only() {
+    let dir = temp_dir("uds-stale");
+    let path = dir.join("stale.sock");
+    drop(bind_unix_socket(path.to_str().unwrap(), 0o660).unwrap());
+    assert!(bind_unix_socket(path.to_str().unwrap(), 0o660).is_ok());
+
+    let file = dir.join("not-a-socket");
+    fs::write(&file, "data").unwrap();
+    assert!(bind_unix_socket(file.to_str().unwrap(), 0o660).is_err());
+    assert_eq!(fs::read_to_string(&file).unwrap(), "data");
+}
+
+#[actix_rt::test]
+async fn test_multiple_listeners() {
+    let ca = ca("PastePoint Test CA");
+    let dir = temp_dir("multi-listeners");
+    let (cert, key) = write_pair(&dir, "server", &server_cert(&ca));
+    let socket = dir.join("multi.sock");
+
+    let mut config = config_with(vec![
+        ListenerConfig::Tls {
+            address: "127.0.0.1:0".to_string(),
+        },
+        tcp("127.0.0.1:0"),
+        unix(&socket, 0o660),
+    ]);
+    config.key_file_path = key.to_string_lossy().into_owned();
+    config.cert_file_path = cert.to_string_lossy().into_owned();
+
+    // TLS listeners need certificates.
+    let server = HttpServer::new(|| App::new().service(health));
+    assert!(bind_listeners(server, &config, None).is_err());
+
+    let certificates = ServerCertificates::from_config(&config).unwrap();
+    let addrs = start(&config, Some(&certificates));
+    assert_eq!(addrs.len(), 3);
+
+    let resp = https_client(&ca, None)
+        .get(format!("https://localhost:{}/health", addrs[0].port()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = awc::Client::new()
+        .get(format!("http://{}/health", addrs[1]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert!(
+        unix_get(socket, "/health")
+            .await
+            .starts_with("HTTP/1.1 200")
+    );
+}
+
+#[actix_rt::test]
+async fn test_https_redirect() {
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.https_redirect.enabled = true;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(config.clone()))
+            .default_service(web::to(redirect_to_https)),
+    )
+    .await;
+
+    // Defaults to the port of the TLS listener on `bind_address`.
+    let req = test::TestRequest::get()
+        .uri("/ws?room=main")
+        .insert_header((header::HOST, "pastepoint.local:80"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(
+        resp.headers().get(header::LOCATION).unwrap(),
+        "https://pastepoint.local:9000/ws?room=main"
+    );
+
+    let req = test::TestRequest::get().uri("/").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    config.https_redirect.https_port = Some(443);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(config))
+            .default_service(web::to(redirect_to_https)),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/create-session")
+        .insert_header((header::HOST, "[::1]:8080"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(
+        resp.headers().get(header::LOCATION).unwrap(),
+        "https://[::1]/create-session"
+    );
+}
+
+#[actix_rt::test]
+async fn test_listener_config_and_mdns_advertisement() {
+    let toml = r#"
+        listeners = [
+            { kind = "unix", path = "/run/pastepoint.sock" },
+            { kind = "tcp", address = "0.0.0.0:8080" },
+        ]
+    "#;
+    let listeners: Vec<ListenerConfig> = Config::builder()
+        .add_source(File::from_str(toml, FileFormat::Toml))
+        .build()
+        .unwrap()
+        .get("listeners")
+        .unwrap();
+    assert_eq!(
+        listeners[0],
+        ListenerConfig::Unix {
+            path: "/run/pastepoint.sock".to_string(),
+            mode: 0o660,
+        }
+    );
+
+    let mut config = config_with(listeners);
+    config.mdns.port = None;
+    let service = MdnsService::from_config(&config).unwrap();
+    assert_eq!(service.port, 8080);
+    assert!(service.txt.contains(&"tls=0".to_string()));
+
+    // Without explicit listeners, `bind_address` is served over TLS.
+    let config = config_with(Vec::new());
+    assert!(config.uses_tls());
+    assert_eq!(config.https_port(), Some(9000));
+}
@@ -0,0 +1,154 @@
+use actix_rt::net::UdpSocket;
+use server::{MDNS_SERVICE_TYPE, MdnsResponder, MdnsService, ServerConfig};
+use simple_dns::{
+    CLASS, Name, Packet, QTYPE, Question, TYPE,
+    rdata::{RData, TXT},
+};
+use std::net::{IpAddr, Ipv4Addr};
+use tokio::time::{Duration, timeout};
+
+fn test_service() -> MdnsService {
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.mdns.instance_name = "Office PastePoint".to_string();
+    config.mdns.hostname = Some("office-server".to_string());
+    config.mdns.addresses = vec![IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10))];
+    MdnsService::from_config(&config).expect("build mDNS service")
+}
+
+fn query(name: &str, qtype: TYPE) -> Vec<u8> {
+    let mut packet = Packet::new_query(42);
+    packet.questions.push(Question::new(
+        Name::new_unchecked(name),
+        QTYPE::TYPE(qtype),
+        CLASS::IN.into(),
+        false,
+    ));
+    packet.build_bytes_vec().unwrap()
+}
+
+fn txt_entries(txt: &TXT) -> Vec<String> {
+    let mut entries: Vec<String> = txt
+        .attributes()
+        .into_iter()
+        .map(|(k, v)| format!("{k}={}", v.unwrap_or_default()))
+        .collect();
+    entries.sort();
+    entries
+}
+
+#[test]
+fn test_service_from_config() {
+    let service = test_service();
+    assert_eq!(
+        service.instance,
+        format!("Office PastePoint.{MDNS_SERVICE_TYPE}")
+    );
+    assert_eq!(service.host, "office-server.local");
+    assert_eq!(service.port, 9000);
+    assert!(service.txt.contains(&"tls=1".to_string()));
+    assert!(service.txt.contains(&"path=/ws".to_string()));
+}
+
+#[test]
+fn test_ptr_query_returns_service_records() {
+    let service = test_service();
+    let reply = service
+        .handle_query(&query(MDNS_SERVICE_TYPE, TYPE::PTR), true)
+        .expect("PTR query should be answered");
+    let packet = Packet::parse(&reply).unwrap();
+
+    assert_eq!(packet.id(), 42);
+    assert_eq!(packet.questions.len(), 1);
+    assert!(packet.answers.iter().any(|r| matches!(
+        &r.rdata,
+        RData::PTR(ptr) if ptr.0.to_string() == service.instance
+    )));
+
+    let srv = packet
+        .additional_records
+        .iter()
+        .find_map(|r| match &r.rdata {
+            RData::SRV(srv) => Some(srv),
+            _ => None,
+        })
+        .expect("SRV record in additional section");
+    assert_eq!(srv.port, 9000);
+    assert_eq!(srv.target.to_string(), "office-server.local");
+
+    let txt = packet
+        .additional_records
+        .iter()
+        .find_map(|r| match &r.rdata {
+            RData::TXT(txt) => Some(txt),
+            _ => None,
+        })
+        .expect("TXT record in additional section");
+    assert_eq!(
+        txt_entries(txt),
+        vec![
+            "path=/ws".to_string(),
+            "tls=1".to_string(),
+            format!("version={}", env!("CARGO_PKG_VERSION")),
+        ]
+    );
+
+    assert!(packet.additional_records.iter().any(|r| matches!(
+        &r.rdata,
+        RData::A(a) if Ipv4Addr::from(a.address) == Ipv4Addr::new(192, 168, 1, 10)
+    )));
+}
+
+#[test]
+fn test_host_query_returns_address() {
+    let service = test_service();
+    let reply = service
+        .handle_query(&query("office-server.local", TYPE::A), false)
+        .expect("A query should be answered");
+    let packet = Packet::parse(&reply).unwrap();
+
+    assert_eq!(packet.id(), 0);
+    assert!(packet.questions.is_empty());
+    assert_eq!(packet.answers.len(), 1);
+}
+
+#[test]
+fn test_unrelated_query_is_ignored() {
+    let service = test_service();
+    assert!(
+        service
+            .handle_query(&query("_http._tcp.local", TYPE::PTR), false)
+            .is_none()
+    );
+    assert!(
+        service
+            .handle_query(&query(MDNS_SERVICE_TYPE, TYPE::AAAA), false)
+            .is_none()
+    );
+}
+
+#[actix_rt::test]
+async fn test_responder_answers_unicast_query() {
+    let responder =
+        MdnsResponder::bind(test_service(), 0, Ipv4Addr::LOCALHOST).expect("bind responder");
+    let port = responder.local_addr().unwrap().port();
+    let _handle = responder.spawn();
+
+    let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    client
+        .send_to(
+            &query(MDNS_SERVICE_TYPE, TYPE::PTR),
+            (Ipv4Addr::LOCALHOST, port),
+        )
+        .await
+        .unwrap();
+
+    let mut buf = vec![0u8; 9000];
+    let (len, _) = timeout(Duration::from_secs(5), client.recv_from(&mut buf))
+        .await
+        .expect("Timed out waiting for mDNS reply")
+        .unwrap();
+
+    let packet = Packet::parse(&buf[..len]).unwrap();
+    assert_eq!(packet.id(), 42);
+    assert!(!packet.answers.is_empty());
+}
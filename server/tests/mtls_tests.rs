@@ -0,0 +1,153 @@
+#![cfg(feature = "openssl")]
+
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, get, http::StatusCode, web};
+use server::{
+    ClientAuthConfig, ClientCert, ClientCertPolicy, ServerConfig, SessionStore, admin_metrics,
+    configure_client_auth, create_session, extract_client_cert,
+};
+use std::{fs, net::SocketAddr};
+
+mod tls_support;
+
+use tls_support::{TestCert, ca, client_cert, https_client, server_cert, temp_dir, write_pair};
+
+#[get("/whoami")]
+async fn whoami(req: HttpRequest) -> HttpResponse {
+    match req.conn_data::<ClientCert>() {
+        Some(cert) => HttpResponse::Ok().body(cert.subject.clone()),
+        None => HttpResponse::Ok().body("anonymous"),
+    }
+}
+
+// Starts an HTTPS server requesting client certificates signed by `ca`.
+fn start_server(name: &str, ca: &TestCert, client_auth: ClientAuthConfig) -> SocketAddr {
+    let dir = temp_dir(name);
+    let (cert_path, key_path) = write_pair(&dir, "server", &server_cert(ca));
+    let ca_path = dir.join("client-ca.pem");
+    fs::write(&ca_path, ca.cert.to_pem().unwrap()).unwrap();
+
+    let client_auth = ClientAuthConfig {
+        ca_file: Some(ca_path.to_string_lossy().into_owned()),
+        ..client_auth
+    };
+    let mut builder = tls_support::acceptor(&cert_path, &key_path);
+    configure_client_auth(&mut builder, &client_auth).unwrap();
+
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.client_auth = client_auth;
+    let config = web::Data::new(config);
+    let store = web::Data::new(SessionStore::default());
+
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(config.clone())
+            .app_data(store.clone())
+            .service(whoami)
+            .service(admin_metrics)
+            .service(create_session)
+    })
+    .workers(1)
+    .on_connect(extract_client_cert)
+    .bind_openssl("127.0.0.1:0", builder)
+    .unwrap();
+    let addr = server.addrs()[0];
+    actix_rt::spawn(server.run());
+    addr
+}
+
+fn enabled() -> ClientAuthConfig {
+    ClientAuthConfig {
+        enabled: true,
+        ..ClientAuthConfig::default()
+    }
+}
+
+#[actix_rt::test]
+async fn test_client_certificate_subject_exposed() {
+    let ca = ca("PastePoint Test CA");
+    let addr = start_server("mtls-subject", &ca, enabled());
+    let alice = client_cert(&ca, "alice");
+
+    let mut resp = https_client(&ca, Some(&alice))
+        .get(format!("https://localhost:{}/whoami", addr.port()))
+        .send()
+        .await
+        .unwrap();
+    let body = resp.body().await.unwrap();
+    assert_eq!(body, "O=PastePoint Tests,CN=alice");
+
+    let mut resp = https_client(&ca, None)
+        .get(format!("https://localhost:{}/whoami", addr.port()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.body().await.unwrap(), "anonymous");
+}
+
+#[actix_rt::test]
+async fn test_admin_requires_certificate() {
+    let ca = ca("PastePoint Test CA");
+    let addr = start_server("mtls-admin", &ca, enabled());
+    let url = format!("https://localhost:{}/admin/metrics", addr.port());
+
+    let resp = https_client(&ca, None).get(&url).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = https_client(&ca, Some(&client_cert(&ca, "ops")))
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Optional routes still work without a certificate.
+    let resp = https_client(&ca, None)
+        .get(format!("https://localhost:{}/create-session", addr.port()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn test_untrusted_certificate_fails_handshake() {
+    let ca = ca("PastePoint Test CA");
+    let addr = start_server("mtls-untrusted", &ca, enabled());
+    let rogue_ca = tls_support::ca("Rogue CA");
+    let mallory = client_cert(&rogue_ca, "mallory");
+
+    let result = https_client(&ca, Some(&mallory))
+        .get(format!("https://localhost:{}/whoami", addr.port()))
+        .send()
+        .await;
+    assert!(result.is_err());
+}
+
+#[actix_rt::test]
+async fn test_route_policies_configurable() {
+    let ca = ca("PastePoint Test CA");
+    let addr = start_server(
+        "mtls-policies",
+        &ca,
+        ClientAuthConfig {
+            admin: ClientCertPolicy::Off,
+            create_session: ClientCertPolicy::Required,
+            ..enabled()
+        },
+    );
+
+    let resp = https_client(&ca, None)
+        .get(format!("https://localhost:{}/create-session", addr.port()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // With the certificate policy off, the loopback check still applies.
+    let resp = https_client(&ca, None)
+        .get(format!("https://localhost:{}/admin/metrics", addr.port()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
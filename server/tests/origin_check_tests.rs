@@ -1,15 +1,15 @@
 use actix_http::header::HeaderValue;
-use server::ServerConfig;
+use server::{OriginPattern, ServerConfig};
 
 #[test]
 fn test_check_origin_allowed() {
     let mut config_https = ServerConfig::load(Some(false)).expect("load config");
-    config_https.cors_allowed_origins = "https://pastepoint.com".to_string();
+    config_https.cors_allowed_origins = vec!["https://pastepoint.com".parse().unwrap()];
     let origin_https = HeaderValue::from_str("https://pastepoint.com").unwrap();
     assert!(config_https.check_origin(&origin_https));
 
     let mut config_http = ServerConfig::load(Some(false)).expect("load config");
-    config_http.cors_allowed_origins = "http://pastepoint.com".to_string();
+    config_http.cors_allowed_origins = vec!["http://pastepoint.com".parse().unwrap()];
     let origin_http = HeaderValue::from_str("http://pastepoint.com").unwrap();
     assert!(config_http.check_origin(&origin_http));
 }
@@ -17,12 +17,12 @@ fn test_check_origin_allowed() {
 #[test]
 fn test_check_origin_allowed_www() {
     let mut config_https = ServerConfig::load(Some(false)).expect("load config");
-    config_https.cors_allowed_origins = "https://www.pastepoint.com".to_string();
+    config_https.cors_allowed_origins = vec!["https://www.pastepoint.com".parse().unwrap()];
     let origin_https = HeaderValue::from_str("https://www.pastepoint.com").unwrap();
     assert!(config_https.check_origin(&origin_https));
 
     let mut config_http = ServerConfig::load(Some(false)).expect("load config");
-    config_http.cors_allowed_origins = "http://www.pastepoint.com".to_string();
+    config_http.cors_allowed_origins = vec!["http://www.pastepoint.com".parse().unwrap()];
     let origin_http = HeaderValue::from_str("http://www.pastepoint.com").unwrap();
     assert!(config_http.check_origin(&origin_http));
 }
@@ -30,12 +30,12 @@ fn test_check_origin_allowed_www() {
 #[test]
 fn test_check_origin_subdomain() {
     let mut config_https = ServerConfig::load(Some(false)).expect("load config");
-    config_https.cors_allowed_origins = "https://pastepoint.com".to_string();
+    config_https.cors_allowed_origins = vec!["https://*.pastepoint.com".parse().unwrap()];
     let origin_https = HeaderValue::from_str("https://sub.pastepoint.com").unwrap();
     assert!(config_https.check_origin(&origin_https));
 
     let mut config_http = ServerConfig::load(Some(false)).expect("load config");
-    config_http.cors_allowed_origins = "http://pastepoint.com".to_string();
+    config_http.cors_allowed_origins = vec!["http://*.pastepoint.com".parse().unwrap()];
     let origin_http = HeaderValue::from_str("http://sub.pastepoint.com").unwrap();
     assert!(config_http.check_origin(&origin_http));
 }
@@ -43,12 +43,125 @@ fn test_check_origin_subdomain() {
 #[test]
 fn test_check_origin_spoofed() {
     let mut config_https = ServerConfig::load(Some(false)).expect("load config");
-    config_https.cors_allowed_origins = "https://pastepoint.com".to_string();
+    config_https.cors_allowed_origins = vec!["https://pastepoint.com".parse().unwrap()];
     let origin_https = HeaderValue::from_str("https://pastepoint.com.evil.com").unwrap();
     assert!(!config_https.check_origin(&origin_https));
 
     let mut config_http = ServerConfig::load(Some(false)).expect("load config");
-    config_http.cors_allowed_origins = "http://pastepoint.com".to_string();
+    config_http.cors_allowed_origins = vec!["http://pastepoint.com".parse().unwrap()];
     let origin_http = HeaderValue::from_str("http://pastepoint.com.evil.com").unwrap();
     assert!(!config_http.check_origin(&origin_http));
 }
+
+#[test]
+fn test_check_origin_multiple_origins() {
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.cors_allowed_origins = vec![
+        "https://intranet.example".parse().unwrap(),
+        "http://192.168.1.20:4200".parse().unwrap(),
+    ];
+    let allowed = |origin: &str| config.check_origin(&HeaderValue::from_str(origin).unwrap());
+
+    assert!(allowed("https://intranet.example"));
+    assert!(allowed("https://intranet.example:443"));
+    assert!(allowed("http://192.168.1.20:4200"));
+    assert!(!allowed("http://intranet.example"));
+    assert!(!allowed("https://intranet.example:8443"));
+    assert!(!allowed("http://192.168.1.20"));
+    assert!(!allowed("https://192.168.1.20:4200"));
+}
+
+#[test]
+fn test_check_origin_wildcards() {
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.cors_allowed_origins = vec![
+        "https://*.pastepoint.com".parse().unwrap(),
+        "https://lan.pastepoint.dev:*".parse().unwrap(),
+    ];
+    let allowed = |origin: &str| config.check_origin(&HeaderValue::from_str(origin).unwrap());
+
+    assert!(allowed("https://a.b.pastepoint.com"));
+    assert!(!allowed("https://pastepoint.com"));
+    assert!(!allowed("https://evilpastepoint.com"));
+    assert!(allowed("https://lan.pastepoint.dev:8443"));
+    assert!(allowed("https://lan.pastepoint.dev"));
+    assert!(!allowed("http://lan.pastepoint.dev:8443"));
+}
+
+#[test]
+fn test_check_origin_localhost_shortcut() {
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.cors_allowed_origins = vec!["localhost".parse().unwrap()];
+    let allowed = |origin: &str| config.check_origin(&HeaderValue::from_str(origin).unwrap());
+
+    assert!(allowed("http://localhost:4200"));
+    assert!(allowed("https://127.0.0.1"));
+    assert!(allowed("http://[::1]:8080"));
+    assert!(!allowed("http://localhost.evil.com"));
+    assert!(!allowed("null"));
+}
+
+#[test]
+fn test_invalid_origin_patterns() {
+    for invalid in [
+        "*",
+        "pastepoint.com",
+        "ftp://pastepoint.com",
+        "https://pastepoint.com/app",
+        "https://app.*.pastepoint.com",
+        "https://user@pastepoint.com",
+    ] {
+        assert!(
+            invalid.parse::<OriginPattern>().is_err(),
+            "{invalid} should be rejected"
+        );
+    }
+    assert_eq!(
+        "https://*.pastepoint.com"
+            .parse::<OriginPattern>()
+            .unwrap()
+            .to_string(),
+        "https://*.pastepoint.com:443"
+    );
+}
+
+#[test]
+fn test_origin_list_validated_on_load() {
+    #[derive(serde::Deserialize)]
+    struct Wrapper {
+        server: ServerConfig,
+    }
+
+    let base = r#"
+        [server]
+        bind_address = "127.0.0.1:9000"
+        key_file_path = "key.pem"
+        cert_file_path = "cert.pem"
+        auto_join = false
+        rate_limit_per_second = 10
+        rate_limit_burst_size = 20
+    "#;
+    let load = |origins: &str| {
+        config::Config::builder()
+            .add_source(config::File::from_str(
+                &format!("{base}cors_allowed_origins = {origins}\n"),
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize::<Wrapper>()
+    };
+
+    let single = load(r#""https://pastepoint.com""#).expect("single origin string");
+    assert_eq!(single.server.cors_allowed_origins.len(), 1);
+
+    let err = load(r#"["localhost", "pastepoint.com", "https://x.com/path"]"#)
+        .err()
+        .expect("invalid origins must fail")
+        .to_string();
+    assert!(
+        err.contains("pastepoint.com' must include a scheme"),
+        "{err}"
+    );
+    assert!(err.contains("must not contain a path"), "{err}");
+}
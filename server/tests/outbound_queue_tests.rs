@@ -0,0 +1,206 @@
+use actix::prelude::*;
+use server::{
+    ChatMessage, ClientMetadata, ConfigSources, LimitsConfig, OutboundKind, Outbox, OverflowPolicy,
+    Push, ServerConfig, WS_SLOW_CONSUMER_METRIC, WsChatServer, metrics,
+};
+
+struct DummyActor;
+
+impl Actor for DummyActor {
+    type Context = Context<Self>;
+}
+
+impl Handler<ChatMessage> for DummyActor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: ChatMessage, _ctx: &mut Context<Self>) {}
+}
+
+fn outbox(max_messages: usize, policy: OverflowPolicy) -> Outbox {
+    Outbox::new(&LimitsConfig {
+        max_outbound_messages: max_messages,
+        outbound_overflow: policy,
+        ..LimitsConfig::default()
+    })
+}
+
+#[test]
+fn test_only_one_message_waits_in_the_mailbox() {
+    let outbox = outbox(10, OverflowPolicy::DropOldest);
+
+    assert_eq!(outbox.push("first".into()), Push::Send("first".into()));
+    assert_eq!(outbox.push("second".into()), Push::Queued);
+    assert_eq!(outbox.push("third".into()), Push::Queued);
+    assert_eq!(outbox.len(), 2);
+
+    assert_eq!(outbox.drain(), Some(vec!["second".into(), "third".into()]));
+    assert!(outbox.is_empty());
+    // Once drained, the next message wakes the session again.
+    assert_eq!(outbox.push("fourth".into()), Push::Send("fourth".into()));
+}
+
+#[test]
+fn test_room_and_member_lists_are_coalesced() {
+    let outbox = outbox(10, OverflowPolicy::DropOldest);
+    let coalesced = metrics().counter(
+        WS_SLOW_CONSUMER_METRIC,
+        &[("kind", "rooms"), ("action", "coalesced")],
+    );
+
+    outbox.push("[SignalMessage] {}".into());
+    for message in [
+        "[SystemRooms] main",
+        "[SystemMembers] a",
+        "[SystemRooms] main, other",
+        "[SystemMembers] a, b",
+        "[SystemRooms] main, other, third",
+    ] {
+        assert_eq!(outbox.push(message.into()), Push::Queued);
+    }
+
+    assert_eq!(
+        outbox.drain(),
+        Some(vec![
+            "[SystemMembers] a, b".into(),
+            "[SystemRooms] main, other, third".into(),
+        ])
+    );
+    assert_eq!(
+        metrics().counter(
+            WS_SLOW_CONSUMER_METRIC,
+            &[("kind", "rooms"), ("action", "coalesced")],
+        ),
+        coalesced + 2
+    );
+}
+
+#[test]
+fn test_drop_oldest_sheds_stale_messages_first() {
+    let outbox = outbox(3, OverflowPolicy::DropOldest);
+
+    outbox.push("[SignalMessage] {}".into());
+    for (kind, message) in [
+        (OutboundKind::Join, "a [SystemJoin] main"),
+        (OutboundKind::Essential, "[SignalMessage] 1"),
+        (OutboundKind::Join, "b [SystemJoin] main"),
+        (OutboundKind::Essential, "[SignalMessage] 2"),
+    ] {
+        assert_eq!(outbox.push_as(kind, message.into()), Push::Queued);
+    }
+    assert_eq!(
+        outbox.drain(),
+        Some(vec![
+            "[SignalMessage] 1".into(),
+            "b [SystemJoin] main".into(),
+            "[SignalMessage] 2".into(),
+        ])
+    );
+
+    // With only messages that must be delivered left, the client is cut off.
+    outbox.push("[SignalMessage] {}".into());
+    for n in 0..3 {
+        assert_eq!(outbox.push(format!("[SignalMessage] {n}")), Push::Queued);
+    }
+    assert_eq!(outbox.push("[SignalMessage] 3".into()), Push::Overflow);
+    assert_eq!(outbox.push("[SignalMessage] 4".into()), Push::Dropped);
+    assert_eq!(outbox.drain(), None);
+}
+
+#[test]
+fn test_disconnect_policy_and_byte_limit() {
+    let outbox = outbox(3, OverflowPolicy::Disconnect);
+    outbox.push("[SignalMessage] {}".into());
+    for _ in 0..3 {
+        outbox.push_as(OutboundKind::Join, "a [SystemJoin] main".into());
+    }
+    assert_eq!(
+        outbox.push_as(OutboundKind::Join, "b [SystemJoin] main".into()),
+        Push::Overflow
+    );
+    assert_eq!(outbox.drain(), None);
+
+    let outbox = Outbox::new(&LimitsConfig {
+        max_outbound_bytes: 20,
+        ..LimitsConfig::default()
+    });
+    outbox.push("[SignalMessage] {}".into());
+    assert_eq!(
+        outbox.push_as(OutboundKind::Join, "a [SystemJoin] main".into()),
+        Push::Queued
+    );
+    assert_eq!(outbox.push("[SignalMessage] 1".into()), Push::Queued);
+    assert_eq!(outbox.drain(), Some(vec!["[SignalMessage] 1".into()]));
+}
+
+#[test]
+fn test_signal_mentioning_join_marker_is_not_dropped() {
+    let signal = r#"[SignalMessage] {"type":"chat","data":"x [SystemJoin] main"}"#;
+    assert_eq!(OutboundKind::of(signal), OutboundKind::Essential);
+    assert_eq!(
+        OutboundKind::of("a [SystemJoin] main"),
+        OutboundKind::Essential
+    );
+
+    // A queue of join notices makes room by dropping them, never the signal.
+    let outbox = outbox(2, OverflowPolicy::DropOldest);
+    outbox.push("[SignalMessage] {}".into());
+    assert_eq!(outbox.push(signal.into()), Push::Queued);
+    for name in ["a", "b"] {
+        assert_eq!(
+            outbox.push_as(OutboundKind::Join, format!("{name} [SystemJoin] main")),
+            Push::Queued
+        );
+    }
+    assert_eq!(
+        outbox.drain(),
+        Some(vec![signal.into(), "b [SystemJoin] main".into()])
+    );
+}
+
+#[actix_rt::test]
+async fn test_broadcasts_go_through_the_outbox() {
+    let mut server = WsChatServer::new(LimitsConfig::default());
+    let outbox = outbox(3, OverflowPolicy::DropOldest);
+    let member = ClientMetadata::new(DummyActor.start().recipient(), "a".into(), outbox.clone());
+    assert!(
+        server
+            .add_member("session", "main", Some(1), member)
+            .is_some()
+    );
+
+    // The session never drains, so everything after the first message waits.
+    for _ in 0..5 {
+        server.send_snapshot("session", "main", 1);
+    }
+    assert_eq!(outbox.len(), 2);
+
+    // Signals push the stale member list out.
+    server.relay_message_to_user("session", "a", ChatMessage("[SignalMessage] 1".into()), "b");
+    server.relay_message_to_user("session", "a", ChatMessage("[SignalMessage] 2".into()), "b");
+    let drained = outbox.drain().unwrap();
+    assert_eq!(drained.len(), 3);
+    assert!(drained[0].starts_with("[SystemRooms]"));
+    assert_eq!(drained[1..], ["[SignalMessage] 1", "[SignalMessage] 2"]);
+}
+
+#[test]
+fn test_outbound_limits_validation() {
+    let err = ServerConfig::load_from(&ConfigSources {
+        overrides: vec![
+            ("limits.max_outbound_messages".to_string(), "0".to_string()),
+            ("limits.max_outbound_bytes".to_string(), "1024".to_string()),
+            (
+                "limits.outbound_overflow".to_string(),
+                "disconnect".to_string(),
+            ),
+        ],
+        ..ConfigSources::default()
+    })
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("limits.max_outbound_messages must be greater than 0"));
+    assert!(err.contains("limits.max_outbound_bytes must be at least limits.max_signal_size"));
+
+    let config = ServerConfig::load(Some(false)).unwrap();
+    assert_eq!(config.limits.outbound_overflow, OverflowPolicy::DropOldest);
+}
@@ -0,0 +1,290 @@
+use actix::prelude::*;
+use actix_test::{TestServer, start};
+use actix_web::{App, web};
+use awc::{
+    Client,
+    error::WsProtocolError,
+    ws::{Frame, Message},
+};
+use futures_util::{Sink, SinkExt, Stream, StreamExt, future::join};
+use serde_json::{Value, json};
+use server::{
+    Avatar, ChatMessage, ClientMetadata, ConfigSources, LimitsConfig, Outbox, Presence,
+    ServerConfig, SessionStore, WS_PREFIX_SYSTEM_ERROR, WS_PREFIX_SYSTEM_MEMBERS,
+    WS_PREFIX_SYSTEM_MEMBERS_DELTA, WsChatServer, chat_ws,
+};
+use std::time::Duration;
+use tokio::time::timeout;
+
+struct DummyActor;
+
+impl Actor for DummyActor {
+    type Context = Context<Self>;
+}
+
+impl Handler<ChatMessage> for DummyActor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: ChatMessage, _ctx: &mut Context<Self>) {}
+}
+
+// Avatar of an anonymous member, which is derived from their name.
+fn avatar(name: &str) -> Value {
+    serde_json::to_value(Avatar::new(name, name)).unwrap()
+}
+
+fn start_server(limits: LimitsConfig) -> TestServer {
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.limits = limits;
+    let config = web::Data::new(config);
+    let store = web::Data::new(SessionStore::new(limits));
+
+    start(move || {
+        App::new()
+            .app_data(store.clone())
+            .app_data(config.clone())
+            .service(chat_ws)
+    })
+}
+
+// Collects text frames until nothing arrives for `quiet`.
+async fn texts<S>(framed: &mut S, quiet: Duration) -> Vec<String>
+where
+    S: Stream<Item = Result<Frame, WsProtocolError>> + Unpin,
+{
+    let mut texts = Vec::new();
+    while let Ok(Some(Ok(frame))) = timeout(quiet, framed.next()).await {
+        if let Frame::Text(text) = frame {
+            texts.push(String::from_utf8_lossy(&text).into_owned());
+        }
+    }
+    texts
+}
+
+// Reads frames for `duration`, answering pings, and returns the texts.
+async fn answer_pings<S>(framed: &mut S, duration: Duration) -> Vec<String>
+where
+    S: Stream<Item = Result<Frame, WsProtocolError>>
+        + Sink<Message, Error = WsProtocolError>
+        + Unpin,
+{
+    let mut texts = Vec::new();
+    let _ = timeout(duration, async {
+        while let Some(Ok(frame)) = framed.next().await {
+            match frame {
+                Frame::Ping(payload) => framed.send(Message::Pong(payload)).await.unwrap(),
+                Frame::Text(text) => texts.push(String::from_utf8_lossy(&text).into_owned()),
+                _ => {}
+            }
+        }
+    })
+    .await;
+    texts
+}
+
+// Name -> presence of the joined and updated members of each member delta
+// among `texts`.
+fn presence_updates(texts: &[String]) -> Vec<Value> {
+    texts
+        .iter()
+        .filter_map(|t| t.strip_prefix(WS_PREFIX_SYSTEM_MEMBERS_DELTA))
+        .map(|body| {
+            let delta: Value = serde_json::from_str(body).unwrap();
+            let members = [&delta["joined"], &delta["updated"]]
+                .into_iter()
+                .flat_map(|list| list.as_array().unwrap())
+                .map(|m| {
+                    (
+                        m["name"].as_str().unwrap().to_string(),
+                        m["presence"].clone(),
+                    )
+                });
+            Value::Object(members.collect())
+        })
+        .collect()
+}
+
+#[actix_rt::test]
+async fn test_presence_changes_are_published() {
+    let mut server = WsChatServer::new(LimitsConfig::default());
+    let outbox = Outbox::new(&LimitsConfig::default());
+    outbox.push(String::new());
+    let alice = ClientMetadata::new(
+        DummyActor.start().recipient(),
+        "Alice".into(),
+        outbox.clone(),
+    );
+    let bob = ClientMetadata::new(DummyActor.start().recipient(), "Bob".into(), outbox.clone());
+    server.add_member("session", "main", Some(1), alice);
+    server.add_member("session", "main", Some(2), bob);
+    server.publish_updates();
+    outbox.drain();
+    outbox.push(String::new());
+
+    assert!(server.set_presence("session", "main", 2, Presence::Away));
+    server.publish_updates();
+    let published = outbox.drain().unwrap();
+    let delta = published[0]
+        .strip_prefix(&format!("{WS_PREFIX_SYSTEM_MEMBERS_DELTA} "))
+        .unwrap();
+    assert_eq!(
+        serde_json::from_str::<Value>(delta).unwrap(),
+        json!({
+            "room": "main",
+            "version": 2,
+            "joined": [],
+            "left": [],
+            "updated": [{
+                "name": "Bob",
+                "presence": "away",
+                "device": {"type": "unknown"},
+                "avatar": avatar("Bob")
+            }]
+        })
+    );
+    outbox.push(String::new());
+
+    // Setting the same presence again is not a change.
+    assert!(server.set_presence("session", "main", 2, Presence::Away));
+    server.publish_updates();
+    assert_eq!(outbox.drain(), Some(Vec::new()));
+    assert!(!server.set_presence("session", "main", 3, Presence::Idle));
+
+    let snapshot = &server.snapshot("session", Some("main"))[1];
+    let body = snapshot
+        .strip_prefix(&format!("{WS_PREFIX_SYSTEM_MEMBERS} "))
+        .unwrap();
+    let members = &serde_json::from_str::<Value>(body).unwrap()["members"];
+    assert_eq!(members[0]["presence"], "active");
+    assert_eq!(members[1]["presence"], "away");
+}
+
+#[actix_rt::test]
+async fn test_status_command_sets_presence() {
+    let srv = start_server(LimitsConfig::default());
+    let (_resp, mut first) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+    let (_resp, mut second) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+    let quiet = Duration::from_millis(500);
+
+    for framed in [&mut first, &mut second] {
+        framed
+            .send(Message::Text("[UserCommand]/join lobby".into()))
+            .await
+            .unwrap();
+    }
+    second
+        .send(Message::Text("[UserCommand]/name".into()))
+        .await
+        .unwrap();
+    let second_name = texts(&mut second, quiet)
+        .await
+        .into_iter()
+        .find_map(|t| t.strip_prefix("[SystemName] ").map(str::to_string))
+        .unwrap();
+    texts(&mut first, quiet).await;
+
+    second
+        .send(Message::Text("[UserCommand]/status away".into()))
+        .await
+        .unwrap();
+    assert_eq!(
+        presence_updates(&texts(&mut first, quiet).await),
+        [json!({ second_name.as_str(): "away" })]
+    );
+
+    // Away sticks through activity until the user says they are back.
+    second
+        .send(Message::Text("[UserCommand]/list".into()))
+        .await
+        .unwrap();
+    assert!(presence_updates(&texts(&mut first, quiet).await).is_empty());
+    second
+        .send(Message::Text("[UserCommand]/status active".into()))
+        .await
+        .unwrap();
+    assert_eq!(
+        presence_updates(&texts(&mut first, quiet).await),
+        [json!({ second_name.as_str(): "active" })]
+    );
+
+    second
+        .send(Message::Text("[UserCommand]/status asleep".into()))
+        .await
+        .unwrap();
+    let received = texts(&mut second, quiet).await;
+    assert!(
+        received
+            .iter()
+            .any(|t| t.starts_with(WS_PREFIX_SYSTEM_ERROR) && t.contains("Status must be one of"))
+    );
+}
+
+#[actix_rt::test]
+async fn test_presence_is_inferred_from_activity_and_heartbeats() {
+    let srv = start_server(LimitsConfig {
+        heartbeat_interval_secs: 1,
+        heartbeat_timeout_secs: 10,
+        idle_threshold_secs: 1,
+        ..LimitsConfig::default()
+    });
+    let (_resp, mut first) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+    let (_resp, mut second) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+
+    for framed in [&mut first, &mut second] {
+        framed
+            .send(Message::Text("[UserCommand]/join lobby".into()))
+            .await
+            .unwrap();
+    }
+    // Both clients answer pings but the users do nothing.
+    let (received, _) = join(
+        answer_pings(&mut first, Duration::from_millis(2500)),
+        answer_pings(&mut second, Duration::from_millis(2500)),
+    )
+    .await;
+    let updates = presence_updates(&received);
+    assert!(
+        updates
+            .iter()
+            .any(|p| p.as_object().unwrap().values().all(|v| v == "idle")),
+        "{updates:?}"
+    );
+
+    // The second client stops answering, as a sleeping laptop would.
+    let received = answer_pings(&mut first, Duration::from_millis(3500)).await;
+    let updates = presence_updates(&received);
+    assert!(
+        updates
+            .iter()
+            .any(|p| p.as_object().unwrap().values().any(|v| v == "away")),
+        "{updates:?}"
+    );
+
+    // Any message from the user makes them active again.
+    second
+        .send(Message::Text("[UserCommand]/list".into()))
+        .await
+        .unwrap();
+    let received = answer_pings(&mut first, Duration::from_millis(500)).await;
+    let updates = presence_updates(&received);
+    assert!(
+        updates
+            .iter()
+            .any(|p| p.as_object().unwrap().values().any(|v| v == "active")),
+        "{updates:?}"
+    );
+}
+
+#[test]
+fn test_idle_threshold_validation() {
+    let err = ServerConfig::load_from(&ConfigSources {
+        overrides: vec![("limits.idle_threshold_secs".to_string(), "0".to_string())],
+        ..ConfigSources::default()
+    })
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("limits.idle_threshold_secs must be greater than 0"));
+
+    let config = ServerConfig::load(Some(false)).unwrap();
+    assert_eq!(config.limits.idle_threshold(), server::IDLE_THRESHOLD);
+}
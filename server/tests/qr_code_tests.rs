@@ -0,0 +1,150 @@
+use actix_web::{App, http::StatusCode, test, web};
+use server::{
+    CONTENT_TYPE_PNG, CONTENT_TYPE_SVG, QrOptions, SESSION_CODE_LENGTH, ServerConfig, SessionStore,
+    build_join_url, session_qr_png, session_qr_svg,
+};
+
+fn create_private_session(store: &SessionStore) -> String {
+    let code = SessionStore::generate_random_code(SESSION_CODE_LENGTH);
+    store
+        .get_or_create_session_uuid(&code, false, true)
+        .expect("Failed to create private session");
+    code
+}
+
+#[actix_rt::test]
+async fn test_build_join_url() {
+    assert_eq!(
+        build_join_url("https://pastepoint.com", "ABCDEFGHJK").unwrap(),
+        "https://pastepoint.com/private/ABCDEFGHJK"
+    );
+    assert_eq!(
+        build_join_url("https://192.168.1.10:4000/", "ABCDEFGHJK").unwrap(),
+        "https://192.168.1.10:4000/private/ABCDEFGHJK"
+    );
+    assert!(build_join_url("not a url", "ABCDEFGHJK").is_err());
+}
+
+#[actix_rt::test]
+async fn test_qr_options_validation() {
+    let options = QrOptions {
+        size: Some(10),
+        ec: None,
+    };
+    assert!(options.size().is_err());
+
+    let options = QrOptions {
+        size: None,
+        ec: Some("x".to_string()),
+    };
+    assert!(options.ec_level().is_err());
+
+    let options = QrOptions {
+        size: Some(512),
+        ec: Some("h".to_string()),
+    };
+    assert_eq!(options.size().unwrap(), 512);
+    assert!(options.ec_level().is_ok());
+}
+
+#[actix_rt::test]
+async fn test_session_qr_svg() {
+    let session_manager = web::Data::new(SessionStore::default());
+    let mut config_value = ServerConfig::load(Some(false)).expect("load config");
+    config_value.public_base_url = Some("https://pastepoint.com".to_string());
+    let config = web::Data::new(config_value);
+    let code = create_private_session(&session_manager);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(session_manager.clone())
+            .app_data(config.clone())
+            .service(session_qr_svg),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/session/{code}/qr.svg?size=128&ec=H"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get("Content-Type").unwrap(),
+        CONTENT_TYPE_SVG
+    );
+
+    let body = test::read_body(resp).await;
+    let svg = std::str::from_utf8(&body).unwrap();
+    assert!(svg.contains("<svg"));
+}
+
+#[actix_rt::test]
+async fn test_session_qr_png() {
+    let session_manager = web::Data::new(SessionStore::default());
+    let config = web::Data::new(ServerConfig::load(Some(false)).expect("load config"));
+    let code = create_private_session(&session_manager);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(session_manager.clone())
+            .app_data(config.clone())
+            .service(session_qr_png),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/session/{code}/qr.png"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get("Content-Type").unwrap(),
+        CONTENT_TYPE_PNG
+    );
+
+    let body = test::read_body(resp).await;
+    assert!(body.starts_with(b"\x89PNG"));
+}
+
+#[actix_rt::test]
+async fn test_session_qr_rejects_bad_requests() {
+    let session_manager = web::Data::new(SessionStore::default());
+    let config = web::Data::new(ServerConfig::load(Some(false)).expect("load config"));
+    let code = create_private_session(&session_manager);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(session_manager.clone())
+            .app_data(config.clone())
+            .service(session_qr_svg),
+    )
+    .await;
+
+    // Unknown but well-formed code
+    let req = test::TestRequest::get()
+        .uri("/session/ABCDEFGHJK/qr.svg")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // Malformed code
+    let req = test::TestRequest::get()
+        .uri("/session/bad-code!/qr.svg")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // Size out of range
+    let req = test::TestRequest::get()
+        .uri(&format!("/session/{code}/qr.svg?size=100000"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // Unknown error-correction level
+    let req = test::TestRequest::get()
+        .uri(&format!("/session/{code}/qr.svg?ec=Z"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
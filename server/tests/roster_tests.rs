@@ -0,0 +1,265 @@
+use actix::prelude::*;
+use awc::{
+    Client,
+    error::WsProtocolError,
+    ws::{Frame, Message},
+};
+use futures_util::{SinkExt, Stream, StreamExt};
+use serde_json::{Value, json};
+use server::{
+    Avatar, ChatMessage, ClientMetadata, LimitsConfig, Outbox, WS_PREFIX_SYSTEM_MEMBERS,
+    WS_PREFIX_SYSTEM_MEMBERS_DELTA, WS_PREFIX_SYSTEM_ROOMS, WS_PREFIX_SYSTEM_ROOMS_DELTA,
+    WsChatServer,
+};
+use std::time::Duration;
+use tokio::time::timeout;
+
+mod common;
+use common::init_test_server;
+
+struct DummyActor;
+
+impl Actor for DummyActor {
+    type Context = Context<Self>;
+}
+
+impl Handler<ChatMessage> for DummyActor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: ChatMessage, _ctx: &mut Context<Self>) {}
+}
+
+// Avatar of an anonymous member, which is derived from their name.
+fn avatar(name: &str) -> Value {
+    serde_json::to_value(Avatar::new(name, name)).unwrap()
+}
+
+fn member(name: &str) -> (ClientMetadata, Outbox) {
+    let outbox = Outbox::new(&LimitsConfig::default());
+    // Take the wake-up slot so every later message stays in the outbox.
+    outbox.push(String::new());
+    let client = ClientMetadata::new(DummyActor.start().recipient(), name.into(), outbox.clone());
+    (client, outbox)
+}
+
+// Drains everything queued for a member, keeping later messages queued too.
+fn pending(outbox: &Outbox) -> Vec<String> {
+    let messages = outbox.drain().unwrap();
+    outbox.push(String::new());
+    messages
+}
+
+// Splits `[Prefix] {json}` into the prefix and the parsed body.
+fn parse(message: &str) -> (&str, Value) {
+    let (prefix, body) = message.split_once(' ').expect("prefixed message");
+    (prefix, serde_json::from_str(body).expect("json body"))
+}
+
+// Collects text frames until nothing arrives for half a second.
+async fn texts<S>(framed: &mut S) -> Vec<String>
+where
+    S: Stream<Item = Result<Frame, WsProtocolError>> + Unpin,
+{
+    let mut texts = Vec::new();
+    while let Ok(Some(Ok(frame))) = timeout(Duration::from_millis(500), framed.next()).await {
+        if let Frame::Text(text) = frame {
+            texts.push(String::from_utf8_lossy(&text).into_owned());
+        }
+    }
+    texts
+}
+
+#[actix_rt::test]
+async fn test_changes_are_published_as_versioned_deltas() {
+    let mut server = WsChatServer::new(LimitsConfig::default());
+    let (alice, alice_outbox) = member("Alice");
+    let (bob, bob_outbox) = member("Bob");
+    server.add_member("session", "main", Some(1), alice);
+    server.publish_updates();
+
+    let published = pending(&alice_outbox);
+    assert_eq!(published.len(), 2);
+    assert_eq!(
+        parse(&published[0]),
+        (
+            WS_PREFIX_SYSTEM_ROOMS_DELTA,
+            json!({"version": 1, "added": ["main"], "removed": []})
+        )
+    );
+    assert_eq!(
+        parse(&published[1]),
+        (
+            WS_PREFIX_SYSTEM_MEMBERS_DELTA,
+            json!({
+                "room": "main",
+                "version": 1,
+                "joined": [{
+                    "name": "Alice",
+                    "presence": "active",
+                    "device": {"type": "unknown"},
+                    "avatar": avatar("Alice")
+                }],
+                "left": [],
+                "updated": []
+            })
+        )
+    );
+
+    // Bob joins and leaves within one window, Carol stays: one delta only.
+    let (carol, _) = member("Carol");
+    server.add_member("session", "main", Some(2), bob);
+    server.add_member("session", "main", Some(3), carol);
+    let room = server.rooms.get_mut("session").unwrap();
+    room.get_mut("main").unwrap().remove(&2);
+    server.publish_updates();
+
+    assert_eq!(
+        pending(&alice_outbox),
+        [format!(
+            "{WS_PREFIX_SYSTEM_MEMBERS_DELTA} {}",
+            json!({
+                "room": "main",
+                "version": 2,
+                "joined": [{
+                    "name": "Carol",
+                    "presence": "active",
+                    "device": {"type": "unknown"},
+                    "avatar": avatar("Carol")
+                }],
+                "left": [],
+                "updated": []
+            })
+        )]
+    );
+    assert!(pending(&bob_outbox).is_empty());
+
+    // Nothing changed, nothing sent.
+    server.mark_changed("session");
+    server.publish_updates();
+    assert!(pending(&alice_outbox).is_empty());
+}
+
+#[actix_rt::test]
+async fn test_room_deltas_reach_the_whole_session() {
+    let mut server = WsChatServer::new(LimitsConfig::default());
+    let (alice, alice_outbox) = member("Alice");
+    let (bob, bob_outbox) = member("Bob");
+    server.add_member("session", "main", Some(1), alice);
+    server.add_member("session", "other", Some(2), bob);
+    server.publish_updates();
+    pending(&alice_outbox);
+    pending(&bob_outbox);
+
+    let (carol, _) = member("Carol");
+    server.add_member("session", "third", Some(3), carol);
+    server.publish_updates();
+
+    let delta = format!(
+        "{WS_PREFIX_SYSTEM_ROOMS_DELTA} {}",
+        json!({"version": 2, "added": ["third"], "removed": []})
+    );
+    for outbox in [&alice_outbox, &bob_outbox] {
+        assert_eq!(pending(outbox), [delta.as_str()]);
+    }
+
+    let snapshot = server.snapshot("session", Some("main"));
+    assert_eq!(
+        parse(&snapshot[0]),
+        (
+            WS_PREFIX_SYSTEM_ROOMS,
+            json!({"version": 2, "rooms": ["main", "other", "third"]})
+        )
+    );
+    assert_eq!(
+        parse(&snapshot[1]),
+        (
+            WS_PREFIX_SYSTEM_MEMBERS,
+            json!({
+                "room": "main",
+                "version": 1,
+                "members": [{
+                    "name": "Alice",
+                    "presence": "active",
+                    "device": {"type": "unknown"},
+                    "avatar": avatar("Alice")
+                }]
+            })
+        )
+    );
+}
+
+#[test]
+fn test_dropped_deltas_trigger_a_resync() {
+    let outbox = Outbox::new(&LimitsConfig {
+        max_outbound_messages: 1,
+        ..LimitsConfig::default()
+    });
+    outbox.push(String::new());
+    outbox.push(format!("{WS_PREFIX_SYSTEM_MEMBERS_DELTA} {{}}"));
+    outbox.push("[SignalMessage] {}".into());
+
+    assert!(outbox.take_resync());
+    assert!(!outbox.take_resync());
+    assert_eq!(outbox.drain().unwrap(), ["[SignalMessage] {}"]);
+}
+
+#[actix_rt::test]
+async fn test_members_see_joins_as_deltas() {
+    let srv = init_test_server(false);
+    let (_resp, mut first) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+    let (_resp, mut second) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+
+    first
+        .send(Message::Text("[UserCommand]/join lobby".into()))
+        .await
+        .unwrap();
+    let received = texts(&mut first).await;
+    let snapshot = received
+        .iter()
+        .find(|t| t.starts_with(WS_PREFIX_SYSTEM_MEMBERS))
+        .expect("members snapshot on join");
+    assert_eq!(parse(snapshot).1["version"], 0);
+
+    second
+        .send(Message::Text("[UserCommand]/join lobby".into()))
+        .await
+        .unwrap();
+    second
+        .send(Message::Text("[UserCommand]/name".into()))
+        .await
+        .unwrap();
+    let second_name = texts(&mut second)
+        .await
+        .into_iter()
+        .find_map(|t| t.strip_prefix("[SystemName] ").map(str::to_string))
+        .unwrap();
+
+    let deltas: Vec<Value> = texts(&mut first)
+        .await
+        .iter()
+        .filter(|t| t.starts_with(WS_PREFIX_SYSTEM_MEMBERS_DELTA))
+        .map(|t| parse(t).1)
+        .collect();
+    assert_eq!(deltas.len(), 1, "{deltas:?}");
+    assert_eq!(deltas[0]["version"], 2);
+    assert_eq!(deltas[0]["joined"][0]["name"], json!(second_name));
+
+    // A client that noticed a gap asks for a fresh snapshot.
+    first
+        .send(Message::Text("[UserCommand]/sync".into()))
+        .await
+        .unwrap();
+    let received = texts(&mut first).await;
+    let members = received
+        .iter()
+        .find(|t| t.starts_with(WS_PREFIX_SYSTEM_MEMBERS))
+        .map(|t| parse(t).1)
+        .expect("members snapshot");
+    assert_eq!(members["version"], 2);
+    assert_eq!(members["members"].as_array().unwrap().len(), 2);
+    assert!(
+        received
+            .iter()
+            .any(|t| t.starts_with(&format!("{WS_PREFIX_SYSTEM_ROOMS} ")))
+    );
+}
@@ -0,0 +1,238 @@
+#![cfg(feature = "rustls")]
+
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, get, web};
+use rustls::{
+    ClientConfig, ClientConnection, HandshakeKind, RootCertStore, StreamOwned,
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer, ServerName},
+    version::TLS12,
+};
+use server::{
+    ClientAuthConfig, ClientCert, RustlsCertificates, ServerCertificates, ServerConfig,
+    extract_client_cert, health,
+};
+use std::{
+    fs,
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    path::Path,
+    sync::Arc,
+};
+
+mod tls_support;
+
+use tls_support::{TestCert, ca, client_cert, server_cert, temp_dir, write_pair};
+
+type TlsStream = StreamOwned<ClientConnection, TcpStream>;
+
+#[get("/whoami")]
+async fn whoami(req: HttpRequest) -> HttpResponse {
+    match req.conn_data::<ClientCert>() {
+        Some(cert) => HttpResponse::Ok().body(cert.subject.clone()),
+        None => HttpResponse::Ok().body("anonymous"),
+    }
+}
+
+fn config_for(dir: &Path) -> ServerConfig {
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.key_file_path = dir.join("server-key.pem").to_string_lossy().into_owned();
+    config.cert_file_path = dir.join("server-cert.pem").to_string_lossy().into_owned();
+    config
+}
+
+fn start_server(certificates: &RustlsCertificates) -> SocketAddr {
+    let server = HttpServer::new(|| App::new().service(health).service(whoami))
+        .workers(1)
+        .on_connect(extract_client_cert)
+        .bind_rustls_0_23("127.0.0.1:0", certificates.server_config().unwrap())
+        .unwrap();
+    let addr = server.addrs()[0];
+    actix_rt::spawn(server.run());
+    addr
+}
+
+fn der(cert: &TestCert) -> CertificateDer<'static> {
+    CertificateDer::from(cert.cert.to_der().unwrap())
+}
+
+fn client_config(ca: &TestCert, client: Option<&TestCert>, tls12_only: bool) -> Arc<ClientConfig> {
+    let mut roots = RootCertStore::empty();
+    roots.add(der(ca)).unwrap();
+
+    let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()));
+    let builder = if tls12_only {
+        builder.with_protocol_versions(&[&TLS12]).unwrap()
+    } else {
+        builder.with_safe_default_protocol_versions().unwrap()
+    }
+    .with_root_certificates(roots);
+
+    let mut config = match client {
+        Some(client) => {
+            let key = PrivateKeyDer::try_from(client.key.private_key_to_pkcs8().unwrap()).unwrap();
+            builder
+                .with_client_auth_cert(vec![der(client)], key)
+                .unwrap()
+        }
+        None => builder.with_no_client_auth(),
+    };
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Arc::new(config)
+}
+
+// Connects and completes the TLS handshake only.
+async fn handshake(addr: SocketAddr, config: Arc<ClientConfig>) -> TlsStream {
+    web::block(move || {
+        let name = ServerName::try_from("localhost").unwrap();
+        let mut connection = ClientConnection::new(config, name).unwrap();
+        let mut socket = TcpStream::connect(addr).unwrap();
+        while connection.is_handshaking() {
+            connection.complete_io(&mut socket).unwrap();
+        }
+        StreamOwned::new(connection, socket)
+    })
+    .await
+    .unwrap()
+}
+
+// Connects, completes the handshake and performs one HTTP/1.1 request.
+async fn request(
+    addr: SocketAddr,
+    config: Arc<ClientConfig>,
+    path: &'static str,
+) -> Result<(TlsStream, String), String> {
+    web::block(move || {
+        let name = ServerName::try_from("localhost").unwrap();
+        let connection = ClientConnection::new(config, name).map_err(|e| e.to_string())?;
+        let mut stream = StreamOwned::new(connection, TcpStream::connect(addr).unwrap());
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .map_err(|e| e.to_string())?;
+
+        let mut response = Vec::new();
+        let mut buf = [0u8; 4096];
+        while !String::from_utf8_lossy(&response).contains("\r\n\r\n") {
+            let n = stream.read(&mut buf).map_err(|e| e.to_string())?;
+            if n == 0 {
+                break;
+            }
+            response.extend_from_slice(&buf[..n]);
+        }
+        Ok((stream, String::from_utf8_lossy(&response).into_owned()))
+    })
+    .await
+    .unwrap()
+}
+
+fn served_certificate(stream: &TlsStream) -> CertificateDer<'static> {
+    stream.conn.peer_certificates().unwrap()[0]
+        .clone()
+        .into_owned()
+}
+
+#[actix_rt::test]
+async fn test_serves_https_with_alpn() {
+    let ca = ca("PastePoint Test CA");
+    let dir = temp_dir("rustls-alpn");
+    write_pair(&dir, "server", &server_cert(&ca));
+    let certificates = RustlsCertificates::from_config(&config_for(&dir)).unwrap();
+    let addr = start_server(&certificates);
+
+    // HTTP/2 is preferred when offered.
+    let stream = handshake(addr, client_config(&ca, None, false)).await;
+    assert_eq!(stream.conn.alpn_protocol(), Some(&b"h2"[..]));
+
+    let mut config = (*client_config(&ca, None, false)).clone();
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    let (stream, response) = request(addr, Arc::new(config), "/health").await.unwrap();
+    assert_eq!(stream.conn.alpn_protocol(), Some(&b"http/1.1"[..]));
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+}
+
+#[actix_rt::test]
+async fn test_session_resumption() {
+    let ca = ca("PastePoint Test CA");
+    let dir = temp_dir("rustls-resumption");
+    write_pair(&dir, "server", &server_cert(&ca));
+    let certificates = RustlsCertificates::from_config(&config_for(&dir)).unwrap();
+    let addr = start_server(&certificates);
+
+    for tls12_only in [false, true] {
+        let mut config = (*client_config(&ca, None, tls12_only)).clone();
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        let config = Arc::new(config);
+
+        let (first, _) = request(addr, config.clone(), "/health").await.unwrap();
+        assert_eq!(first.conn.handshake_kind(), Some(HandshakeKind::Full));
+
+        let (second, response) = request(addr, config, "/health").await.unwrap();
+        assert_eq!(second.conn.handshake_kind(), Some(HandshakeKind::Resumed));
+        assert!(response.starts_with("HTTP/1.1 200"));
+    }
+}
+
+#[actix_rt::test]
+async fn test_reload_and_invalid_pair() {
+    let ca = ca("PastePoint Test CA");
+    let dir = temp_dir("rustls-reload");
+    let first = server_cert(&ca);
+    write_pair(&dir, "server", &first);
+
+    let certificates = RustlsCertificates::from_config(&config_for(&dir)).unwrap();
+    let addr = start_server(&certificates);
+    let reloader = ServerCertificates::from(certificates);
+
+    let second = server_cert(&ca);
+    write_pair(&dir, "server", &second);
+    reloader.reload().unwrap();
+    let stream = handshake(addr, client_config(&ca, None, false)).await;
+    assert_eq!(served_certificate(&stream), der(&second));
+
+    // A certificate whose key does not match keeps the current pair.
+    fs::write(
+        dir.join("server-cert.pem"),
+        server_cert(&ca).cert.to_pem().unwrap(),
+    )
+    .unwrap();
+    let err = reloader.reload().unwrap_err();
+    assert!(err.contains("does not match"), "{err}");
+    let stream = handshake(addr, client_config(&ca, None, false)).await;
+    assert_eq!(served_certificate(&stream), der(&second));
+
+    assert!(RustlsCertificates::from_config(&config_for(&temp_dir("rustls-empty"))).is_err());
+}
+
+#[actix_rt::test]
+async fn test_client_certificates() {
+    let ca = ca("PastePoint Test CA");
+    let dir = temp_dir("rustls-mtls");
+    write_pair(&dir, "server", &server_cert(&ca));
+    let ca_path = dir.join("client-ca.pem");
+    fs::write(&ca_path, ca.cert.to_pem().unwrap()).unwrap();
+
+    let mut config = config_for(&dir);
+    config.client_auth = ClientAuthConfig {
+        enabled: true,
+        ca_file: Some(ca_path.to_string_lossy().into_owned()),
+        ..ClientAuthConfig::default()
+    };
+    let certificates = RustlsCertificates::from_config(&config).unwrap();
+    let addr = start_server(&certificates);
+
+    let mut alice = (*client_config(&ca, Some(&client_cert(&ca, "alice")), false)).clone();
+    alice.alpn_protocols = vec![b"http/1.1".to_vec()];
+    let (_, response) = request(addr, Arc::new(alice), "/whoami").await.unwrap();
+    assert!(
+        response.ends_with("O=PastePoint Tests,CN=alice"),
+        "{response}"
+    );
+
+    let mut anonymous = (*client_config(&ca, None, false)).clone();
+    anonymous.alpn_protocols = vec![b"http/1.1".to_vec()];
+    let (_, response) = request(addr, Arc::new(anonymous), "/whoami").await.unwrap();
+    assert!(response.ends_with("anonymous"), "{response}");
+
+    let rogue = client_cert(&tls_support::ca("Rogue CA"), "mallory");
+    let mut mallory = (*client_config(&ca, Some(&rogue), false)).clone();
+    mallory.alpn_protocols = vec![b"http/1.1".to_vec()];
+    assert!(request(addr, Arc::new(mallory), "/whoami").await.is_err());
+}
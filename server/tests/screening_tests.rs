@@ -0,0 +1,225 @@
+use actix_web::{App, http::StatusCode, test, web};
+use config::{Config, File, FileFormat};
+use server::{
+    LimitsConfig, SCREENING_METRIC, ScreeningConfig, ScreeningDecision, ScreeningRule,
+    ServerConfig, SessionStore, admin_metrics, chat_ws, metrics,
+};
+
+fn parse_screening(toml: &str) -> Result<ScreeningConfig, config::ConfigError> {
+    Config::builder()
+        .add_source(File::from_str(toml, FileFormat::Toml))
+        .build()?
+        .try_deserialize()
+}
+
+fn request(headers: &[(&str, &str)]) -> actix_web::HttpRequest {
+    headers
+        .iter()
+        .fold(test::TestRequest::get().uri("/ws"), |req, &header| {
+            req.insert_header(header)
+        })
+        .to_http_request()
+}
+
+fn ws_request(peer: &str, user_agent: &str) -> actix_http::Request {
+    test::TestRequest::get()
+        .uri("/ws")
+        .insert_header(("Upgrade", "websocket"))
+        .insert_header(("Connection", "Upgrade"))
+        .insert_header(("Sec-WebSocket-Version", "13"))
+        .insert_header(("Sec-WebSocket-Key", "test_key"))
+        .insert_header(("User-Agent", user_agent))
+        .peer_addr(peer.parse().unwrap())
+        .to_request()
+}
+
+#[actix_rt::test]
+async fn test_user_agent_rules() {
+    let screening = parse_screening(
+        r#"
+        user_agent_allow = ["^PastePointBot/"]
+        user_agent_deny = ["(?i)crawler", "(?i)bot"]
+        min_user_agent_length = 8
+        "#,
+    )
+    .expect("valid screening config");
+    let ip = "203.0.113.1".parse().unwrap();
+
+    let denied = screening.screen(&request(&[("User-Agent", "SomeCrawler 1.0")]), ip, 0, 20);
+    assert!(matches!(
+        denied,
+        ScreeningDecision::Reject(ScreeningRule::UserAgentDeny, _)
+    ));
+
+    let short = screening.screen(&request(&[("User-Agent", "curl")]), ip, 0, 20);
+    assert!(matches!(
+        short,
+        ScreeningDecision::Reject(ScreeningRule::UserAgentLength, _)
+    ));
+
+    let allowed = screening.screen(&request(&[("User-Agent", "PastePointBot/2.0")]), ip, 0, 20);
+    assert_eq!(
+        allowed,
+        ScreeningDecision::Allow(ScreeningRule::UserAgentAllow)
+    );
+
+    let browser = screening.screen(&request(&[("User-Agent", "Mozilla/5.0 (X11)")]), ip, 0, 20);
+    assert_eq!(browser, ScreeningDecision::Allow(ScreeningRule::Default));
+
+    assert!(parse_screening(r#"user_agent_deny = ["(unclosed"]"#).is_err());
+}
+
+#[actix_rt::test]
+async fn test_default_rules_reject_bots() {
+    let screening = ScreeningConfig::default();
+    let ip = "203.0.113.2".parse().unwrap();
+
+    assert!(
+        !screening
+            .screen(&request(&[("User-Agent", "Googlebot/2.1")]), ip, 0, 20)
+            .is_allowed()
+    );
+    assert!(
+        !screening
+            .screen(&request(&[("User-Agent", "abc")]), ip, 0, 20)
+            .is_allowed()
+    );
+    assert!(screening.screen(&request(&[]), ip, 0, 20).is_allowed());
+}
+
+#[actix_rt::test]
+async fn test_required_headers_and_ip_concurrency() {
+    let screening = ScreeningConfig {
+        required_headers: vec!["Accept-Language".to_string()],
+        ..ScreeningConfig::default()
+    };
+    let ip = "203.0.113.3".parse().unwrap();
+
+    let missing = screening.screen(&request(&[("User-Agent", "Mozilla/5.0")]), ip, 0, 2);
+    assert!(matches!(
+        missing,
+        ScreeningDecision::Reject(ScreeningRule::RequiredHeader, _)
+    ));
+
+    let headers = [("User-Agent", "Mozilla/5.0"), ("Accept-Language", "en")];
+    assert!(screening.screen(&request(&headers), ip, 1, 2).is_allowed());
+    assert!(matches!(
+        screening.screen(&request(&headers), ip, 2, 2),
+        ScreeningDecision::Reject(ScreeningRule::IpConcurrency, _)
+    ));
+}
+
+#[actix_rt::test]
+async fn test_api_key_bypass() {
+    let screening = ScreeningConfig {
+        bypass_api_keys: vec!["s3cret-key".to_string()],
+        ..ScreeningConfig::default()
+    };
+    let ip = "203.0.113.4".parse().unwrap();
+
+    let bypassed = screening.screen(
+        &request(&[("User-Agent", "bot"), ("X-Api-Key", "s3cret-key")]),
+        ip,
+        5,
+        1,
+    );
+    assert_eq!(
+        bypassed,
+        ScreeningDecision::Allow(ScreeningRule::ApiKeyBypass)
+    );
+
+    let wrong_key = screening.screen(
+        &request(&[("User-Agent", "bot"), ("X-Api-Key", "wrong")]),
+        ip,
+        0,
+        1,
+    );
+    assert!(!wrong_key.is_allowed());
+}
+
+#[actix_rt::test]
+async fn test_per_ip_cap_on_websocket_route() {
+    let limits = LimitsConfig {
+        max_connections_per_ip: 2,
+        ..LimitsConfig::default()
+    };
+    let mut server_config = ServerConfig::load(Some(false)).expect("load config");
+    server_config.limits = limits;
+    server_config.screening.bypass_api_keys = vec!["s3cret-key".to_string()];
+    let session_manager = web::Data::new(SessionStore::new(limits));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(session_manager.clone())
+            .app_data(web::Data::new(server_config))
+            .service(chat_ws)
+            .service(admin_metrics),
+    )
+    .await;
+
+    let rejected_before = metrics().counter(
+        SCREENING_METRIC,
+        &[("rule", "ip_concurrency"), ("decision", "reject")],
+    );
+    let ip = "198.51.100.7".parse().unwrap();
+
+    // Keep the responses alive so the session actors stay connected.
+    let mut connections = Vec::new();
+    for _ in 0..2 {
+        let resp = test::call_service(&app, ws_request("198.51.100.7:1000", "Mozilla/5.0")).await;
+        assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+        connections.push(resp);
+    }
+    assert_eq!(session_manager.connections_for_ip(ip), 2);
+
+    // Over the cap: upgraded only to be closed with the reason, and not counted.
+    let resp = test::call_service(&app, ws_request("198.51.100.7:1000", "Mozilla/5.0")).await;
+    assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+    assert_eq!(session_manager.connections_for_ip(ip), 2);
+    assert!(
+        metrics().counter(
+            SCREENING_METRIC,
+            &[("rule", "ip_concurrency"), ("decision", "reject")],
+        ) > rejected_before
+    );
+
+    // A bypass key exempts trusted automation from the cap.
+    let req = test::TestRequest::get()
+        .uri("/ws")
+        .insert_header(("Upgrade", "websocket"))
+        .insert_header(("Connection", "Upgrade"))
+        .insert_header(("Sec-WebSocket-Version", "13"))
+        .insert_header(("Sec-WebSocket-Key", "test_key"))
+        .insert_header(("User-Agent", "bot"))
+        .insert_header(("X-Api-Key", "s3cret-key"))
+        .peer_addr("198.51.100.7:1000".parse().unwrap())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+    connections.push(resp);
+    assert_eq!(session_manager.connections_for_ip(ip), 3);
+
+    let resp = test::call_service(&app, ws_request("198.51.100.8:1000", "Mozilla/5.0")).await;
+    assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+    connections.push(resp);
+
+    let resp = test::call_service(&app, ws_request("198.51.100.9:1000", "Googlebot/2.1")).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get()
+        .uri("/admin/metrics")
+        .peer_addr("127.0.0.1:9000".parse().unwrap())
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.contains(&format!("# TYPE {SCREENING_METRIC} counter")));
+    assert!(body.contains("rule=\"ip_concurrency\",decision=\"reject\""));
+    assert!(body.contains("rule=\"user_agent_deny\",decision=\"reject\""));
+
+    let req = test::TestRequest::get()
+        .uri("/admin/metrics")
+        .peer_addr("198.51.100.7:9000".parse().unwrap())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
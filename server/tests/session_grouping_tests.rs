@@ -0,0 +1,124 @@
+use actix_web::{App, http::StatusCode, test, web};
+use server::{GroupingStrategy, ServerConfig, SessionGroupingConfig, SessionStore, chat_ws};
+
+fn grouping(strategy: GroupingStrategy) -> SessionGroupingConfig {
+    SessionGroupingConfig {
+        strategy,
+        ..SessionGroupingConfig::default()
+    }
+}
+
+#[actix_rt::test]
+async fn test_exact_grouping() {
+    let config = grouping(GroupingStrategy::Exact);
+    assert_eq!(
+        config.group_for("192.168.1.10".parse().unwrap()),
+        "192.168.1.10"
+    );
+    assert_ne!(
+        config.group_for("192.168.1.10".parse().unwrap()),
+        config.group_for("192.168.1.11".parse().unwrap())
+    );
+}
+
+#[actix_rt::test]
+async fn test_prefix_grouping_ipv4_and_ipv6() {
+    let config = grouping(GroupingStrategy::Prefix);
+    assert_eq!(
+        config.group_for("192.168.1.10".parse().unwrap()),
+        "192.168.1.0/24"
+    );
+    assert_eq!(
+        config.group_for("192.168.1.10".parse().unwrap()),
+        config.group_for("192.168.1.200".parse().unwrap())
+    );
+    assert_ne!(
+        config.group_for("192.168.1.10".parse().unwrap()),
+        config.group_for("192.168.2.10".parse().unwrap())
+    );
+
+    assert_eq!(
+        config.group_for("2001:db8:1:2:aaaa::1".parse().unwrap()),
+        config.group_for("2001:db8:1:2:bbbb::2".parse().unwrap())
+    );
+    assert_eq!(
+        config.group_for("2001:db8:1:2:aaaa::1".parse().unwrap()),
+        "2001:db8:1:2::/64"
+    );
+
+    // IPv4-mapped IPv6 addresses group with their IPv4 form
+    assert_eq!(
+        config.group_for("::ffff:192.168.1.10".parse().unwrap()),
+        "192.168.1.0/24"
+    );
+}
+
+#[actix_rt::test]
+async fn test_explicit_cidr_groups() {
+    let config: SessionGroupingConfig = serde_json::from_value(serde_json::json!({
+        "strategy": "exact",
+        "groups": [
+            { "cidr": "10.0.0.0/8", "name": "corp" },
+            { "cidr": "10.20.0.0/16", "name": "office" },
+            { "cidr": "2001:db8::/48", "name": "office" }
+        ]
+    }))
+    .unwrap();
+
+    assert_eq!(config.group_for("10.1.2.3".parse().unwrap()), "group:corp");
+    assert_eq!(
+        config.group_for("10.20.5.6".parse().unwrap()),
+        "group:office"
+    );
+    assert_eq!(
+        config.group_for("2001:db8:0:1::5".parse().unwrap()),
+        "group:office"
+    );
+    assert_eq!(
+        config.group_for("172.16.0.1".parse().unwrap()),
+        "172.16.0.1"
+    );
+}
+
+#[actix_rt::test]
+async fn test_clients_on_same_subnet_share_public_session() {
+    let session_manager = web::Data::new(SessionStore::default());
+    let mut config_value = ServerConfig::load(Some(false)).expect("load config");
+    config_value.session_grouping = grouping(GroupingStrategy::Prefix);
+    let config = web::Data::new(config_value);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(session_manager.clone())
+            .app_data(config.clone())
+            .service(chat_ws),
+    )
+    .await;
+
+    // Keep the responses alive so the sessions are not torn down mid-test
+    let mut responses = Vec::new();
+    for peer in [
+        "192.168.1.10:5000",
+        "192.168.1.20:5000",
+        "192.168.2.10:5000",
+    ] {
+        let req = test::TestRequest::get()
+            .uri("/ws")
+            .insert_header(("Host", "pastepoint.local"))
+            .insert_header(("Upgrade", "websocket"))
+            .insert_header(("Connection", "Upgrade"))
+            .insert_header(("Sec-WebSocket-Version", "13"))
+            .insert_header(("Sec-WebSocket-Key", "test_key"))
+            .insert_header(("User-Agent", "PastePoint Test Client"))
+            .peer_addr(peer.parse().unwrap())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+        responses.push(resp);
+    }
+
+    let map = session_manager.key_to_session.lock().unwrap();
+    assert_eq!(map.len(), 2);
+    assert!(map.contains_key("pastepoint.local:192.168.1.0/24"));
+    assert!(map.contains_key("pastepoint.local:192.168.2.0/24"));
+}
@@ -0,0 +1,169 @@
+#![cfg(feature = "openssl")]
+
+use actix_web::{App, HttpServer, web};
+use openssl::{
+    hash::MessageDigest,
+    ssl::{SslConnector, SslMethod, SslStream, SslVerifyMode},
+};
+use server::{ServerCertificates, ServerConfig, TlsCertificates, health};
+use std::{
+    fs,
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    path::Path,
+};
+use tokio::time::{Duration, sleep};
+
+mod tls_support;
+
+use tls_support::{TestCert, ca, server_cert, temp_dir, write_pair};
+
+fn config_for(dir: &Path) -> ServerConfig {
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.key_file_path = dir.join("server-key.pem").to_string_lossy().into_owned();
+    config.cert_file_path = dir.join("server-cert.pem").to_string_lossy().into_owned();
+    config
+}
+
+fn start_server(certificates: &TlsCertificates) -> SocketAddr {
+    let server = HttpServer::new(|| App::new().service(health))
+        .workers(1)
+        .bind_openssl("127.0.0.1:0", certificates.acceptor().unwrap())
+        .unwrap();
+    let addr = server.addrs()[0];
+    actix_rt::spawn(server.run());
+    addr
+}
+
+// Opens a TLS connection trusting `ca` on a blocking thread.
+async fn handshake(addr: SocketAddr, ca: &TestCert) -> SslStream<TcpStream> {
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector
+        .cert_store_mut()
+        .add_cert(ca.cert.clone())
+        .unwrap();
+    connector.set_verify(SslVerifyMode::PEER);
+    let connector = connector.build();
+
+    web::block(move || {
+        let stream = TcpStream::connect(addr).unwrap();
+        connector.connect("localhost", stream).unwrap()
+    })
+    .await
+    .unwrap()
+}
+
+async fn served_fingerprint(addr: SocketAddr, ca: &TestCert) -> Vec<u8> {
+    let stream = handshake(addr, ca).await;
+    fingerprint(&stream.ssl().peer_certificate().unwrap())
+}
+
+fn fingerprint(cert: &openssl::x509::X509Ref) -> Vec<u8> {
+    cert.digest(MessageDigest::sha256()).unwrap().to_vec()
+}
+
+// Sends a keep-alive health check over an existing connection.
+async fn health_check(stream: SslStream<TcpStream>) -> (SslStream<TcpStream>, String) {
+    web::block(move || {
+        let mut stream = stream;
+        stream
+            .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut buf = [0u8; 1024];
+        let n = stream.read(&mut buf).unwrap();
+        let response = String::from_utf8_lossy(&buf[..n]).into_owned();
+        (stream, response)
+    })
+    .await
+    .unwrap()
+}
+
+#[actix_rt::test]
+async fn test_reload_serves_new_certificate() {
+    let ca = ca("PastePoint Test CA");
+    let dir = temp_dir("tls-reload");
+    let first = server_cert(&ca);
+    write_pair(&dir, "server", &first);
+
+    let certificates = TlsCertificates::from_config(&config_for(&dir)).unwrap();
+    let addr = start_server(&certificates);
+    assert_eq!(
+        served_fingerprint(addr, &ca).await,
+        fingerprint(&first.cert)
+    );
+
+    // Connections accepted before the reload keep working.
+    let existing = handshake(addr, &ca).await;
+
+    let second = server_cert(&ca);
+    write_pair(&dir, "server", &second);
+    certificates.reload().unwrap();
+    assert_eq!(
+        served_fingerprint(addr, &ca).await,
+        fingerprint(&second.cert)
+    );
+
+    let (existing, response) = health_check(existing).await;
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert_eq!(
+        fingerprint(&existing.ssl().peer_certificate().unwrap()),
+        fingerprint(&first.cert)
+    );
+}
+
+#[actix_rt::test]
+async fn test_invalid_pair_keeps_previous_certificate() {
+    let ca = ca("PastePoint Test CA");
+    let dir = temp_dir("tls-invalid");
+    let first = server_cert(&ca);
+    write_pair(&dir, "server", &first);
+
+    let certificates = TlsCertificates::from_config(&config_for(&dir)).unwrap();
+    let addr = start_server(&certificates);
+
+    // A certificate whose key does not match.
+    let other = server_cert(&ca);
+    fs::write(dir.join("server-cert.pem"), other.cert.to_pem().unwrap()).unwrap();
+    let err = certificates.reload().unwrap_err();
+    assert!(err.contains("does not match"), "{err}");
+    assert_eq!(
+        served_fingerprint(addr, &ca).await,
+        fingerprint(&first.cert)
+    );
+
+    // A truncated certificate file.
+    fs::write(dir.join("server-cert.pem"), "-----BEGIN CERTIFICATE-----\n").unwrap();
+    assert!(certificates.reload().is_err());
+    assert_eq!(
+        served_fingerprint(addr, &ca).await,
+        fingerprint(&first.cert)
+    );
+}
+
+#[actix_rt::test]
+async fn test_missing_certificate_is_an_error() {
+    let dir = temp_dir("tls-missing");
+    let result = TlsCertificates::from_config(&config_for(&dir));
+    assert!(result.is_err());
+}
+
+#[actix_rt::test]
+async fn test_watch_picks_up_changed_files() {
+    let ca = ca("PastePoint Test CA");
+    let dir = temp_dir("tls-watch");
+    write_pair(&dir, "server", &server_cert(&ca));
+
+    let certificates = TlsCertificates::from_config(&config_for(&dir)).unwrap();
+    let addr = start_server(&certificates);
+    ServerCertificates::from(certificates).watch(Duration::from_millis(50));
+
+    sleep(Duration::from_millis(100)).await;
+    let renewed = server_cert(&ca);
+    write_pair(&dir, "server", &renewed);
+    sleep(Duration::from_millis(300)).await;
+
+    assert_eq!(
+        served_fingerprint(addr, &ca).await,
+        fingerprint(&renewed.cert)
+    );
+}
@@ -0,0 +1,139 @@
+#![allow(dead_code)]
+
+use openssl::{
+    asn1::Asn1Time,
+    bn::{BigNum, MsbOption},
+    hash::MessageDigest,
+    pkey::{PKey, Private},
+    rsa::Rsa,
+    ssl::{SslConnector, SslFiletype, SslMethod, SslVerifyMode},
+    x509::{
+        X509, X509NameBuilder,
+        extension::{BasicConstraints, KeyUsage, SubjectAlternativeName},
+    },
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A certificate and its private key.
+pub struct TestCert {
+    pub cert: X509,
+    pub key: PKey<Private>,
+}
+
+fn build(cn: &str, issuer: Option<&TestCert>, ca: bool, server: bool) -> TestCert {
+    let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_text("O", "PastePoint Tests").unwrap();
+    name.append_entry_by_text("CN", cn).unwrap();
+    let name = name.build();
+
+    let mut serial = BigNum::new().unwrap();
+    serial.rand(64, MsbOption::MAYBE_ZERO, false).unwrap();
+
+    let mut builder = X509::builder().unwrap();
+    builder.set_version(2).unwrap();
+    builder
+        .set_serial_number(&serial.to_asn1_integer().unwrap())
+        .unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder
+        .set_issuer_name(issuer.map_or(&name, |i| i.cert.subject_name()))
+        .unwrap();
+    builder.set_pubkey(&key).unwrap();
+    builder
+        .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    builder
+        .set_not_after(&Asn1Time::days_from_now(7).unwrap())
+        .unwrap();
+
+    if ca {
+        builder
+            .append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+            .unwrap();
+        builder
+            .append_extension(
+                KeyUsage::new()
+                    .critical()
+                    .key_cert_sign()
+                    .crl_sign()
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+    }
+    if server {
+        let san = SubjectAlternativeName::new()
+            .dns("localhost")
+            .ip("127.0.0.1")
+            .build(&builder.x509v3_context(issuer.map(|i| i.cert.as_ref()), None))
+            .unwrap();
+        builder.append_extension(san).unwrap();
+    }
+
+    let signing_key = issuer.map_or(&key, |i| &i.key);
+    builder.sign(signing_key, MessageDigest::sha256()).unwrap();
+    TestCert {
+        cert: builder.build(),
+        key,
+    }
+}
+
+pub fn ca(cn: &str) -> TestCert {
+    build(cn, None, true, false)
+}
+
+pub fn server_cert(ca: &TestCert) -> TestCert {
+    build("localhost", Some(ca), false, true)
+}
+
+pub fn client_cert(ca: &TestCert, cn: &str) -> TestCert {
+    build(cn, Some(ca), false, false)
+}
+
+/// A fresh directory under the system temp dir for PEM files.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pastepoint-{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes `cert.pem` and `key.pem` for `cert` into `dir`, returning their paths.
+pub fn write_pair(dir: &Path, prefix: &str, cert: &TestCert) -> (PathBuf, PathBuf) {
+    let cert_path = dir.join(format!("{prefix}-cert.pem"));
+    let key_path = dir.join(format!("{prefix}-key.pem"));
+    fs::write(&cert_path, cert.cert.to_pem().unwrap()).unwrap();
+    fs::write(&key_path, cert.key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+    (cert_path, key_path)
+}
+
+/// An OpenSSL-backed `awc` client trusting `ca`, optionally presenting `client`.
+pub fn https_client(ca: &TestCert, client: Option<&TestCert>) -> awc::Client {
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector
+        .cert_store_mut()
+        .add_cert(ca.cert.clone())
+        .unwrap();
+    connector.set_verify(SslVerifyMode::PEER);
+    if let Some(client) = client {
+        connector.set_certificate(&client.cert).unwrap();
+        connector.set_private_key(&client.key).unwrap();
+    }
+    connector.set_alpn_protos(b"\x08http/1.1").unwrap();
+
+    awc::Client::builder()
+        .connector(awc::Connector::new().openssl(connector.build()))
+        .finish()
+}
+
+/// Loads a server certificate pair from disk into an OpenSSL acceptor.
+pub fn acceptor(cert: &Path, key: &Path) -> openssl::ssl::SslAcceptorBuilder {
+    let mut builder = openssl::ssl::SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    builder.set_private_key_file(key, SslFiletype::PEM).unwrap();
+    builder.set_certificate_chain_file(cert).unwrap();
+    builder
+}
@@ -0,0 +1,185 @@
+use actix_web::{App, http::StatusCode, test, web};
+use server::{
+    ConfigSources, ORIGIN_REJECTION_METRIC, ServerConfig, SessionStore, UaPattern,
+    WebSocketOriginConfig, chat_ws, create_session, metrics, private_chat_ws,
+};
+
+fn ws_request(uri: &str, origin: Option<&str>, user_agent: &str) -> actix_http::Request {
+    let req = test::TestRequest::get()
+        .uri(uri)
+        .insert_header(("Upgrade", "websocket"))
+        .insert_header(("Connection", "Upgrade"))
+        .insert_header(("Sec-WebSocket-Version", "13"))
+        .insert_header(("Sec-WebSocket-Key", "test_key"))
+        .insert_header(("User-Agent", user_agent))
+        .peer_addr("192.0.2.10:4000".parse().unwrap());
+    match origin {
+        Some(origin) => req.insert_header(("Origin", origin)),
+        None => req,
+    }
+    .to_request()
+}
+
+fn strict_config() -> ServerConfig {
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.cors_allowed_origins = vec![
+        "https://pastepoint.com".parse().unwrap(),
+        "https://*.pastepoint.com".parse().unwrap(),
+    ];
+    config.websocket_origin = WebSocketOriginConfig {
+        enforce: true,
+        allow_missing: false,
+        native_user_agents: vec![UaPattern(
+            regex::Regex::new("^PastePoint-Desktop/").unwrap(),
+        )],
+    };
+    config
+}
+
+#[actix_rt::test]
+async fn test_ws_origin_enforced_on_public_route() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(SessionStore::default()))
+            .app_data(web::Data::new(strict_config()))
+            .service(chat_ws),
+    )
+    .await;
+
+    let rejected_before = metrics().counter(
+        ORIGIN_REJECTION_METRIC,
+        &[("route", "public"), ("reason", "mismatch")],
+    );
+
+    let mut connections = Vec::new();
+    let resp = test::call_service(
+        &app,
+        ws_request("/ws", Some("https://pastepoint.com"), "Mozilla/5.0"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+    connections.push(resp);
+
+    let resp = test::call_service(
+        &app,
+        ws_request("/ws", Some("https://evil.example"), "Mozilla/5.0"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    assert!(
+        metrics().counter(
+            ORIGIN_REJECTION_METRIC,
+            &[("route", "public"), ("reason", "mismatch")],
+        ) > rejected_before
+    );
+}
+
+#[actix_rt::test]
+async fn test_ws_missing_origin_native_allowance() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(SessionStore::default()))
+            .app_data(web::Data::new(strict_config()))
+            .service(chat_ws),
+    )
+    .await;
+
+    let mut connections = Vec::new();
+    let resp = test::call_service(&app, ws_request("/ws", None, "PastePoint-Desktop/1.4.0")).await;
+    assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+    connections.push(resp);
+
+    let resp = test::call_service(&app, ws_request("/ws", None, "Mozilla/5.0")).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    assert!(
+        metrics().counter(
+            ORIGIN_REJECTION_METRIC,
+            &[("route", "public"), ("reason", "missing")],
+        ) > 0
+    );
+}
+
+#[actix_rt::test]
+async fn test_ws_origin_enforced_on_private_route() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(SessionStore::default()))
+            .app_data(web::Data::new(strict_config()))
+            .service(create_session)
+            .service(private_chat_ws),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/create-session").to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let uri = format!("/ws/{}", body["code"].as_str().unwrap());
+
+    let resp = test::call_service(
+        &app,
+        ws_request(&uri, Some("https://pastepoint.com.evil.com"), "Mozilla/5.0"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = test::call_service(
+        &app,
+        ws_request(&uri, Some("https://app.pastepoint.com"), "Mozilla/5.0"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+}
+
+#[actix_rt::test]
+async fn test_ws_origin_enforcement_disabled() {
+    let mut config = strict_config();
+    config.websocket_origin.enforce = false;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(SessionStore::default()))
+            .app_data(web::Data::new(config))
+            .service(chat_ws),
+    )
+    .await;
+
+    let resp = test::call_service(
+        &app,
+        ws_request("/ws", Some("https://evil.example"), "Mozilla/5.0"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+}
+
+#[actix_rt::test]
+async fn test_production_policy_admits_ios_client() {
+    let production = ServerConfig::load_from(&ConfigSources {
+        file: Some("config/production.toml".to_string()),
+        ..ConfigSources::default()
+    })
+    .expect("load production config");
+    let mut config = strict_config();
+    config.websocket_origin = production.websocket_origin;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(SessionStore::default()))
+            .app_data(web::Data::new(config))
+            .service(chat_ws),
+    )
+    .await;
+
+    // What the iOS app sends: no Origin and `Bundle.userAgent`.
+    let resp = test::call_service(&app, ws_request("/ws", None, "PastePoint-iOS/1.2.0")).await;
+    assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+
+    // URLSession's default User-Agent, which older builds sent.
+    let resp = test::call_service(
+        &app,
+        ws_request(
+            "/ws",
+            None,
+            "PastePoint/42 CFNetwork/1494.0.7 Darwin/23.4.0",
+        ),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
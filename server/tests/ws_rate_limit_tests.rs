@@ -0,0 +1,240 @@
+use actix_test::{TestServer, start};
+use actix_web::{App, web};
+use awc::{
+    Client,
+    error::WsProtocolError,
+    ws::{CloseCode, Frame, Message},
+};
+use futures_util::{SinkExt, Stream, StreamExt};
+use server::{
+    ConfigSources, LimitsConfig, MessageKind, MessageQuota, ServerConfig, SessionRateLimiter,
+    SessionStore, Throttle, WS_PREFIX_SYSTEM_WARNING, chat_ws,
+};
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+
+fn limits(commands: MessageQuota, signals: MessageQuota, max_throttled: u32) -> LimitsConfig {
+    LimitsConfig {
+        commands,
+        signals,
+        max_throttled_messages: max_throttled,
+        ..LimitsConfig::default()
+    }
+}
+
+fn start_server(limits: LimitsConfig) -> TestServer {
+    let mut config = ServerConfig::load(Some(false)).expect("load config");
+    config.limits = limits;
+    let config = web::Data::new(config);
+    let store = web::Data::new(SessionStore::new(limits));
+
+    start(move || {
+        App::new()
+            .app_data(store.clone())
+            .app_data(config.clone())
+            .service(chat_ws)
+    })
+}
+
+// Collects text frames until nothing arrives for half a second.
+async fn texts<S>(framed: &mut S) -> Vec<String>
+where
+    S: Stream<Item = Result<Frame, WsProtocolError>> + Unpin,
+{
+    let mut texts = Vec::new();
+    while let Ok(Some(Ok(frame))) = timeout(Duration::from_millis(500), framed.next()).await {
+        if let Frame::Text(text) = frame {
+            texts.push(String::from_utf8_lossy(&text).into_owned());
+        }
+    }
+    texts
+}
+
+#[test]
+fn test_token_buckets_refill_per_kind() {
+    let mut limiter = SessionRateLimiter::new(&limits(
+        MessageQuota::new(1, 2, 1024, 1024),
+        MessageQuota::new(10, 10, 100, 200),
+        100,
+    ));
+    let start = Instant::now();
+
+    for _ in 0..2 {
+        assert_eq!(
+            limiter.check_at(MessageKind::Command, 10, start),
+            Throttle::Allowed
+        );
+    }
+    assert_eq!(
+        limiter.check_at(MessageKind::Command, 10, start),
+        Throttle::Throttled {
+            retry_after: Duration::from_secs(1),
+            first: true
+        }
+    );
+    assert!(matches!(
+        limiter.check_at(MessageKind::Command, 10, start),
+        Throttle::Throttled { first: false, .. }
+    ));
+
+    // Signals have their own budget, limited here by bytes rather than count.
+    assert_eq!(
+        limiter.check_at(MessageKind::Signal, 150, start),
+        Throttle::Allowed
+    );
+    assert_eq!(
+        limiter.check_at(MessageKind::Signal, 100, start),
+        Throttle::Throttled {
+            retry_after: Duration::from_millis(500),
+            first: true
+        }
+    );
+    assert_eq!(
+        limiter.check_at(MessageKind::Signal, 50, start),
+        Throttle::Allowed
+    );
+
+    let later = start + Duration::from_secs(1);
+    assert_eq!(
+        limiter.check_at(MessageKind::Command, 10, later),
+        Throttle::Allowed
+    );
+    assert_eq!(
+        limiter.check_at(MessageKind::Signal, 100, later),
+        Throttle::Allowed
+    );
+}
+
+#[test]
+fn test_sustained_abuse_disconnects() {
+    let mut limiter = SessionRateLimiter::new(&LimitsConfig {
+        commands: MessageQuota::new(1, 1, 1024, 1024),
+        max_throttled_messages: 3,
+        abuse_window_secs: 10,
+        ..LimitsConfig::default()
+    });
+    let start = Instant::now();
+
+    assert_eq!(
+        limiter.check_at(MessageKind::Command, 1, start),
+        Throttle::Allowed
+    );
+    for _ in 0..3 {
+        assert!(matches!(
+            limiter.check_at(MessageKind::Command, 1, start),
+            Throttle::Throttled { .. }
+        ));
+    }
+    // A fresh window forgives earlier drops.
+    let later = start + Duration::from_secs(11);
+    assert!(matches!(
+        limiter.check_at(MessageKind::Binary, 10_000_000, later),
+        Throttle::Throttled { .. }
+    ));
+    for _ in 0..2 {
+        assert!(matches!(
+            limiter.check_at(MessageKind::Binary, 10_000_000, later),
+            Throttle::Throttled { .. }
+        ));
+    }
+    assert_eq!(
+        limiter.check_at(MessageKind::Binary, 10_000_000, later),
+        Throttle::Disconnect
+    );
+}
+
+#[test]
+fn test_quota_validation() {
+    let err = ServerConfig::load_from(&ConfigSources {
+        overrides: vec![
+            (
+                "limits.signals".to_string(),
+                r#"{"messages_per_sec": 0, "message_burst": 10, "bytes_per_sec": 1024, "byte_burst": 1024}"#
+                    .to_string(),
+            ),
+            ("limits.abuse_window_secs".to_string(), "0".to_string()),
+        ],
+        ..ConfigSources::default()
+    })
+    .unwrap_err()
+    .to_string();
+    for expected in [
+        "limits.signals.messages_per_sec must be greater than 0",
+        "limits.signals.byte_burst must be at least the largest allowed message (131072 bytes)",
+        "limits.abuse_window_secs must be greater than 0",
+    ] {
+        assert!(err.contains(expected), "missing '{expected}' in: {err}");
+    }
+}
+
+#[actix_rt::test]
+async fn test_throttled_client_is_warned() {
+    let srv = start_server(limits(
+        MessageQuota::new(1, 2, 1024, 65536),
+        MessageQuota::new(1, 5, 1024, 131072),
+        100,
+    ));
+    let (_resp, mut framed) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+
+    for _ in 0..5 {
+        framed
+            .send(Message::Text("[UserCommand]/name".into()))
+            .await
+            .unwrap();
+    }
+    let received = texts(&mut framed).await;
+    let names = received
+        .iter()
+        .filter(|t| t.starts_with("[SystemName]"))
+        .count();
+    let warnings: Vec<_> = received
+        .iter()
+        .filter(|t| t.starts_with(WS_PREFIX_SYSTEM_WARNING))
+        .collect();
+    assert_eq!(names, 2);
+    assert_eq!(warnings.len(), 1, "{received:?}");
+    assert!(warnings[0].contains("Rate limit exceeded for commands"));
+
+    // Signals are not held back by the exhausted command budget.
+    framed
+        .send(Message::Text("[SignalMessage] {\"to\":\"nobody\"}".into()))
+        .await
+        .unwrap();
+    let received = texts(&mut framed).await;
+    assert!(
+        !received
+            .iter()
+            .any(|t| t.starts_with(WS_PREFIX_SYSTEM_WARNING)),
+        "{received:?}"
+    );
+}
+
+#[actix_rt::test]
+async fn test_abusive_client_is_disconnected() {
+    let srv = start_server(LimitsConfig {
+        binary: MessageQuota::new(1, 1, 65536, 65536),
+        max_throttled_messages: 3,
+        ..LimitsConfig::default()
+    });
+    let (_resp, mut framed) = Client::new().ws(srv.url("/ws")).connect().await.unwrap();
+
+    for _ in 0..5 {
+        framed
+            .send(Message::Binary(vec![0u8; 16].into()))
+            .await
+            .unwrap();
+    }
+    let close = timeout(Duration::from_secs(5), async {
+        while let Some(Ok(frame)) = framed.next().await {
+            if let Frame::Close(reason) = frame {
+                return reason;
+            }
+        }
+        None
+    })
+    .await
+    .expect("abusive client should be disconnected")
+    .expect("close frame should carry a reason");
+    assert_eq!(close.code, CloseCode::Policy);
+    assert_eq!(close.description.as_deref(), Some("Rate limit exceeded"));
+}